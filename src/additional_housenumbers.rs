@@ -0,0 +1,87 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Compares OSM house numbers with reference ones and shows the ones which are only in OSM.
+
+use crate::areas;
+use crate::context;
+use crate::util;
+use std::io::Write;
+
+/// Formats one additional house number, optionally with its OSM object, e.g. '42 (node/123)'.
+fn format_house_number(house_number: &util::HouseNumber, with_ids: bool) -> String {
+    let number = house_number.get_number().to_string();
+    if !with_ids {
+        return number;
+    }
+
+    match (house_number.get_object_type(), house_number.get_id()) {
+        (Some(object_type), Some(id)) => format!("{number} ({object_type}/{id})"),
+        _ => number,
+    }
+}
+
+/// Inner main() that is allowed to fail.
+pub fn our_main(
+    argv: &[String],
+    stream: &mut dyn Write,
+    ctx: &context::Context,
+) -> anyhow::Result<()> {
+    let relation = clap::Arg::new("relation")
+        .required(true)
+        .help("name of the relation");
+    let with_ids = clap::Arg::new("with-ids")
+        .long("with-ids")
+        .action(clap::ArgAction::SetTrue)
+        .help("append the OSM object of each house number, e.g. '42 (node/123)'");
+    let args = [relation, with_ids];
+    let app = clap::Command::new("osm-gimmisn")
+        .override_usage("osm-gimmisn additional-housenumbers [--with-ids] <relation>");
+    let args = app.args(&args).try_get_matches_from(argv)?;
+    let relation_name: &String = args.get_one("relation").unwrap();
+    let with_ids = *args.get_one::<bool>("with-ids").unwrap();
+
+    let mut relations = areas::Relations::new(ctx)?;
+    let mut relation = relations.get_relation(relation_name)?;
+    for result in relation.get_additional_housenumbers()? {
+        let house_numbers: Vec<String> = result
+            .house_numbers
+            .iter()
+            .map(|i| format_house_number(i, with_ids))
+            .collect();
+        // Street name, # of only_in_osm items.
+        stream.write_all(
+            format!(
+                "{}\t{}\n",
+                result.street.get_osm_name(),
+                house_numbers.len()
+            )
+            .as_bytes(),
+        )?;
+        // only_in_osm items.
+        stream.write_all(format!("{house_numbers:?}\n").as_bytes())?;
+    }
+
+    ctx.get_unit().make_error()
+}
+
+/// Similar to plain main(), but with an interface that allows testing.
+pub fn main(argv: &[String], stream: &mut dyn Write, ctx: &context::Context) -> i32 {
+    match our_main(argv, stream, ctx) {
+        Ok(_) => 0,
+        Err(err) => {
+            stream.write_all(format!("{err:?}\n").as_bytes()).unwrap();
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,98 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Tests for the additional_housenumbers module.
+
+use super::*;
+use std::io::Read;
+use std::io::Seek;
+
+/// Creates a context where 'gh195' has '1' and '3' in OSM, but only '1' in the reference.
+fn make_test_context() -> context::Context {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '1', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', '', '', 'way');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gh195', '11', 'Kalotaszeg utca', '1', '', '', '', '', '', '', '', '', '', 'node');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gh195', '12', 'Kalotaszeg utca', '3', '', '', '', '', '', '', '', '', '', 'way');",
+        )
+        .unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+    ctx
+}
+
+/// Tests main().
+#[test]
+fn test_main() {
+    let argv = vec!["".to_string(), "gh195".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let ctx = make_test_context();
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t1\n[\"3\"]\n"
+    );
+}
+
+/// Tests main(): --with-ids appends the OSM object of each house number.
+#[test]
+fn test_main_with_ids() {
+    let argv = vec![
+        "".to_string(),
+        "--with-ids".to_string(),
+        "gh195".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let ctx = make_test_context();
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t1\n[\"3 (way/12)\"]\n"
+    );
+}
@@ -15,6 +15,8 @@ use crate::stats;
 use crate::util;
 use anyhow::Context;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::io::Read;
 use std::io::Write;
 use std::rc::Rc;
@@ -57,10 +59,32 @@ struct OverpassTags {
     unit: Option<String>,
     #[serde(rename(deserialize = "addr:city"))]
     city: Option<String>,
+    #[serde(rename(deserialize = "addr:full"))]
+    full: Option<String>,
     // endregion housenumbers
     fixme: Option<String>,
 }
 
+impl OverpassTags {
+    /// Gets the street and the house number. If addr:street is missing, but addr:full is present,
+    /// then the street (and possibly the house number) is taken from addr:full instead.
+    fn get_street_and_number(&self) -> (String, String) {
+        let street = self.street.clone().unwrap_or_default();
+        let housenumber = self.housenumber.clone().unwrap_or_default();
+        let full = match self.full {
+            Some(ref value) if street.is_empty() => value,
+            _ => return (street, housenumber),
+        };
+
+        let (full_street, full_housenumber) = util::split_street_and_number(full);
+        if housenumber.is_empty() {
+            (full_street, full_housenumber)
+        } else {
+            (full_street, housenumber)
+        }
+    }
+}
+
 /// OverpassElement represents one result from Overpass.
 #[derive(serde::Deserialize)]
 struct OverpassElement {
@@ -132,6 +156,11 @@ impl RelationFiles {
         )
     }
 
+    /// Builds the file name of the house number coverage history of a relation.
+    pub fn get_housenumbers_percent_log_path(&self) -> String {
+        format!("{}/{}.percent.log", self.workdir, self.name)
+    }
+
     /// Builds the file name of the additional house number json cache file of a relation.
     pub fn get_additional_housenumbers_jsoncache_path(&self) -> String {
         format!("{}/additional-cache-{}.json", self.workdir, self.name)
@@ -161,7 +190,7 @@ impl RelationFiles {
         let mut ret: Vec<util::OsmHouseNumber> = Vec::new();
         let conn = ctx.get_database_connection()?;
         let mut stmt =
-            conn.prepare("select osm_id, housenumber, conscriptionnumber, street, place, osm_type from osm_housenumbers where relation = ?1")?;
+            conn.prepare("select osm_id, housenumber, conscriptionnumber, street, place, osm_type, user from osm_housenumbers where relation = ?1")?;
         let mut rows = stmt.query([&self.name])?;
         while let Some(row) = rows.next()? {
             let id: String = row.get(0).unwrap();
@@ -170,6 +199,7 @@ impl RelationFiles {
             let street: String = row.get(3).unwrap();
             let place: String = row.get(4).unwrap();
             let object_type: String = row.get(5).unwrap();
+            let user: String = row.get(6).unwrap();
             ret.push(util::OsmHouseNumber::new(
                 id.parse()?,
                 &housenumber,
@@ -177,11 +207,32 @@ impl RelationFiles {
                 &street,
                 &Some(place),
                 &object_type,
+                &user,
             ));
         }
         Ok(ret)
     }
 
+    /// Gets the most common postcode of the OSM house numbers for each street of a relation.
+    pub fn get_osm_street_postcodes(
+        &self,
+        ctx: &context::Context,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let conn = ctx.get_database_connection()?;
+        let mut stmt = conn.prepare(
+            "select street, postcode, count(*) as count from osm_housenumbers where relation = ?1 and street != '' and postcode != '' group by street, postcode order by street, count desc, postcode",
+        )?;
+        let mut rows = stmt.query([&self.name])?;
+        let mut ret: HashMap<String, String> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let street: String = row.get(0).unwrap();
+            let postcode: String = row.get(1).unwrap();
+            // The first row is the most common postcode of the street.
+            ret.entry(street).or_insert(postcode);
+        }
+        Ok(ret)
+    }
+
     /// Opens the reference house number list of a relation for reading.
     pub fn get_ref_housenumbers_read_stream(
         &self,
@@ -301,9 +352,10 @@ impl RelationFiles {
             let unit = element.tags.unit.unwrap_or("".into());
             let name = element.tags.name.unwrap_or("".into());
             let osm_type = element.osm_type.to_string();
+            let user = element.user.unwrap_or("".into());
             let ret = tx.execute(
-                "insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
-                [relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type],
+                "insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type, user) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                [relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type, user],
             );
             if ret.is_err() {
                 info!("write_osm_json_housenumbers: ignoring duplicated housenumber: relation is '{}', id is '{}'", self.name, element.id);
@@ -345,10 +397,9 @@ pub fn write_whole_country(ctx: &context::Context, result: &str) -> anyhow::Resu
     let tx = conn.transaction()?;
     tx.execute("delete from whole_country", [])?;
     for element in overpass.elements {
+        let (street, housenumber) = element.tags.get_street_and_number();
         let postcode = element.tags.postcode.unwrap_or("".into());
         let city = element.tags.city.unwrap_or("".into());
-        let street = element.tags.street.unwrap_or("".into());
-        let housenumber = element.tags.housenumber.unwrap_or("".into());
         let user = element.user.unwrap_or("".into());
         let osm_id = element.id.to_string();
         let osm_type = element.osm_type.to_string();
@@ -381,5 +432,26 @@ pub fn write_whole_country(ctx: &context::Context, result: &str) -> anyhow::Resu
     Ok(())
 }
 
+/// Counts the unique house numbers in an overpass result, the same way as the whole_country table
+/// is counted.
+pub fn count_housenumbers(result: &str) -> anyhow::Result<usize> {
+    let overpass: OverpassResult =
+        serde_json::from_str(result).context("failed to parse overpass result as json")?;
+    let mut house_numbers: HashSet<String> = HashSet::new();
+    for element in overpass.elements {
+        let (street, housenumber) = element.tags.get_street_and_number();
+        house_numbers.insert(
+            [
+                element.tags.postcode.unwrap_or("".into()),
+                element.tags.city.unwrap_or("".into()),
+                street,
+                housenumber,
+            ]
+            .join("\t"),
+        );
+    }
+    Ok(house_numbers.len())
+}
+
 #[cfg(test)]
 mod tests;
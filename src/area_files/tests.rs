@@ -85,3 +85,39 @@ fn test_write_whole_country_non_json_input() {
 
     assert!(ret.is_ok());
 }
+
+/// Tests write_whole_country(), when addr:street is missing, but addr:full is present.
+#[test]
+fn test_write_whole_country_addr_full() {
+    let ctx = context::tests::make_test_context().unwrap();
+    let result = serde_json::json!({
+        "osm3s": {
+            "timestamp_osm_base": "2020-05-10T07:01:10Z",
+            "timestamp_areas_base": "2020-05-10T07:01:10Z",
+        },
+        "elements": [
+            {
+                "type": "node",
+                "id": 1,
+                "tags": {
+                    "addr:postcode": "1111",
+                    "addr:city": "Budapest",
+                    "addr:full": "Fő utca 12/a",
+                },
+            },
+        ],
+    })
+    .to_string();
+
+    write_whole_country(&ctx, &result).unwrap();
+
+    let conn = ctx.get_database_connection().unwrap();
+    let (street, housenumber): (String, String) = conn
+        .query_row("select street, housenumber from whole_country", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(street, "Fő utca");
+    assert_eq!(housenumber, "12/a");
+    assert_eq!(count_housenumbers(&result).unwrap(), 1);
+}
@@ -13,6 +13,7 @@
 use crate::area_files;
 use crate::cache;
 use crate::context;
+use crate::error::OsmGimmisnError;
 use crate::i18n::translate as tr;
 use crate::ranges;
 use crate::stats;
@@ -20,6 +21,7 @@ use crate::util;
 use crate::yattag;
 use anyhow::Context;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Write as _;
 use std::io::BufRead;
@@ -28,16 +30,16 @@ use std::ops::DerefMut;
 use std::rc::Rc;
 
 /// The filters -> <street> -> ranges key from data/relation-<name>.yaml.
-#[derive(Clone, serde::Deserialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct RelationRangesDict {
     pub end: String,
-    refsettlement: Option<String>,
+    pub refsettlement: Option<String>,
     pub start: String,
 }
 
 /// The filters key from data/relation-<name>.yaml.
-#[derive(Clone, serde::Deserialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct RelationFiltersDict {
@@ -61,23 +63,55 @@ impl RelationFiltersDict {
     }
 }
 
+/// The osmrelation key: a single OSM relation ID, or a list of them for areas which are modeled as
+/// several relations.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum OsmRelation {
+    /// One relation.
+    Single(u64),
+    /// Multiple relations, the area is their union.
+    Multiple(Vec<u64>),
+}
+
+impl OsmRelation {
+    /// Gets the relation IDs.
+    pub fn get_ids(&self) -> Vec<u64> {
+        match self {
+            OsmRelation::Single(id) => vec![*id],
+            OsmRelation::Multiple(ids) => ids.clone(),
+        }
+    }
+}
+
 /// A relation from data/relation-<name>.yaml.
-#[derive(Clone, serde::Deserialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct RelationDict {
     additional_housenumbers: Option<bool>,
     pub alias: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub display_name: Option<String>,
+    done: Option<bool>,
+    pub excluded_users: Option<Vec<String>>,
     pub filters: Option<HashMap<String, RelationFiltersDict>>,
     housenumber_letters: Option<bool>,
     inactive: Option<bool>,
+    pub interpolation: Option<String>,
+    min_missing: Option<usize>,
     missing_streets: Option<String>,
+    pub osm_housenumbers_query: Option<String>,
     osm_street_filters: Option<Vec<String>>,
-    pub osmrelation: Option<u64>,
+    pub osm_streets_query: Option<String>,
+    pub osmrelation: Option<OsmRelation>,
+    pub osm_snapshot: Option<String>,
+    pub reference_housenumbers: Option<serde_yaml::Value>,
     pub refcounty: Option<String>,
     pub refsettlement: Option<String>,
     pub refstreets: Option<HashMap<String, String>>,
     pub street_filters: Option<Vec<String>>,
+    pub street_match: Option<String>,
     pub source: Option<String>,
 }
 
@@ -85,30 +119,52 @@ impl Default for RelationDict {
     fn default() -> Self {
         let additional_housenumbers = None;
         let alias = None;
+        let description = None;
+        let display_name = None;
+        let done = None;
+        let excluded_users = None;
         let filters = None;
         let housenumber_letters = None;
         let inactive = None;
+        let interpolation = None;
+        let min_missing = None;
         let missing_streets = None;
+        let osm_housenumbers_query = None;
         let osm_street_filters = None;
+        let osm_streets_query = None;
         let osmrelation = None;
+        let osm_snapshot = None;
+        let reference_housenumbers = None;
         let refcounty = None;
         let refsettlement = None;
         let refstreets = None;
         let street_filters = None;
+        let street_match = None;
         let source = None;
         RelationDict {
             additional_housenumbers,
             alias,
+            description,
+            display_name,
+            done,
+            excluded_users,
             filters,
             housenumber_letters,
             inactive,
+            interpolation,
+            min_missing,
             missing_streets,
+            osm_housenumbers_query,
             osm_street_filters,
+            osm_streets_query,
             osmrelation,
+            osm_snapshot,
+            reference_housenumbers,
             refcounty,
             refsettlement,
             refstreets,
             street_filters,
+            street_match,
             source,
         }
     }
@@ -155,9 +211,42 @@ impl RelationConfig {
         }
     }
 
-    /// Gets the OSM relation object's ID.
+    /// Gets if the relation is marked as done, i.e. its OSM data is not updated nightly.
+    pub fn is_done(&self) -> bool {
+        RelationConfig::get_property(&self.parent.done, &self.dict.done).unwrap_or(false)
+    }
+
+    /// Gets the minimal number of missing house numbers of a street to list it, 0 by default.
+    pub fn get_min_missing(&self) -> usize {
+        RelationConfig::get_property(&self.parent.min_missing, &self.dict.min_missing).unwrap_or(0)
+    }
+
+    /// Gets the OSM relation object's ID, the first one in case there are multiple.
     pub fn get_osmrelation(&self) -> u64 {
-        self.parent.osmrelation.unwrap()
+        self.get_osmrelations()[0]
+    }
+
+    /// Gets the IDs of all OSM relation objects of the area.
+    pub fn get_osmrelations(&self) -> Vec<u64> {
+        self.parent.osmrelation.as_ref().unwrap().get_ids()
+    }
+
+    /// Gets the date (YYYY-MM-DD) the OSM data of the relation is pinned to, if any.
+    pub fn get_osm_snapshot(&self) -> Option<String> {
+        RelationConfig::get_property(&self.parent.osm_snapshot, &self.dict.osm_snapshot)
+    }
+
+    /// Gets the query template which replaces the generated street query, relative to data/.
+    pub fn get_osm_streets_query_template(&self) -> Option<String> {
+        RelationConfig::get_property(&self.parent.osm_streets_query, &self.dict.osm_streets_query)
+    }
+
+    /// Gets the query template which replaces the generated house number query, relative to data/.
+    pub fn get_osm_housenumbers_query_template(&self) -> Option<String> {
+        RelationConfig::get_property(
+            &self.parent.osm_housenumbers_query,
+            &self.dict.osm_housenumbers_query,
+        )
     }
 
     /// Gets the relation's refcounty identifier from reference.
@@ -173,11 +262,32 @@ impl RelationConfig {
         RelationConfig::get_property(&self.parent.refsettlement, &self.dict.refsettlement).unwrap()
     }
 
+    /// Determines if the relation has a refsettlement identifier.
+    pub fn has_refsettlement(&self) -> bool {
+        RelationConfig::get_property(&self.parent.refsettlement, &self.dict.refsettlement).is_some()
+    }
+
     /// Gets the alias(es) of the relation: alternative names which are also accepted.
     fn get_alias(&self) -> Vec<String> {
         RelationConfig::get_property(&self.parent.alias, &self.dict.alias).unwrap_or_default()
     }
 
+    /// Gets the free-form note of the maintainers about the relation, if any.
+    pub fn get_description(&self) -> Option<String> {
+        RelationConfig::get_property(&self.parent.description, &self.dict.description)
+    }
+
+    /// Gets the name of the relation to be shown to users, if it differs from the slug.
+    pub fn get_display_name(&self) -> Option<String> {
+        RelationConfig::get_property(&self.parent.display_name, &self.dict.display_name)
+    }
+
+    /// Gets the OSM users whose edits are ignored when looking at OSM house numbers.
+    fn get_excluded_users(&self) -> Vec<String> {
+        RelationConfig::get_property(&self.parent.excluded_users, &self.dict.excluded_users)
+            .unwrap_or_default()
+    }
+
     /// Return value can be 'yes', 'no' and 'only'.
     pub fn should_check_missing_streets(&self) -> String {
         match RelationConfig::get_property(&self.parent.missing_streets, &self.dict.missing_streets)
@@ -187,6 +297,12 @@ impl RelationConfig {
         }
     }
 
+    /// Return value can be 'exact' and 'casefold'.
+    fn get_street_match(&self) -> String {
+        RelationConfig::get_property(&self.parent.street_match, &self.dict.street_match)
+            .unwrap_or_else(|| "exact".into())
+    }
+
     /// Do we care if 42/B is missing when 42/A is provided?
     fn should_check_housenumber_letters(&self) -> bool {
         RelationConfig::get_property(
@@ -231,17 +347,27 @@ impl RelationConfig {
         filters.get(street)
     }
 
+    /// Gets the default interpolation of the relation's ranges.
+    fn get_interpolation(&self) -> String {
+        RelationConfig::get_property(&self.parent.interpolation, &self.dict.interpolation)
+            .unwrap_or_default()
+    }
+
+    /// Gets the interpolation of a street filter, falling back to the relation-level default.
+    fn get_filter_interpolation(&self, filter: &RelationFiltersDict) -> String {
+        match filter.interpolation {
+            Some(ref value) => value.to_string(),
+            None => self.get_interpolation(),
+        }
+    }
+
     /// Determines in a relation's street is interpolation=all or not.
     pub fn get_street_is_even_odd(&self, street: &str) -> bool {
-        let mut interpolation_all = false;
-        if let Some(filter_for_street) = self.get_filter_street(street) {
-            if let Some(ref interpolation) = filter_for_street.interpolation {
-                if interpolation == "all" {
-                    interpolation_all = true;
-                }
-            }
-        }
-        !interpolation_all
+        let interpolation = match self.get_filter_street(street) {
+            Some(filter_for_street) => self.get_filter_interpolation(filter_for_street),
+            None => self.get_interpolation(),
+        };
+        interpolation != "all"
     }
 
     /// Decides is a ref street should be shown for an OSM street.
@@ -288,6 +414,25 @@ impl RelationConfig {
         ret
     }
 
+    /// Gets the relation-specific reference house number paths, relative to the repo root. Empty
+    /// means the global reference is used.
+    fn get_reference_housenumbers(&self) -> Vec<String> {
+        match RelationConfig::get_property(
+            &self.parent.reference_housenumbers,
+            &self.dict.reference_housenumbers,
+        ) {
+            Some(serde_yaml::Value::String(value)) => {
+                value.split(' ').map(|i| i.to_string()).collect()
+            }
+            Some(serde_yaml::Value::Sequence(values)) => values
+                .iter()
+                .filter_map(|i| i.as_str())
+                .map(|i| i.to_string())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Gets list of streets which are only in reference, but have to be filtered out.
     fn get_street_filters(&self) -> Vec<String> {
         RelationConfig::get_property(&self.parent.street_filters, &self.dict.street_filters)
@@ -435,6 +580,8 @@ pub struct Relation<'a> {
     config: RelationConfig,
     osm_housenumbers: HashMap<String, Vec<util::HouseNumber>>,
     lints: Vec<RelationLint>,
+    /// Reference street name - house number pairs which can't be interpreted.
+    unparseable_refs: Vec<(String, String)>,
 }
 
 impl<'a> Relation<'a> {
@@ -453,11 +600,19 @@ impl<'a> Relation<'a> {
             my_config = serde_json::from_value(value.clone())
                 .context(format!("failed to parse '{relation_path}'"))?;
         }
-        let config = RelationConfig::new(parent_config, &my_config);
+        let mut parent_config = parent_config.clone();
+        if parent_config.interpolation.is_none() {
+            let interpolation = ctx.get_ini().get_default_interpolation()?;
+            if !interpolation.is_empty() {
+                parent_config.interpolation = Some(interpolation);
+            }
+        }
+        let config = RelationConfig::new(&parent_config, &my_config);
         // osm street name -> house number list map, so we don't have to read the on-disk list of the
         // relation again and again for each street.
         let osm_housenumbers: HashMap<String, Vec<util::HouseNumber>> = HashMap::new();
         let lints: Vec<RelationLint> = Vec::new();
+        let unparseable_refs: Vec<(String, String)> = Vec::new();
         Ok(Relation {
             ctx,
             name: name.into(),
@@ -465,6 +620,7 @@ impl<'a> Relation<'a> {
             config,
             osm_housenumbers,
             lints,
+            unparseable_refs,
         })
     }
 
@@ -477,6 +633,14 @@ impl<'a> Relation<'a> {
         self.name.clone()
     }
 
+    /// Gets the name of the relation to be shown to users: the display-name key, falling back to
+    /// the name, which is also used in URLs and file names.
+    pub fn get_display_name(&self) -> String {
+        self.config
+            .get_display_name()
+            .unwrap_or_else(|| self.get_name())
+    }
+
     /// Gets access to the file interface.
     pub fn get_files(&self) -> &area_files::RelationFiles {
         &self.file
@@ -503,10 +667,7 @@ impl<'a> Relation<'a> {
             }
         };
         for (street, filter) in filters {
-            let mut interpolation = "";
-            if let Some(ref value) = filter.interpolation {
-                interpolation = value;
-            }
+            let interpolation = self.config.get_filter_interpolation(filter);
             let mut i: Vec<ranges::Range> = Vec::new();
             if let Some(ref value) = filter.ranges {
                 for range in value {
@@ -520,7 +681,7 @@ impl<'a> Relation<'a> {
                         .trim()
                         .parse::<i64>()
                         .context("failed to parse() 'end'")?;
-                    i.push(ranges::Range::new(start, end, interpolation));
+                    i.push(ranges::Range::new(start, end, &interpolation));
                 }
                 filter_dict.insert(street.into(), ranges::Ranges::new(i));
             }
@@ -545,7 +706,7 @@ impl<'a> Relation<'a> {
     }
 
     /// Reads list of streets for an area from OSM.
-    fn get_osm_streets(&self, sorted_result: bool) -> anyhow::Result<Vec<util::Street>> {
+    pub fn get_osm_streets(&self, sorted_result: bool) -> anyhow::Result<Vec<util::Street>> {
         let mut ret: Vec<util::Street> = Vec::new();
         for row in self.file.get_osm_json_streets(self.ctx)? {
             let mut street = util::Street::new(
@@ -575,17 +736,29 @@ impl<'a> Relation<'a> {
 
     /// Produces a query which lists streets in relation.
     pub fn get_osm_streets_query(&self) -> anyhow::Result<String> {
+        let template = self
+            .config
+            .get_osm_streets_query_template()
+            .unwrap_or("streets-template.overpassql".into());
         let contents = self.ctx.get_file_system().read_to_string(&format!(
             "{}/{}",
             self.ctx.get_abspath("data"),
-            "streets-template.overpassql"
+            template
         ))?;
         Ok(util::process_template(
             &contents,
-            self.config.get_osmrelation(),
+            &self.config.get_osmrelations(),
         ))
     }
 
+    /// Produces the settings line of a JSON query, scoped to the snapshot date if there is one.
+    fn get_json_query_settings(&self) -> String {
+        match self.config.get_osm_snapshot() {
+            Some(date) => format!("[out:json] [date:\"{date}T00:00:00Z\"];"),
+            None => "[out:json];".to_string(),
+        }
+    }
+
     /// Produces a query which lists streets in relation, in JSON format.
     pub fn get_osm_streets_json_query(&self) -> anyhow::Result<String> {
         let query = self.get_osm_streets_query()?;
@@ -594,7 +767,7 @@ impl<'a> Relation<'a> {
         for line in query.lines() {
             i += 1;
             if i == 1 {
-                lines.push("[out:json];".to_string());
+                lines.push(self.get_json_query_settings());
                 continue;
             }
 
@@ -603,7 +776,8 @@ impl<'a> Relation<'a> {
         Ok(lines.join("\n"))
     }
 
-    /// Gets streets from reference.
+    /// Gets streets from reference. Entries are qualified by refcounty and refsettlement, so
+    /// same-named streets of other areas are not merged into the result.
     fn get_ref_streets(&self) -> anyhow::Result<Vec<String>> {
         let conn = self.ctx.get_database_connection()?;
 
@@ -635,8 +809,12 @@ impl<'a> Relation<'a> {
             let street_ranges = self.get_street_ranges()?;
             let mut house_numbers: HashMap<String, Vec<util::HouseNumber>> = HashMap::new();
             let osm_housenumbers = self.file.get_osm_json_housenumbers(self.ctx)?;
+            let excluded_users = self.config.get_excluded_users();
             let mut lints: Vec<RelationLint> = Vec::new();
             for row in osm_housenumbers {
+                if excluded_users.contains(&row.user) {
+                    continue;
+                }
                 let mut street = &row.street;
                 if street.is_empty() {
                     if let Some(ref value) = row.place {
@@ -716,6 +894,20 @@ impl<'a> Relation<'a> {
         })
     }
 
+    /// Gets the OSM house numbers of all streets, keyed by the street name.
+    pub fn get_osm_housenumbers_by_street(
+        &mut self,
+    ) -> anyhow::Result<BTreeMap<String, Vec<util::HouseNumber>>> {
+        // Reads the house numbers of all streets on first use.
+        self.get_osm_housenumbers("")?;
+        Ok(self
+            .osm_housenumbers
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect())
+    }
+
     /// Determines what suffix should the Nth reference use for hours numbers.
     fn get_ref_suffix(index: usize) -> &'static str {
         match index {
@@ -724,37 +916,97 @@ impl<'a> Relation<'a> {
         }
     }
 
-    /// Writes known house numbers (not their coordinates) from a reference, based on street names
-    /// from OSM. Uses build_reference_cache() to build an indexed reference, the result will be
-    /// used by get_ref_housenumbers().
-    pub fn write_ref_housenumbers(&self) -> anyhow::Result<()> {
-        let streets: Vec<String> = self
-            .get_osm_streets(/*sorted_results=*/ true)?
-            .iter()
-            .map(|i| i.get_osm_name().into())
-            .collect();
+    /// Formats a reference house number as a line for write_ref_housenumbers().
+    fn format_ref_housenumber_line(street: &str, housenumber: &str, comment: &str) -> String {
+        let suffix = Relation::get_ref_suffix(if comment.is_empty() { 0 } else { 1 });
+        let comment = if comment == " " { "" } else { comment };
+        format!("{street}\t{housenumber}{suffix}\t{comment}")
+    }
 
+    /// Looks up the reference house numbers of the given OSM streets in the global reference index.
+    fn get_ref_housenumber_lines_from_index(
+        &self,
+        streets: &[String],
+    ) -> anyhow::Result<Vec<String>> {
         let conn = self.ctx.get_database_connection()?;
         let mut lst: Vec<String> = Vec::new();
         let mut stmt = conn.prepare(
             "select housenumber, comment from ref_housenumbers where county_code = ?1 and settlement_code = ?2 and street = ?3")?;
         for street in streets {
-            let street = self.config.get_ref_street_from_osm_street(&street);
+            let street = self.config.get_ref_street_from_osm_street(street);
             for refsettlement in self.config.get_street_refsettlement(&street) {
                 let mut rows =
                     stmt.query([&self.config.get_refcounty(), &refsettlement, &street])?;
                 while let Some(row) = rows.next()? {
                     let housenumber: String = row.get(0).unwrap();
-                    let mut comment: String = row.get(1).unwrap();
-                    let suffix = Relation::get_ref_suffix(if comment.is_empty() { 0 } else { 1 });
-                    if comment == " " {
-                        comment = "".into();
-                    }
-                    lst.push(street.clone() + "\t" + &housenumber + suffix + "\t" + &comment);
+                    let comment: String = row.get(1).unwrap();
+                    lst.push(Relation::format_ref_housenumber_line(
+                        &street,
+                        &housenumber,
+                        &comment,
+                    ));
                 }
             }
         }
+        Ok(lst)
+    }
 
+    /// Looks up the reference house numbers of the given OSM streets in relation-specific reference
+    /// files, bypassing the global reference index.
+    fn get_ref_housenumber_lines_from_files(
+        &self,
+        streets: &[String],
+        relpaths: &[String],
+    ) -> anyhow::Result<Vec<String>> {
+        // Reference street name -> reference settlements.
+        let mut refsettlements: HashMap<String, Vec<String>> = HashMap::new();
+        for street in streets {
+            let street = self.config.get_ref_street_from_osm_street(street);
+            let value = self.config.get_street_refsettlement(&street);
+            refsettlements.insert(street, value);
+        }
+        let refcounty = self.config.get_refcounty();
+        let mut lst: Vec<String> = Vec::new();
+        for relpath in relpaths {
+            let abspath = self.ctx.get_abspath(relpath);
+            util::for_each_reference_housenumber(self.ctx, &abspath, &mut |row| {
+                if row.county != refcounty {
+                    return Ok(());
+                }
+                if let Some(value) = refsettlements.get(&row.street) {
+                    if value.contains(&row.settlement) {
+                        lst.push(Relation::format_ref_housenumber_line(
+                            &row.street,
+                            &row.housenumber,
+                            &row.comment.unwrap_or_default(),
+                        ));
+                    }
+                }
+                Ok(())
+            })
+            .context(format!("failed to read {abspath}"))?;
+        }
+        Ok(lst)
+    }
+
+    /// Writes known house numbers (not their coordinates) from a reference, based on street names
+    /// from OSM. Uses build_reference_cache() to build an indexed reference, the result will be
+    /// used by get_ref_housenumbers().
+    pub fn write_ref_housenumbers(&self) -> anyhow::Result<()> {
+        let streets: Vec<String> = self
+            .get_osm_streets(/*sorted_results=*/ true)?
+            .iter()
+            .map(|i| i.get_osm_name().into())
+            .collect();
+
+        let reference_housenumbers = self.config.get_reference_housenumbers();
+        let mut lst: Vec<String> = if reference_housenumbers.is_empty() {
+            self.get_ref_housenumber_lines_from_index(&streets)?
+        } else {
+            self.get_ref_housenumber_lines_from_files(&streets, &reference_housenumbers)?
+        };
+
+        // The same line may come from multiple references, write it only once.
         lst.sort();
         lst.dedup();
         let stream = self
@@ -792,12 +1044,8 @@ impl<'a> Relation<'a> {
         Ok(normalized_invalids)
     }
 
-    /// Gets house numbers from reference, produced by write_ref_housenumbers()."""
-    fn get_ref_housenumbers(
-        &mut self,
-        osm_street_names: &[util::Street],
-    ) -> anyhow::Result<HashMap<String, Vec<util::HouseNumber>>> {
-        let mut ret: HashMap<String, Vec<util::HouseNumber>> = HashMap::new();
+    /// Reads the lines produced by write_ref_housenumbers(), grouped by reference street name.
+    fn read_ref_housenumber_lines(&self) -> anyhow::Result<HashMap<String, Vec<String>>> {
         let mut lines: HashMap<String, Vec<String>> = HashMap::new();
         let read: Rc<RefCell<dyn Read>> = self.file.get_ref_housenumbers_read_stream(self.ctx)?;
         let mut guard = read.borrow_mut();
@@ -816,6 +1064,47 @@ impl<'a> Relation<'a> {
             }
             lines.entry(key).or_default().push(value.into());
         }
+        Ok(lines)
+    }
+
+    /// Counts how many reference house numbers each filter range contains, so ranges which match
+    /// nothing can be found. Returns (street, range, count) tuples.
+    pub fn get_filter_range_matches(&self) -> anyhow::Result<Vec<(String, String, usize)>> {
+        let mut ret: Vec<(String, String, usize)> = Vec::new();
+        let lines = self.read_ref_housenumber_lines()?;
+        let street_ranges = self.get_street_ranges()?;
+        let mut streets: Vec<&String> = street_ranges.keys().collect();
+        streets.sort_by_key(|i| util::get_sort_key(i));
+        // No filtering, so all reference house numbers are counted.
+        let normalizers: HashMap<String, ranges::Ranges> = HashMap::new();
+        for street in streets {
+            let ref_street = self.config.get_ref_street_from_osm_street(street);
+            let mut numbers: Vec<i64> = Vec::new();
+            if let Some(values) = lines.get(&ref_street) {
+                for value in values {
+                    let house_numbers =
+                        normalize(self, value, street, &normalizers, &mut None, None)?;
+                    for house_number in house_numbers {
+                        let (number, _) = util::split_house_number(house_number.get_number());
+                        numbers.push(number.into());
+                    }
+                }
+            }
+            for range in street_ranges[street].get_items() {
+                let count = numbers.iter().filter(|i| range.contains(**i)).count();
+                ret.push((street.to_string(), range.to_string(), count));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Gets house numbers from reference, produced by write_ref_housenumbers()."""
+    fn get_ref_housenumbers(
+        &mut self,
+        osm_street_names: &[util::Street],
+    ) -> anyhow::Result<HashMap<String, Vec<util::HouseNumber>>> {
+        let mut ret: HashMap<String, Vec<util::HouseNumber>> = HashMap::new();
+        let lines = self.read_ref_housenumber_lines()?;
         let street_ranges = self
             .get_street_ranges()
             .context("get_street_ranges() failed")?;
@@ -836,6 +1125,11 @@ impl<'a> Relation<'a> {
             let mut used_invalids: Vec<String> = Vec::new();
             if let Some(value) = lines.get(&ref_street_name) {
                 for house_number in value {
+                    if !util::is_parseable_house_number(house_number) {
+                        let house_number = house_number.split('\t').next().unwrap_or("");
+                        self.unparseable_refs
+                            .push((ref_street_name.to_string(), house_number.to_string()));
+                    }
                     let normalized = normalize(
                         self,
                         house_number,
@@ -949,7 +1243,7 @@ impl<'a> Relation<'a> {
             }
         }
         // Sort by length, reverse.
-        ongoing_streets.sort_by(|a, b| b.house_numbers.len().cmp(&a.house_numbers.len()));
+        ongoing_streets.sort_by_key(|i| std::cmp::Reverse(i.house_numbers.len()));
 
         Ok(MissingHousenumbers {
             ongoing_streets,
@@ -977,18 +1271,31 @@ impl<'a> Relation<'a> {
             })
             .collect();
 
-        let only_in_reference = util::get_only_in_first(&reference_streets, &osm_streets);
+        let (only_in_reference, in_both) = if self.config.get_street_match() == "casefold" {
+            let (in_both, only_in_reference): (Vec<util::Street>, Vec<util::Street>) =
+                reference_streets.iter().cloned().partition(|i| {
+                    osm_streets
+                        .iter()
+                        .any(|j| util::casefold_eq(j.get_osm_name(), i.get_osm_name()))
+                });
+            (only_in_reference, in_both)
+        } else {
+            (
+                util::get_only_in_first(&reference_streets, &osm_streets),
+                util::get_in_both(&reference_streets, &osm_streets),
+            )
+        };
         let only_in_ref_names: Vec<String> = only_in_reference
             .iter()
-            .filter(|i| !street_blacklist.contains(i.get_osm_name()))
-            .map(|i| i.get_osm_name())
-            .cloned()
-            .collect();
-        let in_both: Vec<String> = util::get_in_both(&reference_streets, &osm_streets)
-            .iter()
+            .filter(|i| {
+                !street_blacklist
+                    .iter()
+                    .any(|j| util::casefold_eq(j, i.get_osm_name()))
+            })
             .map(|i| i.get_osm_name())
             .cloned()
             .collect();
+        let in_both: Vec<String> = in_both.iter().map(|i| i.get_osm_name()).cloned().collect();
 
         Ok((only_in_ref_names, in_both))
     }
@@ -1008,7 +1315,11 @@ impl<'a> Relation<'a> {
         let osm_street_blacklist = self.config.get_osm_street_filters();
 
         let mut only_in_osm = util::get_only_in_first(&osm_streets, &ref_street_objs);
-        only_in_osm.retain(|i| !osm_street_blacklist.contains(i.get_osm_name()));
+        only_in_osm.retain(|i| {
+            !osm_street_blacklist
+                .iter()
+                .any(|j| util::casefold_eq(j, i.get_osm_name()))
+        });
 
         Ok(only_in_osm)
     }
@@ -1126,7 +1437,21 @@ impl<'a> Relation<'a> {
     ) -> anyhow::Result<(usize, usize, usize, f64, yattag::HtmlTable)> {
         let json = cache::get_missing_housenumbers_json(self)
             .context("get_missing_housenumbers_json() failed")?;
-        let missing_housenumbers: MissingHousenumbers = serde_json::from_str(&json)?;
+        let ret = self.get_missing_housenumbers_stats(&json)?;
+
+        // Write the bottom line to a file, so the index page show it fast.
+        self.set_osm_housenumber_coverage(&format!("{:.2}", ret.3))?;
+
+        Ok(ret)
+    }
+
+    /// Calculates the missing house number counts, coverage percent and table of the relation from
+    /// the output of cache::get_missing_housenumbers_json(), without storing the coverage.
+    pub fn get_missing_housenumbers_stats(
+        &self,
+        json: &str,
+    ) -> anyhow::Result<(usize, usize, usize, f64, yattag::HtmlTable)> {
+        let missing_housenumbers: MissingHousenumbers = serde_json::from_str(json)?;
 
         let (table, todo_count) =
             self.numbered_streets_to_table(&missing_housenumbers.ongoing_streets);
@@ -1143,9 +1468,6 @@ impl<'a> Relation<'a> {
             100_f64
         };
 
-        // Write the bottom line to a file, so the index page show it fast.
-        self.set_osm_housenumber_coverage(&format!("{percent:.2}"))?;
-
         Ok((
             missing_housenumbers.ongoing_streets.len(),
             todo_count,
@@ -1201,7 +1523,7 @@ impl<'a> Relation<'a> {
             }
         }
         // Sort by length, reverse.
-        additional.sort_by(|a, b| b.house_numbers.len().cmp(&a.house_numbers.len()));
+        additional.sort_by_key(|i| std::cmp::Reverse(i.house_numbers.len()));
 
         Ok(additional)
     }
@@ -1229,26 +1551,37 @@ impl<'a> Relation<'a> {
 
     /// Produces a query which lists house numbers in relation.
     pub fn get_osm_housenumbers_query(&self) -> anyhow::Result<String> {
+        let template = self
+            .config
+            .get_osm_housenumbers_query_template()
+            .unwrap_or("street-housenumbers-template.overpassql".into());
         let contents = self.ctx.get_file_system().read_to_string(&format!(
             "{}/{}",
             self.ctx.get_abspath("data"),
-            "street-housenumbers-template.overpassql"
+            template
         ))?;
         Ok(util::process_template(
             &contents,
-            self.config.get_osmrelation(),
+            &self.config.get_osmrelations(),
         ))
     }
 
     /// Produces a query which lists housenumbers in relation, in JSON format.
     pub fn get_osm_housenumbers_json_query(&self) -> anyhow::Result<String> {
         let query = self.get_osm_housenumbers_query()?;
+        // The last editors are only needed (and only stored) when some of them are excluded.
+        let with_meta = !self.config.get_excluded_users().is_empty();
         let mut i = 0;
         let mut lines = Vec::new();
         for line in query.lines() {
             i += 1;
             if i == 1 {
-                lines.push("[out:json];".to_string());
+                lines.push(self.get_json_query_settings());
+                continue;
+            }
+
+            if with_meta && line.trim() == "out body;" {
+                lines.push("out meta;".to_string());
                 continue;
             }
 
@@ -1295,7 +1628,7 @@ impl<'a> Relation<'a> {
                 return Ok(Vec::new());
             }
         };
-        let keys: Vec<String> = filters.iter().map(|(key, _value)| key.clone()).collect();
+        let keys: Vec<String> = filters.keys().cloned().collect();
         let osm_streets: Vec<String> = self
             .get_osm_streets(/*sorted_result=*/ true)?
             .iter()
@@ -1332,6 +1665,45 @@ impl<'a> Relation<'a> {
         Ok(())
     }
 
+    /// Records the current house number coverage as today's `<date>\t<percent>` line in
+    /// workdir/<relation>.percent.log. A second run on the same day overwrites the line.
+    pub fn write_osm_housenumber_coverage_history(&self) -> anyhow::Result<()> {
+        let coverage = self.get_osm_housenumber_coverage()?;
+        let format = time::format_description::parse("[year]-[month]-[day]")?;
+        let today = self.ctx.get_time().now().format(&format)?;
+        let mut history: BTreeMap<String, String> = self
+            .get_osm_housenumber_coverage_history()?
+            .into_iter()
+            .collect();
+        history.insert(today, coverage);
+        let mut content = String::new();
+        for (date, percent) in history {
+            content.push_str(&format!("{date}\t{percent}\n"));
+        }
+        let path = self.file.get_housenumbers_percent_log_path();
+        self.ctx
+            .get_file_system()
+            .write_from_string_atomic(&content, &path)
+    }
+
+    /// Gets the date - house number coverage pairs of the relation, sorted by date.
+    pub fn get_osm_housenumber_coverage_history(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let path = self.file.get_housenumbers_percent_log_path();
+        if !self.ctx.get_file_system().path_exists(&path) {
+            return Ok(Vec::new());
+        }
+        let content = self.ctx.get_file_system().read_to_string(&path)?;
+        let mut ret: Vec<(String, String)> = Vec::new();
+        for line in content.lines() {
+            let (date, percent) = line
+                .split_once('\t')
+                .context(format!("failed to parse '{line}' in '{path}'"))?;
+            ret.push((date.to_string(), percent.to_string()));
+        }
+        ret.sort();
+        Ok(ret)
+    }
+
     pub fn write_lints(&mut self) -> anyhow::Result<()> {
         let conn = self.ctx.get_database_connection()?;
         conn.execute(
@@ -1349,6 +1721,100 @@ impl<'a> Relation<'a> {
         Ok(())
     }
 
+    /// Writes the reference house numbers which could not be interpreted during the last
+    /// missing-housenumbers analysis.
+    pub fn write_unparseable_refs(&mut self) -> anyhow::Result<()> {
+        let conn = self.ctx.get_database_connection()?;
+        conn.execute(
+            "delete from relation_unparseable_refs where relation_name = ?1",
+            [&self.name],
+        )?;
+        self.unparseable_refs.sort();
+        self.unparseable_refs.dedup();
+        for (street_name, housenumber) in self.unparseable_refs.iter() {
+            conn.execute(
+                "insert into relation_unparseable_refs (relation_name, street_name, housenumber) values (?1, ?2, ?3)",
+                [&self.name, street_name, housenumber],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Gets the reference street name - house number pairs which could not be interpreted.
+    pub fn get_unparseable_refs(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let conn = self.ctx.get_database_connection()?;
+        let mut stmt = conn.prepare(
+            "select street_name, housenumber from relation_unparseable_refs where relation_name = ?1 order by street_name, housenumber",
+        )?;
+        let mut rows = stmt.query([&self.name])?;
+        let mut ret: Vec<(String, String)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            ret.push((row.get(0)?, row.get(1)?));
+        }
+        Ok(ret)
+    }
+
+    /// Hashes the inputs of the analysis: the OSM streets and house numbers, the reference house
+    /// numbers and the config of the relation. Unlike mtimes, this survives copying the state
+    /// between machines.
+    pub fn input_hash(&self) -> anyhow::Result<String> {
+        let mut buf = String::new();
+        // A new version may analyze the same input differently.
+        writeln!(buf, "{}", env!("CARGO_PKG_VERSION"))?;
+        // serde_json::Value has sorted keys, so the order of e.g. refstreets is stable.
+        let parent = serde_json::to_value(&self.config.parent)?;
+        let dict = serde_json::to_value(&self.config.dict)?;
+        let interpolation = self.ctx.get_ini().get_default_interpolation()?;
+        writeln!(buf, "{parent}\t{dict}\t{interpolation}")?;
+        let conn = self.ctx.get_database_connection()?;
+        for table in ["osm_streets", "osm_housenumbers"] {
+            let mut stmt = conn.prepare(&format!("select * from {table} where relation = ?1"))?;
+            let column_count = stmt.column_count();
+            let mut rows = stmt.query([&self.name])?;
+            let mut lines: Vec<String> = Vec::new();
+            while let Some(row) = rows.next()? {
+                let mut line = String::new();
+                for index in 0..column_count {
+                    let value: String = row.get(index)?;
+                    write!(line, "{value}\t")?;
+                }
+                lines.push(line);
+            }
+            // Sort on all columns, the row order of the database is not stable.
+            lines.sort();
+            for line in lines {
+                writeln!(buf, "{line}")?;
+            }
+        }
+        let path = self.file.get_ref_housenumbers_path();
+        if self.ctx.get_file_system().path_exists(&path) {
+            buf += &self.ctx.get_file_system().read_to_string(&path)?;
+        }
+        Ok(util::get_stable_hash(buf.as_bytes()))
+    }
+
+    /// Gets the input_hash() of the last house number coverage calculation, if there is one.
+    pub fn get_osm_housenumber_coverage_input_hash(&self) -> anyhow::Result<Option<String>> {
+        let conn = self.ctx.get_database_connection()?;
+        let mut stmt = conn
+            .prepare("select input_hash from osm_housenumber_coverages where relation_name = ?1")?;
+        let mut rows = stmt.query([&self.name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Records the input_hash() of the current house number coverage.
+    pub fn set_osm_housenumber_coverage_input_hash(&self, input_hash: &str) -> anyhow::Result<()> {
+        let conn = self.ctx.get_database_connection()?;
+        conn.execute(
+            "update osm_housenumber_coverages set input_hash = ?1 where relation_name = ?2",
+            [input_hash, &self.name],
+        )?;
+        Ok(())
+    }
+
     pub fn get_osm_housenumber_coverage(&self) -> anyhow::Result<String> {
         let conn = self.ctx.get_database_connection()?;
         let mut stmt = conn
@@ -1414,6 +1880,25 @@ impl<'a> Relation<'a> {
         let now = self.ctx.get_time().now();
         Ok(modified.to_offset(now.offset()))
     }
+
+    /// Decides if the OSM house numbers of this relation were not refreshed for a while.
+    pub fn is_stale(&self) -> anyhow::Result<bool> {
+        if self.config.is_done() {
+            // Not updated on purpose.
+            return Ok(false);
+        }
+
+        let page = format!("housenumbers/{}", self.name);
+        if !stats::has_sql_mtime(self.ctx, &page)? {
+            // Never fetched: that's a new relation, not a stale one.
+            return Ok(false);
+        }
+
+        let modified = stats::get_sql_mtime(self.ctx, &page)?;
+        let stale_days = self.ctx.get_ini().get_stale_days()?;
+        let now = self.ctx.get_time().now();
+        Ok(now - modified >= time::Duration::days(stale_days))
+    }
 }
 
 /// List of relations from data/relations.yaml.
@@ -1428,6 +1913,7 @@ pub struct Relations<'a> {
     activate_all: bool,
     activate_new: bool,
     activate_invalid: bool,
+    ignore_done: bool,
     refcounty_names: HashMap<String, String>,
     refsettlement_names: HashMap<String, HashMap<String, String>>,
 }
@@ -1439,17 +1925,21 @@ impl<'a> Relations<'a> {
         if let Ok(stream) = ctx.get_file_system().open_read(&yamls_cache_path) {
             let mut guard = stream.borrow_mut();
             let read = guard.deref_mut();
-            yaml_cache = serde_json::from_reader(read)?;
+            yaml_cache = serde_json::from_reader(read).map_err(|err| {
+                OsmGimmisnError::Config(format!("failed to parse {yamls_cache_path}: {err}"))
+            })?;
         }
         let mut dict: RelationsDict = HashMap::new();
         if let Some(value) = yaml_cache.get("relations.yaml") {
-            dict =
-                serde_json::from_value(value.clone()).context("failed to parse relations.yaml")?;
+            dict = serde_json::from_value(value.clone()).map_err(|err| {
+                OsmGimmisnError::Config(format!("failed to parse relations.yaml: {err}"))
+            })?;
         }
         let relations: HashMap<String, Relation<'a>> = HashMap::new();
         let activate_all = false;
         let activate_new = false;
         let activate_invalid = false;
+        let ignore_done = false;
         let refcounty_names: HashMap<String, String> = match yaml_cache.get("refcounty-names.yaml")
         {
             Some(value) => serde_json::from_value(value.clone())
@@ -1470,12 +1960,14 @@ impl<'a> Relations<'a> {
             activate_all,
             activate_new,
             activate_invalid,
+            ignore_done,
             refcounty_names,
             refsettlement_names,
         })
     }
 
-    /// Gets the relation that has the specified name.
+    /// Gets the relation that has the specified name. The YAML is only parsed on the first lookup of
+    /// a name, later lookups return a clone of the cached relation.
     pub fn get_relation(&mut self, name: &str) -> anyhow::Result<Relation<'a>> {
         if !self.relations.contains_key(name) {
             let relation = Relation::new(
@@ -1498,6 +1990,25 @@ impl<'a> Relations<'a> {
         ret
     }
 
+    /// Gets the effective config of all relations: the relations.yaml entry, with keys overridden
+    /// by relation-<name>.yaml.
+    pub fn get_effective_configs(&self) -> BTreeMap<String, serde_json::Value> {
+        let mut ret: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        let parents = self.yaml_cache.get("relations.yaml");
+        for name in self.get_names() {
+            let mut config = serde_json::Map::new();
+            if let Some(serde_json::Value::Object(parent)) = parents.and_then(|i| i.get(&name)) {
+                config.extend(parent.clone());
+            }
+            let relation_path = format!("relation-{name}.yaml");
+            if let Some(serde_json::Value::Object(mine)) = self.yaml_cache.get(&relation_path) {
+                config.extend(mine.clone());
+            }
+            ret.insert(name, serde_json::Value::Object(config));
+        }
+        ret
+    }
+
     fn is_new(&self, relation: &Relation<'a>) -> bool {
         if !self.activate_new {
             return false;
@@ -1602,6 +2113,16 @@ impl<'a> Relations<'a> {
         self.activate_invalid = true;
     }
 
+    /// Updates relations marked as done, too.
+    pub fn ignore_done(&mut self) {
+        self.ignore_done = true;
+    }
+
+    /// Decides if the OSM data of a relation should not be updated, as it's marked as done.
+    pub fn is_done(&self, relation: &Relation<'a>) -> bool {
+        !self.ignore_done && relation.config.is_done()
+    }
+
     /// If refcounty is not None, forget about all relations outside that refcounty.
     pub fn limit_to_refcounty(&mut self, refcounty: &Option<&String>) -> anyhow::Result<()> {
         let refcounty: String = match refcounty {
@@ -1665,6 +2186,24 @@ impl<'a> Relations<'a> {
         Ok(())
     }
 
+    /// Gets the sorted names of relations with a house number coverage below `threshold`. Relations
+    /// with no coverage yet count as 0%.
+    pub fn get_relations_below_percent(&mut self, threshold: f64) -> anyhow::Result<Vec<String>> {
+        let mut ret: Vec<String> = Vec::new();
+        for name in self.get_names() {
+            let relation = self.get_relation(&name)?;
+            let mut percent = 0_f64;
+            if relation.has_osm_housenumber_coverage()? {
+                let string = relation.get_osm_housenumber_coverage()?;
+                percent = string.parse::<f64>().context("parse to f64 failed")?;
+            }
+            if percent < threshold {
+                ret.push(name);
+            }
+        }
+        Ok(ret)
+    }
+
     /// Produces refsettlement IDs of a refcounty.
     pub fn refcounty_get_refsettlement_ids(&self, refcounty_name: &str) -> Vec<String> {
         let refcounty = match self.refsettlement_names.get(refcounty_name) {
@@ -1673,7 +2212,7 @@ impl<'a> Relations<'a> {
                 return Vec::new();
             }
         };
-        let mut ret: Vec<String> = refcounty.iter().map(|(key, _value)| key.clone()).collect();
+        let mut ret: Vec<String> = refcounty.keys().cloned().collect();
         ret.sort();
         ret
     }
@@ -1694,6 +2233,82 @@ impl<'a> Relations<'a> {
     }
 }
 
+/// Reads a YAML file from datadir, resolving `!include <path>` values, so relations can share
+/// e.g. common filter blocks. Returns the original text if there are no includes, to keep line
+/// numbers in parse errors.
+pub fn read_yaml_with_includes(
+    ctx: &context::Context,
+    datadir: &str,
+    path: &str,
+) -> anyhow::Result<String> {
+    let data = ctx.get_file_system().read_to_string(path)?;
+    if !data.contains("!include") {
+        return Ok(data);
+    }
+
+    let mut stack: Vec<String> = Vec::new();
+    let value = read_yaml_value(ctx, datadir, path, &mut stack)?;
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+/// Reads a YAML file to a value with resolved includes. Stack is the chain of files being read,
+/// to detect cycles.
+fn read_yaml_value(
+    ctx: &context::Context,
+    datadir: &str,
+    path: &str,
+    stack: &mut Vec<String>,
+) -> anyhow::Result<serde_yaml::Value> {
+    if stack.iter().any(|i| i == path) {
+        stack.push(path.to_string());
+        return Err(
+            OsmGimmisnError::Config(format!("include cycle: {}", stack.join(" -> "))).into(),
+        );
+    }
+
+    stack.push(path.to_string());
+    let data = ctx.get_file_system().read_to_string(path)?;
+    let value: serde_yaml::Value = serde_yaml::from_str(&data).map_err(|err| {
+        OsmGimmisnError::Config(format!("serde_yaml::from_str() failed for {path}: {err}"))
+    })?;
+    let value = resolve_yaml_includes(ctx, datadir, value, stack)?;
+    stack.pop();
+    Ok(value)
+}
+
+/// Replaces `!include <path>` tagged values with the content of the referenced file.
+fn resolve_yaml_includes(
+    ctx: &context::Context,
+    datadir: &str,
+    value: serde_yaml::Value,
+    stack: &mut Vec<String>,
+) -> anyhow::Result<serde_yaml::Value> {
+    match value {
+        serde_yaml::Value::Tagged(tagged) if tagged.tag == "include" => {
+            let include = tagged
+                .value
+                .as_str()
+                .context("expected a path as the value of !include")?;
+            read_yaml_value(ctx, datadir, &format!("{datadir}/{include}"), stack)
+        }
+        serde_yaml::Value::Sequence(sequence) => {
+            let mut ret = serde_yaml::Sequence::new();
+            for item in sequence {
+                ret.push(resolve_yaml_includes(ctx, datadir, item, stack)?);
+            }
+            Ok(serde_yaml::Value::Sequence(ret))
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut ret = serde_yaml::Mapping::new();
+            for (key, value) in mapping {
+                ret.insert(key, resolve_yaml_includes(ctx, datadir, value, stack)?);
+            }
+            Ok(serde_yaml::Value::Mapping(ret))
+        }
+        value => Ok(value),
+    }
+}
+
 pub fn normalizer_contains(
     number: i64,
     normalizer: &ranges::Ranges,
@@ -1923,7 +2538,7 @@ rel(@RELATION@)->.searchRelation;
 area(@AREA@)->.searchArea;
 (rel(@RELATION@);
 "#;
-    let mut query = util::process_template(header, relation.config.get_osmrelation());
+    let mut query = util::process_template(header, &relation.config.get_osmrelations());
     for street in streets {
         let _ = writeln!(query, "way[\"name\"=\"{street}\"](r.searchRelation);");
         let _ = writeln!(query, "way[\"name\"=\"{street}\"](area.searchArea);");
@@ -1948,7 +2563,7 @@ pub fn make_turbo_query_for_street_objs(
 rel(@RELATION@)->.searchRelation;
 area(@AREA@)->.searchArea;
 ("#;
-    let mut query = util::process_template(header, relation.config.get_osmrelation());
+    let mut query = util::process_template(header, &relation.config.get_osmrelations());
     let mut ids = Vec::new();
     for street in streets {
         ids.push((street.get_osm_type(), street.get_osm_id().to_string()));
@@ -677,6 +677,99 @@ fn test_relation_get_osm_streets_json_query() {
     assert_eq!(ret, "[out:json];\naaa 42 bbb 3600000042 ccc");
 }
 
+/// Tests Relation.get_osm_streets_json_query(), when the relation is pinned to a snapshot.
+#[test]
+fn test_relation_get_osm_streets_json_query_snapshot() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "osm-snapshot": "2020-01-01",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    template_value
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)] [timeout:425];\naaa @RELATION@ bbb @AREA@ ccc\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+
+    let ret = relation.get_osm_streets_json_query().unwrap();
+
+    assert_eq!(
+        ret,
+        "[out:json] [date:\"2020-01-01T00:00:00Z\"];\naaa 42 bbb 3600000042 ccc"
+    );
+}
+
+/// Tests Relation.get_osm_streets_query(): a per-relation template replaces the generated query.
+#[test]
+fn test_relation_get_osm_streets_query_override() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+            "budafok": {
+                "osmrelation": 43,
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "osm-streets-query": "streets-template-gazdagret.overpassql",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    template_value
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)] [timeout:425];\ngenerated @RELATION@\n")
+        .unwrap();
+    let override_value = context::tests::TestFileSystem::make_file();
+    override_value
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)] [timeout:425];\nassociatedStreet @RELATION@\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+            (
+                "data/streets-template-gazdagret.overpassql",
+                &override_value,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+
+    let relation = relations.get_relation("gazdagret").unwrap();
+    let ret = relation.get_osm_streets_query().unwrap();
+    assert_eq!(ret, "[out:csv(::id)] [timeout:425];\nassociatedStreet 42\n");
+
+    // No override: the generated query is used.
+    let relation = relations.get_relation("budafok").unwrap();
+    let ret = relation.get_osm_streets_query().unwrap();
+    assert_eq!(ret, "[out:csv(::id)] [timeout:425];\ngenerated 43\n");
+}
+
 /// Tests Relation.get_osm_housenumbers_query().
 #[test]
 fn test_relation_get_osm_housenumbers_query() {
@@ -747,6 +840,54 @@ fn test_relation_get_osm_housenumbers_json_query() {
     assert_eq!(ret, "[out:json];\nhousenr aaa 42 bbb 3600000042 ccc");
 }
 
+/// Tests Relation.get_osm_housenumbers_json_query(): metadata is only requested with excluded users.
+#[test]
+fn test_relation_get_osm_housenumbers_json_query_excluded_users() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 43,
+            },
+            "gazdagret": {
+                "osmrelation": 42,
+                "excluded-users": ["baduser"],
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    overpass_template
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)] [timeout:425];\nhousenr @AREA@\nout body;\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "data/street-housenumbers-template.overpassql",
+                &overpass_template,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+
+    let gazdagret = relations.get_relation("gazdagret").unwrap();
+    let budafok = relations.get_relation("budafok").unwrap();
+
+    assert_eq!(
+        gazdagret.get_osm_housenumbers_json_query().unwrap(),
+        "[out:json];\nhousenr 3600000042\nout meta;"
+    );
+    assert_eq!(
+        budafok.get_osm_housenumbers_json_query().unwrap(),
+        "[out:json];\nhousenr 3600000043\nout body;"
+    );
+}
+
 /// Tests RelationFiles.write_osm_streets().
 #[test]
 fn test_relation_files_write_osm_streets() {
@@ -874,6 +1015,66 @@ fn test_relation_get_street_ranges() {
     assert_eq!(street_blacklist, ["mystreet2".to_string()]);
 }
 
+/// Tests Relation::get_street_ranges(): ranges without interpolation inherit the relation default.
+#[test]
+fn test_relation_get_street_ranges_interpolation_default() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relation-myrelation.yaml": {
+            "interpolation": "all",
+            "filters": {
+                "mystreet1": {
+                    "ranges": [
+                        {
+                            "start": "1",
+                            "end": "6",
+                        },
+                    ],
+                },
+                "mystreet2": {
+                    "interpolation": "notall",
+                    "ranges": [
+                        {
+                            "start": "1",
+                            "end": "5",
+                        },
+                    ],
+                },
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("myrelation").unwrap();
+
+    let filters = relation.get_street_ranges().unwrap();
+
+    let mut expected_filters: HashMap<String, ranges::Ranges> = HashMap::new();
+    expected_filters.insert(
+        "mystreet1".into(),
+        ranges::Ranges::new(vec![ranges::Range::new(1, 6, "all")]),
+    );
+    expected_filters.insert(
+        "mystreet2".into(),
+        ranges::Ranges::new(vec![ranges::Range::new(1, 5, "notall")]),
+    );
+    assert_eq!(filters, expected_filters);
+    assert_eq!(
+        relation.get_config().get_street_is_even_odd("mystreet1"),
+        false
+    );
+    assert_eq!(
+        relation.get_config().get_street_is_even_odd("mystreet2"),
+        true
+    );
+}
+
 /// Tests Relation::get_street_ranges() error handling.
 #[test]
 fn test_relation_get_street_ranges_error() {
@@ -2004,6 +2205,63 @@ fn test_relation_get_missing_housenumbers_letter_suffix_normalize() {
     assert_eq!(housenumber_range_names, expected);
 }
 
+/// Tests Relation::get_missing_housenumbers(): edits of excluded users are treated as missing.
+#[test]
+fn test_relation_get_missing_housenumbers_excluded_users() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "myrelation": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+        "relation-myrelation.yaml": {
+            "excluded-users": ["baduser"],
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-myrelation.lst",
+                &ref_file,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Tűzkő utca', '1', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Tűzkő utca', '2', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('myrelation', '1', 'Tűzkő utca', '', '', '', '', '');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type, user) values ('myrelation', '1', 'Tűzkő utca', '1', '', '', '', '', '', '', '', '', '', 'node', 'gooduser');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type, user) values ('myrelation', '2', 'Tűzkő utca', '2', '', '', '', '', '', '', '', '', '', 'node', 'baduser');",
+        )
+        .unwrap();
+    }
+    let mut relations = Relations::new(&ctx).unwrap();
+    let mut relation = relations.get_relation("myrelation").unwrap();
+    relation.write_ref_housenumbers().unwrap();
+
+    let ongoing_streets = relation.get_missing_housenumbers().unwrap().ongoing_streets;
+
+    assert_eq!(ongoing_streets.len(), 1);
+    let numbers: Vec<_> = ongoing_streets[0]
+        .house_numbers
+        .iter()
+        .map(|i| i.get_number())
+        .collect();
+    // 2 is in OSM, but only from an excluded user.
+    assert_eq!(numbers, ["2"]);
+}
+
 /// Tests Relation::get_missing_housenumbers(): '42/A*' and '42/a' matches.
 #[test]
 fn test_relation_get_missing_housenumbers_letter_suffix_source_suffix() {
@@ -2161,9 +2419,9 @@ fn test_relation_get_missing_streets() {
     );
 }
 
-/// Tests Relation::get_additional_streets().
+/// Tests Relation::get_missing_streets(): street-match controls if the case is ignored.
 #[test]
-fn test_relation_get_additional_streets() {
+fn test_relation_get_missing_streets_casefold() {
     let mut ctx = context::tests::make_test_context().unwrap();
     {
         let ref_streets = ctx.get_ini().get_reference_street_path().unwrap();
@@ -2171,18 +2429,14 @@ fn test_relation_get_additional_streets() {
     }
     let yamls_cache = serde_json::json!({
         "relations.yaml": {
-            "gazdagret": {
-                "osmrelation": 42,
+            "exact": {
                 "refcounty": "01",
                 "refsettlement": "011",
             },
-        },
-        "relation-gazdagret.yaml": {
-            "osm-street-filters": [
-                "Second Only In OSM utca",
-            ],
-            "refstreets": {
-                "OSM Name 1": "Ref Name 1",
+            "casefold": {
+                "refcounty": "01",
+                "refsettlement": "011",
+                "street-match": "casefold",
             },
         },
     });
@@ -2196,59 +2450,151 @@ fn test_relation_get_additional_streets() {
     {
         let conn = ctx.get_database_connection().unwrap();
         conn.execute_batch(
-            "insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '1', 'Törökugrató utca', '1', '', '', '', '', '', '', '', '', '', 'node');
-             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '2', 'Törökugrató utca', '2', '', '', '', '', '', '', '', '', '', 'node');
-            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '3', 'Tűzkő utca', '9', '', '', '', '', '', '', '', '', '', 'node');
-            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '4', 'Tűzkő utca', '10', '', '', '', '', '', '', '', '', '', 'node');
-            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '5', 'OSM Name 1', '1', '', '', '', '', '', '', '', '', '', 'node');
-            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '6', 'OSM Name 1', '2', '', '', '', '', '', '', '', '', '', 'node');
-            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '7', 'Only In OSM utca', '1', '', '', '', '', '', '', '', '', '', 'node');
-            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '8', 'Second Only In OSM utca', '1', '', '', '', '', '', '', '', '', '', 'node');
-            insert into mtimes (page, last_modified) values ('housenumbers/gazdagret', '0');",
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('exact', '1', 'tűzkő Utca', '', '', '', '', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('casefold', '1', 'tűzkő Utca', '', '', '', '', '');",
         )
         .unwrap();
     }
     let mut relations = Relations::new(&ctx).unwrap();
-    let relation_name = "gazdagret";
-    let relation = relations.get_relation(relation_name).unwrap();
-    let only_in_osm = relation
-        .get_additional_streets(/*sorted_result=*/ true)
-        .unwrap();
 
-    assert_eq!(only_in_osm, [util::Street::from_string("Only In OSM utca")]);
+    let exact = relations.get_relation("exact").unwrap();
+    let (only_in_reference, in_both) = exact.get_missing_streets().unwrap();
+    assert_eq!(only_in_reference.contains(&"Tűzkő utca".to_string()), true);
+    assert_eq!(in_both.is_empty(), true);
 
-    // This is filtered out, even if it's OSM-only.
-    let osm_street_blacklist = relation.get_config().get_osm_street_filters();
-    assert_eq!(osm_street_blacklist, ["Second Only In OSM utca"]);
+    let casefold = relations.get_relation("casefold").unwrap();
+    let (only_in_reference, in_both) = casefold.get_missing_streets().unwrap();
+    assert_eq!(only_in_reference.contains(&"Tűzkő utca".to_string()), false);
+    assert_eq!(in_both, ["Tűzkő utca"]);
 }
 
-/// Tests Relation::get_additional_streets(): when the osm-street-filters key is missing.
+/// Tests Relation::get_missing_streets(): same-named reference streets in other refcounties are
+/// not used.
 #[test]
-fn test_relation_get_additional_streets_no_osm_street_filters() {
+fn test_relation_get_missing_streets_refcounty() {
     let mut ctx = context::tests::make_test_context().unwrap();
-    {
-        let conn = ctx.get_database_connection().unwrap();
-        conn.execute_batch(
-            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kővirág sor', '37/B', '');",
-         )
-         .unwrap();
-    }
     let yamls_cache = serde_json::json!({
         "relations.yaml": {
             "myrelation": {
-                "refcounty": "0",
-                "refsettlement": "0",
-                "osmrelation": 42,
+                "refcounty": "01",
+                "refsettlement": "011",
             },
         },
     });
     let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
-    let ref_file = context::tests::TestFileSystem::make_file();
     let files = context::tests::TestFileSystem::make_files(
         &ctx,
-        &[
-            ("data/yamls.cache", &yamls_cache_value),
-            (
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_streets (county_code, settlement_code, street) values ('01', '011', 'Kossuth utca');
+             insert into ref_streets (county_code, settlement_code, street) values ('02', '011', 'Kossuth utca');
+             insert into ref_streets (county_code, settlement_code, street) values ('02', '011', 'Petőfi utca');",
+        )
+        .unwrap();
+    }
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("myrelation").unwrap();
+
+    let (only_in_reference, in_both) = relation.get_missing_streets().unwrap();
+
+    // 'Kossuth utca' is only listed once and 'Petőfi utca' from the other refcounty is not used.
+    assert_eq!(only_in_reference, ["Kossuth utca"]);
+    assert_eq!(in_both.is_empty(), true);
+}
+
+/// Tests Relation::get_additional_streets().
+#[test]
+fn test_relation_get_additional_streets() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    {
+        let ref_streets = ctx.get_ini().get_reference_street_path().unwrap();
+        util::build_street_reference_index(&ctx, &ref_streets).unwrap();
+    }
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "osm-street-filters": [
+                "Second Only In OSM utca",
+            ],
+            "refstreets": {
+                "OSM Name 1": "Ref Name 1",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '1', 'Törökugrató utca', '1', '', '', '', '', '', '', '', '', '', 'node');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '2', 'Törökugrató utca', '2', '', '', '', '', '', '', '', '', '', 'node');
+            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '3', 'Tűzkő utca', '9', '', '', '', '', '', '', '', '', '', 'node');
+            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '4', 'Tűzkő utca', '10', '', '', '', '', '', '', '', '', '', 'node');
+            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '5', 'OSM Name 1', '1', '', '', '', '', '', '', '', '', '', 'node');
+            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '6', 'OSM Name 1', '2', '', '', '', '', '', '', '', '', '', 'node');
+            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '7', 'Only In OSM utca', '1', '', '', '', '', '', '', '', '', '', 'node');
+            insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '8', 'Second Only In OSM utca', '1', '', '', '', '', '', '', '', '', '', 'node');
+            insert into mtimes (page, last_modified) values ('housenumbers/gazdagret', '0');",
+        )
+        .unwrap();
+    }
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation_name = "gazdagret";
+    let relation = relations.get_relation(relation_name).unwrap();
+    let only_in_osm = relation
+        .get_additional_streets(/*sorted_result=*/ true)
+        .unwrap();
+
+    assert_eq!(only_in_osm, [util::Street::from_string("Only In OSM utca")]);
+
+    // This is filtered out, even if it's OSM-only.
+    let osm_street_blacklist = relation.get_config().get_osm_street_filters();
+    assert_eq!(osm_street_blacklist, ["Second Only In OSM utca"]);
+}
+
+/// Tests Relation::get_additional_streets(): when the osm-street-filters key is missing.
+#[test]
+fn test_relation_get_additional_streets_no_osm_street_filters() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kővirág sor', '37/B', '');",
+         )
+         .unwrap();
+    }
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "myrelation": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
                 "workdir/street-housenumbers-reference-myrelation.lst",
                 &ref_file,
             ),
@@ -3017,6 +3363,47 @@ fn test_relations_limit_to_refarea() {
     assert_eq!(relations.get_names(), expected_relation_names);
 }
 
+/// Tests Relations::get_relations_below_percent().
+#[test]
+fn test_relations_get_relations_below_percent() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "complete": {
+            },
+            "half": {
+            },
+            "nocoverage": {
+            },
+            "threshold": {
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+    for (name, coverage) in [
+        ("complete", "100.00"),
+        ("half", "50.00"),
+        ("threshold", "80.00"),
+    ] {
+        relations
+            .get_relation(name)
+            .unwrap()
+            .set_osm_housenumber_coverage(coverage)
+            .unwrap();
+    }
+
+    let actual = relations.get_relations_below_percent(80_f64).unwrap();
+
+    assert_eq!(actual, ["half", "nocoverage"]);
+}
+
 /// Tests RelationConfig::should_check_missing_streets().
 #[test]
 fn test_relation_config_should_check_missing_streets() {
@@ -3866,3 +4253,473 @@ fn test_relation_get_osm_housenumber_split() {
     assert_eq!(housenumbers[0].get_number(), "12/A");
     assert_eq!(housenumbers[1].get_number(), "12/B");
 }
+
+/// Tests read_yaml_with_includes(): a relation includes a shared filters fragment.
+#[test]
+fn test_read_yaml_with_includes() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let relation = context::tests::TestFileSystem::make_file();
+    relation
+        .borrow_mut()
+        .write_all(b"filters: !include filters-shared.yaml\n")
+        .unwrap();
+    let shared = context::tests::TestFileSystem::make_file();
+    shared
+        .borrow_mut()
+        .write_all(b"'Budaorsi ut':\n  invalid: ['7']\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/relation-myrelation.yaml", &relation),
+            ("data/filters-shared.yaml", &shared),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let data = read_yaml_with_includes(
+        &ctx,
+        &ctx.get_abspath("data"),
+        &ctx.get_abspath("data/relation-myrelation.yaml"),
+    )
+    .unwrap();
+
+    let relation: RelationDict = serde_yaml::from_str(&data).unwrap();
+    let filters = relation.filters.unwrap();
+    assert_eq!(
+        filters["Budaorsi ut"].invalid.as_ref().unwrap(),
+        &vec!["7".to_string()]
+    );
+}
+
+/// Tests read_yaml_with_includes(): an include cycle is an error, not an endless loop.
+#[test]
+fn test_read_yaml_with_includes_cycle() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let first = context::tests::TestFileSystem::make_file();
+    first
+        .borrow_mut()
+        .write_all(b"filters: !include second.yaml\n")
+        .unwrap();
+    let second = context::tests::TestFileSystem::make_file();
+    second
+        .borrow_mut()
+        .write_all(b"'Budaorsi ut': !include first.yaml\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/first.yaml", &first), ("data/second.yaml", &second)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let datadir = ctx.get_abspath("data");
+
+    let ret = read_yaml_with_includes(&ctx, &datadir, &format!("{datadir}/first.yaml"));
+
+    let err = ret.unwrap_err().to_string();
+    assert_eq!(
+        err,
+        format!(
+            "config error: include cycle: {datadir}/first.yaml -> {datadir}/second.yaml -> {datadir}/first.yaml"
+        )
+    );
+}
+
+/// Tests Relation::is_stale().
+#[test]
+fn test_relation_is_stale() {
+    let ctx = context::tests::make_test_context().unwrap();
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+    // No house numbers yet.
+    assert_eq!(relation.is_stale().unwrap(), false);
+
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        // 31 days before the 2020-05-10 of TestTime.
+        let modified = time::macros::datetime!(2020-04-09 0:00 UTC);
+        conn.execute(
+            "insert into mtimes (page, last_modified) values ('housenumbers/gazdagret', ?1)",
+            [modified.unix_timestamp_nanos().to_string()],
+        )
+        .unwrap();
+    }
+
+    assert_eq!(relation.is_stale().unwrap(), true);
+}
+
+/// Tests Relation::write_ref_housenumbers(): overlapping references don't produce duplicate lines.
+#[test]
+fn test_relation_write_ref_housenumbers_dedup() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "reference-housenumbers": [
+                "workdir/refs/hazszamok_1.tsv",
+                "workdir/refs/hazszamok_2.tsv",
+            ],
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let reference1 = context::tests::TestFileSystem::make_file();
+    reference1
+        .borrow_mut()
+        .write_all(
+            "COUNTY_CODE\tSETTLEMENT_CODE\tSTREET\tHOUSENUMBER\n\
+             01\t011\tTűzkő utca\t3\n\
+             01\t011\tTűzkő utca\t1\n\
+             01\t011\tTűzkő utca\t1\n"
+                .as_bytes(),
+        )
+        .unwrap();
+    let reference2 = context::tests::TestFileSystem::make_file();
+    reference2
+        .borrow_mut()
+        .write_all(
+            "COUNTY_CODE\tSETTLEMENT_CODE\tSTREET\tHOUSENUMBER\n\
+             01\t011\tTűzkő utca\t3\n\
+             01\t011\tTűzkő utca\t5\n"
+                .as_bytes(),
+        )
+        .unwrap();
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/refs/hazszamok_1.tsv", &reference1),
+            ("workdir/refs/hazszamok_2.tsv", &reference2),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_file,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', '');",
+        )
+        .unwrap();
+    }
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+
+    relation.write_ref_housenumbers().unwrap();
+
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&ref_file),
+        "Tűzkő utca\t1\t\nTűzkő utca\t3\t\nTűzkő utca\t5\t\n"
+    );
+}
+
+/// Tests Relation::write_ref_housenumbers(): the relation-specific reference-housenumbers key
+/// overrides the global reference.
+#[test]
+fn test_relation_write_ref_housenumbers_override() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "reference-housenumbers": "workdir/refs/hazszamok_detailed.tsv",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let reference = context::tests::TestFileSystem::make_file();
+    reference
+        .borrow_mut()
+        .write_all(
+            "COUNTY_CODE\tSETTLEMENT_CODE\tSTREET\tHOUSENUMBER\n\
+             01\t011\tTűzkő utca\t1\n\
+             01\t011\tTűzkő utca\t3\n\
+             01\t012\tTűzkő utca\t5\n\
+             01\t011\tOther utca\t7\n"
+                .as_bytes(),
+        )
+        .unwrap();
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/refs/hazszamok_detailed.tsv", &reference),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_file,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('01', '011', 'Tűzkő utca', '42', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', '');",
+        )
+        .unwrap();
+    }
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+
+    relation.write_ref_housenumbers().unwrap();
+
+    // 42 from the global reference is not used, 5 is in a different settlement.
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&ref_file),
+        "Tűzkő utca\t1\t\nTűzkő utca\t3\t\n"
+    );
+}
+
+/// Tests Relation::input_hash().
+#[test]
+fn test_relation_input_hash() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "refstreets": {
+                "OSM Name 1": "Ref Name 1",
+                "OSM Name 2": "Ref Name 2",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_housenumbers = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_housenumbers,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('budafok', '2', 'Vöröskúti határsor', '', '', '', '', '');",
+        )
+        .unwrap();
+    }
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+    let hash = relation.input_hash().unwrap();
+
+    // Stable when nothing changes, including the data of other relations.
+    assert_eq!(relation.input_hash().unwrap(), hash);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('budafok', '3', 'Kossuth Lajos utca', '', '', '', '', '');",
+        )
+        .unwrap();
+    }
+    assert_eq!(relation.input_hash().unwrap(), hash);
+
+    // Changes when the OSM data of the relation changes.
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "update osm_streets set name = 'Törökugrató utca' where relation = 'gazdagret';",
+        )
+        .unwrap();
+    }
+    let hash2 = relation.input_hash().unwrap();
+    assert_ne!(hash2, hash);
+
+    // Changes when the reference of the relation changes.
+    file_system
+        .write_from_string(
+            "Törökugrató utca\t1\t",
+            &ctx.get_abspath("workdir/street-housenumbers-reference-gazdagret.lst"),
+        )
+        .unwrap();
+    assert_ne!(relation.input_hash().unwrap(), hash2);
+}
+
+/// Tests Relation::get_street_ranges(): the interpolation falls back to the ini default.
+#[test]
+fn test_relation_get_street_ranges_default_interpolation() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "default_interpolation = 'all'\n").unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "myrelation": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-myrelation.yaml": {
+            "filters": {
+                "Tűzkő utca": {
+                    "ranges": [
+                        {
+                            "start": "1",
+                            "end": "5",
+                        },
+                    ],
+                },
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("myrelation").unwrap();
+
+    let street_ranges = relation.get_street_ranges().unwrap();
+
+    // Neither the range nor the relation has an interpolation, so 'all' from wsgi.ini is used.
+    assert_eq!(street_ranges["Tűzkő utca"].get_items()[0].contains(2), true);
+    assert_eq!(
+        relation.get_config().get_street_is_even_odd("Tűzkő utca"),
+        false
+    );
+}
+
+/// Tests Relation.get_osm_streets_query(): a relation with multiple osmrelation IDs.
+#[test]
+fn test_relation_get_osm_streets_query_multiple_osmrelations() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": [42, 43],
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    template_value
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)] [timeout:425];\nrel(@RELATION@); area(@AREA@);\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+
+    let ret = relation.get_osm_streets_query().unwrap();
+
+    assert_eq!(
+        ret,
+        "[out:csv(::id)] [timeout:425];\nrel(id:42,43); area(id:3600000042,3600000043);\n"
+    );
+    assert_eq!(relation.get_config().get_osmrelation(), 42);
+}
+
+/// File system which counts the open_read() calls, for test purposes.
+struct CountingFileSystem {
+    inner: Rc<dyn context::FileSystem>,
+    reads: Rc<RefCell<usize>>,
+}
+
+impl context::FileSystem for CountingFileSystem {
+    fn path_exists(&self, path: &str) -> bool {
+        self.inner.path_exists(path)
+    }
+
+    fn getmtime(&self, path: &str) -> anyhow::Result<time::OffsetDateTime> {
+        self.inner.getmtime(path)
+    }
+
+    fn open_read(&self, path: &str) -> anyhow::Result<Rc<RefCell<dyn Read>>> {
+        *self.reads.borrow_mut() += 1;
+        self.inner.open_read(path)
+    }
+
+    fn open_write(&self, path: &str) -> anyhow::Result<Rc<RefCell<dyn Write>>> {
+        self.inner.open_write(path)
+    }
+
+    fn unlink(&self, path: &str) -> anyhow::Result<()> {
+        self.inner.unlink(path)
+    }
+
+    fn listdir(&self, path: &str) -> anyhow::Result<Vec<String>> {
+        self.inner.listdir(path)
+    }
+
+    fn try_lock(&self, path: &str) -> anyhow::Result<Option<Box<dyn std::any::Any>>> {
+        self.inner.try_lock(path)
+    }
+
+    fn make_temp(&self, prefix: &str) -> anyhow::Result<String> {
+        self.inner.make_temp(prefix)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> anyhow::Result<()> {
+        self.inner.rename(from, to)
+    }
+}
+
+/// Tests Relations::get_relation(): repeated lookups don't read the YAML again.
+#[test]
+fn test_relations_get_relation_cached() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "myrelation": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-myrelation.yaml": {
+            "refcounty": "01",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let reads = Rc::new(RefCell::new(0));
+    let file_system: Rc<dyn context::FileSystem> = Rc::new(CountingFileSystem {
+        inner: context::tests::TestFileSystem::from_files(&files),
+        reads: reads.clone(),
+    });
+    ctx.set_file_system(&file_system);
+    let mut relations = Relations::new(&ctx).unwrap();
+
+    let relation1 = relations.get_relation("myrelation").unwrap();
+    let relation2 = relations.get_relation("myrelation").unwrap();
+
+    assert_eq!(*reads.borrow(), 1);
+    assert_eq!(relation1.get_config().get_refcounty(), "01");
+    assert_eq!(relation2.get_config().get_refcounty(), "01");
+}
@@ -12,7 +12,11 @@
 
 use crate::areas;
 use crate::context;
+use crate::i18n;
+use crate::i18n::translate as tr;
 use crate::stats;
+use crate::util;
+use crate::yattag;
 use anyhow::Context as _;
 
 /// Decides if we have an up to date cache entry or not.
@@ -133,10 +137,228 @@ pub fn get_missing_housenumbers_json(relation: &mut areas::Relation<'_>) -> anyh
     )?;
 
     relation.write_lints()?;
+    relation.write_unparseable_refs()?;
 
     Ok(output)
 }
 
+/// Estimates when the house number coverage of a relation reaches 100%, based on its history.
+fn get_completion_estimate(relation: &areas::Relation<'_>) -> anyhow::Result<Option<String>> {
+    let format = time::format_description::parse("[year]-[month]-[day]")?;
+    let mut series: Vec<(time::Date, f64)> = Vec::new();
+    for (date, coverage) in relation.get_osm_housenumber_coverage_history()? {
+        series.push((time::Date::parse(&date, &format)?, coverage.parse()?));
+    }
+    match stats::estimate_completion(&series) {
+        Some(date) => Ok(Some(date.format(&format)?)),
+        None => Ok(None),
+    }
+}
+
+/// Renders the missing house numbers HTML of a relation, based on the analysis in `json`.
+fn missing_housenumbers_html(
+    ctx: &context::Context,
+    relation: &areas::Relation<'_>,
+    json: &str,
+) -> anyhow::Result<yattag::Doc> {
+    let doc = yattag::Doc::new();
+    let (todo_street_count, todo_count, done_count, percent, table) = relation
+        .get_missing_housenumbers_stats(json)
+        .context("get_missing_housenumbers_stats() failed")?;
+
+    {
+        let p = doc.tag("p", &[]);
+        let prefix = ctx.get_ini().get_uri_prefix();
+        let relation_name = relation.get_name();
+        p.text(
+            &tr("OpenStreetMap is possibly missing the below {0} house numbers for {1} streets.")
+                .replace("{0}", &todo_count.to_string())
+                .replace("{1}", &todo_street_count.to_string()),
+        );
+        let percent = util::format_percent(percent).context("format_percent() failed")?;
+        p.text(
+            &tr(" (existing: {0}, ready: {1}).")
+                .replace("{0}", &done_count.to_string())
+                .replace("{1}", &percent),
+        );
+        if let Some(date) = get_completion_estimate(relation)? {
+            p.text(&tr(" At this rate, 100% is reached by {0}.").replace("{0}", &date));
+        }
+        doc.stag("br");
+        {
+            let a = doc.tag(
+                "a",
+                &[(
+                    "href",
+                    &tr("https://vmiklos.hu/osm-gimmisn/usage.html#filtering-out-incorrect-information"),
+                )],
+            );
+            a.text(&tr("Filter incorrect information"));
+        }
+        doc.text(".");
+        doc.stag("br");
+        {
+            let a = doc.tag(
+                "a",
+                &[(
+                    "href",
+                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-turbo"),
+                )],
+            );
+            a.text(&tr("Overpass turbo query for the below streets"));
+        }
+        doc.stag("br");
+        {
+            let a = doc.tag(
+                "a",
+                &[(
+                    "href",
+                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-result.txt"),
+                )],
+            );
+            a.text(&tr("Plain text format"));
+        }
+        doc.stag("br");
+        {
+            let a = doc.tag(
+                "a",
+                &[(
+                    "href",
+                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-result.chkl"),
+                )],
+            );
+            a.text(&tr("Checklist format"));
+        }
+        doc.stag("br");
+        {
+            let a = doc.tag(
+                "a",
+                &[(
+                    "href",
+                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-lints"),
+                )],
+            );
+            a.text(&tr("View lints"));
+        }
+        let unparseable_count = relation.get_unparseable_refs()?.len();
+        if unparseable_count > 0 {
+            doc.stag("br");
+            let a = doc.tag(
+                "a",
+                &[
+                    ("id", "unparseable-refs"),
+                    (
+                        "href",
+                        &format!("{prefix}/missing-housenumbers/{relation_name}/view-unparseable"),
+                    ),
+                ],
+            );
+            a.text(
+                &tr("{0} unparseable reference entries")
+                    .replace("{0}", &unparseable_count.to_string()),
+            );
+        }
+    }
+
+    doc.append_value(util::html_table_from_list(&table).get_value());
+    if let Ok((osm_invalids, ref_invalids)) = relation.get_invalid_refstreets() {
+        doc.append_value(
+            util::invalid_refstreets_to_html(&osm_invalids, &ref_invalids).get_value(),
+        );
+    }
+    doc.append_value(
+        util::invalid_filter_keys_to_html(&relation.get_invalid_filter_keys()?).get_value(),
+    );
+
+    Ok(doc)
+}
+
+/// Decides if we have an up to date HTML cache entry in the current language or not.
+fn is_missing_housenumbers_html_cached(relation: &mut areas::Relation<'_>) -> anyhow::Result<bool> {
+    if !is_missing_housenumbers_json_cached(relation)? {
+        return Ok(false);
+    }
+
+    let relation_name = relation.get_name();
+    let datadir = relation.get_ctx().get_abspath("data");
+    let dependencies = vec![
+        format!("{datadir}/relation-{relation_name}.yaml"),
+        relation.get_files().get_housenumbers_percent_log_path(),
+    ];
+    let sql_dependencies = vec![format!("missing-housenumbers-cache/{relation_name}")];
+    is_sql_cache_current(
+        relation.get_ctx(),
+        &format!(
+            "missing-housenumbers-html/{}/{relation_name}",
+            i18n::get_language()
+        ),
+        &dependencies,
+        &sql_dependencies,
+    )
+}
+
+/// Gets the cached HTML of the missing housenumbers for a relation, in the current language. With
+/// `html_only`, the HTML is always regenerated, but from the stored analysis, which is reused even
+/// if it's outdated.
+pub fn get_missing_housenumbers_html(
+    ctx: &context::Context,
+    relation: &mut areas::Relation<'_>,
+    html_only: bool,
+) -> anyhow::Result<yattag::Doc> {
+    let relation_name = relation.get_name();
+    let language = i18n::get_language();
+    let doc = yattag::Doc::new();
+    let json = if html_only {
+        stats::get_sql_json(ctx, "missing_housenumbers_cache", &relation_name)?
+    } else {
+        if is_missing_housenumbers_html_cached(relation)
+            .context("is_missing_housenumbers_html_cached() failed")?
+        {
+            let conn = ctx.get_database_connection()?;
+            let html: String = conn.query_row(
+                "select html from missing_housenumbers_html_cache where relation = ?1 and language = ?2",
+                [&relation_name, &language],
+                |row| row.get(0),
+            )?;
+            doc.append_value(html);
+            return Ok(doc);
+        }
+        get_missing_housenumbers_json(relation).context("get_missing_housenumbers_json() failed")?
+    };
+
+    doc.append_value(missing_housenumbers_html(ctx, relation, &json)?.get_value());
+    {
+        let conn = ctx.get_database_connection()?;
+        conn.execute(
+            r#"insert into missing_housenumbers_html_cache (relation, language, html) values (?1, ?2, ?3)
+                 on conflict(relation, language) do update set html = excluded.html"#,
+            [&relation_name, &language, &doc.get_value()],
+        )?;
+    }
+    stats::set_sql_mtime(
+        ctx,
+        &format!("missing-housenumbers-html/{language}/{relation_name}"),
+    )?;
+    Ok(doc)
+}
+
+/// Gets the languages which have a cached missing housenumbers HTML for a relation.
+pub fn get_missing_housenumbers_html_languages(
+    ctx: &context::Context,
+    relation_name: &str,
+) -> anyhow::Result<Vec<String>> {
+    let conn = ctx.get_database_connection()?;
+    let mut stmt = conn.prepare(
+        "select language from missing_housenumbers_html_cache where relation = ?1 order by language",
+    )?;
+    let mut rows = stmt.query([relation_name])?;
+    let mut ret: Vec<String> = Vec::new();
+    while let Some(row) = rows.next()? {
+        ret.push(row.get(0)?);
+    }
+    Ok(ret)
+}
+
 /// Decides if we have an up to date additional json cache entry or not.
 fn is_additional_housenumbers_json_cached(
     relation: &mut areas::Relation<'_>,
@@ -188,5 +410,44 @@ pub fn get_additional_housenumbers_json(
     Ok(output)
 }
 
+/// Removes the cached output of a relation, so it's regenerated on next access. Returns if there
+/// was anything to remove.
+pub fn invalidate_relation(relation: &areas::Relation<'_>) -> anyhow::Result<bool> {
+    let ctx = relation.get_ctx();
+    let relation_name = relation.get_name();
+    let mut removed = false;
+    {
+        let conn = ctx.get_database_connection()?;
+        conn.execute(
+            "delete from mtimes where page = ?1",
+            [format!("missing-housenumbers-cache/{relation_name}")],
+        )?;
+        let rows = conn.execute(
+            "delete from missing_housenumbers_cache where relation = ?1",
+            [&relation_name],
+        )?;
+        removed |= rows > 0;
+        conn.execute(
+            "delete from mtimes where page like ?1",
+            [format!("missing-housenumbers-html/%/{relation_name}")],
+        )?;
+        let rows = conn.execute(
+            "delete from missing_housenumbers_html_cache where relation = ?1",
+            [&relation_name],
+        )?;
+        removed |= rows > 0;
+    }
+
+    let jsoncache_path = relation
+        .get_files()
+        .get_additional_housenumbers_jsoncache_path();
+    if ctx.get_file_system().path_exists(&jsoncache_path) {
+        ctx.get_file_system().unlink(&jsoncache_path)?;
+        removed = true;
+    }
+
+    Ok(removed)
+}
+
 #[cfg(test)]
 mod tests;
@@ -12,6 +12,7 @@
 
 use crate::areas;
 use crate::context;
+use crate::error::OsmGimmisnError;
 use anyhow::Context;
 use std::collections::HashMap;
 use std::io::Write;
@@ -37,9 +38,12 @@ pub fn our_main(argv: &[String], ctx: &context::Context) -> anyhow::Result<()> {
             .strip_prefix(&format!("{datadir}/"))
             .context("yaml outside datadir")?
             .to_string();
-        let data = ctx.get_file_system().read_to_string(&yaml_path)?;
-        let cache_value = serde_yaml::from_str::<serde_json::Value>(&data)
-            .context(format!("serde_yaml::from_str() failed for {yaml_path}"))?;
+        let data = areas::read_yaml_with_includes(ctx, &datadir, &yaml_path)?;
+        let cache_value = serde_yaml::from_str::<serde_json::Value>(&data).map_err(|err| {
+            OsmGimmisnError::Config(format!(
+                "serde_yaml::from_str() failed for {yaml_path}: {err}"
+            ))
+        })?;
         cache.insert(cache_key, cache_value);
     }
 
@@ -55,10 +59,13 @@ pub fn our_main(argv: &[String], ctx: &context::Context) -> anyhow::Result<()> {
     let yaml_path = format!("{datadir}/relations.yaml");
     let mut relation_ids: Vec<u64> = Vec::new();
     let data = ctx.get_file_system().read_to_string(&yaml_path)?;
-    let relations: areas::RelationsDict = serde_yaml::from_str(&data)
-        .context(format!("serde_yaml::from_str() failed for {yaml_path}"))?;
+    let relations: areas::RelationsDict = serde_yaml::from_str(&data).map_err(|err| {
+        OsmGimmisnError::Config(format!(
+            "serde_yaml::from_str() failed for {yaml_path}: {err}"
+        ))
+    })?;
     for (_key, value) in relations {
-        relation_ids.push(value.osmrelation.context("no osmrelation")?);
+        relation_ids.extend(value.osmrelation.context("no osmrelation")?.get_ids());
     }
     relation_ids.sort_unstable();
     relation_ids.dedup();
@@ -110,3 +110,29 @@ fn test_main_error() {
 
     assert_eq!(ret, 1);
 }
+
+/// Tests our_main(): a malformed YAML file is a config error.
+#[test]
+fn test_our_main_invalid_yaml() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv = vec!["".to_string(), "data".to_string(), "workdir".to_string()];
+    let relations_value = context::tests::TestFileSystem::make_file();
+    relations_value
+        .borrow_mut()
+        .write_all(b"gazdagret: [\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/relations.yaml", &relations_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = our_main(&argv, &ctx);
+
+    let err = ret.err().unwrap();
+    assert!(matches!(
+        err.downcast_ref::<OsmGimmisnError>(),
+        Some(OsmGimmisnError::Config(_))
+    ));
+}
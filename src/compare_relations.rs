@@ -0,0 +1,144 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Compares the OSM streets and house numbers of two relations, to help splitting or merging them.
+
+use crate::areas;
+use crate::context;
+use std::collections::BTreeSet;
+use std::io::Write;
+
+/// The result of comparing two sets: items in both, only in the first and only in the second.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Overlap<T> {
+    /// Items present in both sets.
+    pub both: Vec<T>,
+    /// Items present only in the first set.
+    pub only_a: Vec<T>,
+    /// Items present only in the second set.
+    pub only_b: Vec<T>,
+}
+
+impl<T: Clone + Ord> Overlap<T> {
+    /// Splits the two sets into the three partitions.
+    fn new(a: &BTreeSet<T>, b: &BTreeSet<T>) -> Self {
+        Overlap {
+            both: a.intersection(b).cloned().collect(),
+            only_a: a.difference(b).cloned().collect(),
+            only_b: b.difference(a).cloned().collect(),
+        }
+    }
+}
+
+/// Gets the names of the OSM streets of a relation.
+fn get_street_names(relation: &areas::Relation<'_>) -> anyhow::Result<BTreeSet<String>> {
+    Ok(relation
+        .get_osm_streets(/*sorted_result=*/ false)?
+        .iter()
+        .map(|i| i.get_osm_name().to_string())
+        .collect())
+}
+
+/// Gets the (street name, house number) pairs of the OSM house numbers of a relation.
+fn get_housenumbers(
+    relation: &mut areas::Relation<'_>,
+) -> anyhow::Result<BTreeSet<(String, String)>> {
+    let mut ret = BTreeSet::new();
+    for (street, house_numbers) in relation.get_osm_housenumbers_by_street()? {
+        for house_number in house_numbers {
+            ret.insert((street.to_string(), house_number.get_number().to_string()));
+        }
+    }
+    Ok(ret)
+}
+
+/// Compares the OSM streets of two relations.
+pub fn compare_streets(
+    a: &areas::Relation<'_>,
+    b: &areas::Relation<'_>,
+) -> anyhow::Result<Overlap<String>> {
+    Ok(Overlap::new(&get_street_names(a)?, &get_street_names(b)?))
+}
+
+/// Compares the OSM house numbers of two relations.
+pub fn compare_housenumbers(
+    a: &mut areas::Relation<'_>,
+    b: &mut areas::Relation<'_>,
+) -> anyhow::Result<Overlap<(String, String)>> {
+    Ok(Overlap::new(&get_housenumbers(a)?, &get_housenumbers(b)?))
+}
+
+/// Inner main() that is allowed to fail.
+pub fn our_main(
+    argv: &[String],
+    stream: &mut dyn Write,
+    ctx: &context::Context,
+) -> anyhow::Result<()> {
+    let relation_a = clap::Arg::new("relation-a")
+        .required(true)
+        .help("name of the first relation");
+    let relation_b = clap::Arg::new("relation-b")
+        .required(true)
+        .help("name of the second relation");
+    let args = [relation_a, relation_b];
+    let app = clap::Command::new("osm-gimmisn")
+        .override_usage("osm-gimmisn compare <relation-a> <relation-b>");
+    let args = app.args(&args).try_get_matches_from(argv)?;
+    let relation_a: &String = args.get_one("relation-a").unwrap();
+    let relation_b: &String = args.get_one("relation-b").unwrap();
+
+    let mut relations = areas::Relations::new(ctx)?;
+    for name in [relation_a, relation_b] {
+        if !relations.get_names().contains(name) {
+            return Err(anyhow::anyhow!("no such relation: '{name}'"));
+        }
+    }
+    let mut a = relations.get_relation(relation_a)?;
+    let mut b = relations.get_relation(relation_b)?;
+
+    let streets = compare_streets(&a, &b)?;
+    for (label, items) in [
+        ("both", &streets.both),
+        ("only-a", &streets.only_a),
+        ("only-b", &streets.only_b),
+    ] {
+        for street in items {
+            stream.write_all(format!("street\t{label}\t{street}\n").as_bytes())?;
+        }
+    }
+
+    let housenumbers = compare_housenumbers(&mut a, &mut b)?;
+    for (label, items) in [
+        ("both", &housenumbers.both),
+        ("only-a", &housenumbers.only_a),
+        ("only-b", &housenumbers.only_b),
+    ] {
+        for (street, housenumber) in items {
+            stream
+                .write_all(format!("housenumber\t{label}\t{street}\t{housenumber}\n").as_bytes())?;
+        }
+    }
+
+    ctx.get_unit().make_error()
+}
+
+/// Similar to plain main(), but with an interface that allows testing.
+pub fn main(argv: &[String], stream: &mut dyn Write, ctx: &context::Context) -> i32 {
+    match our_main(argv, stream, ctx) {
+        Ok(_) => 0,
+        Err(err) => {
+            stream.write_all(format!("{err:?}\n").as_bytes()).unwrap();
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
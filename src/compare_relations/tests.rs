@@ -0,0 +1,120 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Tests for the compare_relations module.
+
+use super::*;
+use std::io::Read;
+use std::io::Seek;
+
+/// Creates a context with two overlapping relations: 'a' and 'b'.
+fn make_test_context() -> context::Context {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "a": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+            "b": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 43,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('a', '1', 'Common utca', 'residential', '', '', '', 'way');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('a', '2', 'West utca', 'residential', '', '', '', 'way');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('b', '3', 'Common utca', 'residential', '', '', '', 'way');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('b', '4', 'East utca', 'residential', '', '', '', 'way');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('a', '11', 'Common utca', '1', '', '', '', '', '', '', '', '', '', 'node');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('a', '12', 'Common utca', '3', '', '', '', '', '', '', '', '', '', 'node');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('b', '13', 'Common utca', '3', '', '', '', '', '', '', '', '', '', 'node');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('b', '14', 'Common utca', '5', '', '', '', '', '', '', '', '', '', 'node');",
+        )
+        .unwrap();
+    }
+    ctx
+}
+
+/// Tests compare_streets() and compare_housenumbers().
+#[test]
+fn test_compare() {
+    let ctx = make_test_context();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    let mut a = relations.get_relation("a").unwrap();
+    let mut b = relations.get_relation("b").unwrap();
+
+    let streets = compare_streets(&a, &b).unwrap();
+    let housenumbers = compare_housenumbers(&mut a, &mut b).unwrap();
+
+    assert_eq!(streets.both, vec!["Common utca".to_string()]);
+    assert_eq!(streets.only_a, vec!["West utca".to_string()]);
+    assert_eq!(streets.only_b, vec!["East utca".to_string()]);
+    assert_eq!(
+        housenumbers.both,
+        vec![("Common utca".to_string(), "3".to_string())]
+    );
+    assert_eq!(
+        housenumbers.only_a,
+        vec![("Common utca".to_string(), "1".to_string())]
+    );
+    assert_eq!(
+        housenumbers.only_b,
+        vec![("Common utca".to_string(), "5".to_string())]
+    );
+}
+
+/// Tests main().
+#[test]
+fn test_main() {
+    let argv = vec!["".to_string(), "a".to_string(), "b".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let ctx = make_test_context();
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "street\tboth\tCommon utca\n\
+         street\tonly-a\tWest utca\n\
+         street\tonly-b\tEast utca\n\
+         housenumber\tboth\tCommon utca\t3\n\
+         housenumber\tonly-a\tCommon utca\t1\n\
+         housenumber\tonly-b\tCommon utca\t5\n"
+    );
+}
+
+/// Tests main(), when one of the relations doesn't exist.
+#[test]
+fn test_main_no_such_relation() {
+    let argv = vec!["".to_string(), "a".to_string(), "c".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let ctx = make_test_context();
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 1);
+}
@@ -20,6 +20,7 @@ use std::path::Path;
 use std::rc::Rc;
 use std::time::Duration;
 
+use crate::error::OsmGimmisnError;
 use crate::sql;
 
 /// File system interface.
@@ -42,6 +43,23 @@ pub trait FileSystem {
     /// Return a list containing the names of the files in the directory.
     fn listdir(&self, path: &str) -> anyhow::Result<Vec<String>>;
 
+    /// Tries to take an exclusive lock on a file, creating it if needed. Returns None if the lock
+    /// is already held, otherwise the lock is released when the returned guard is dropped.
+    fn try_lock(&self, path: &str) -> anyhow::Result<Option<Box<dyn std::any::Any>>>;
+
+    /// Creates a new, empty temporary file next to `prefix` and returns its path.
+    fn make_temp(&self, prefix: &str) -> anyhow::Result<String>;
+
+    /// Renames `from` to `to`, replacing `to` if it already exists.
+    fn rename(&self, from: &str, to: &str) -> anyhow::Result<()>;
+
+    /// Returns the size of a file in bytes.
+    fn file_size(&self, path: &str) -> anyhow::Result<u64> {
+        let stream = self.open_read(path)?;
+        let mut guard = stream.borrow_mut();
+        Ok(std::io::copy(&mut *guard, &mut std::io::sink())?)
+    }
+
     /// Read the entire contents of a file into a string.
     fn read_to_string(&self, path: &str) -> anyhow::Result<String> {
         let stream = self.open_read(path)?;
@@ -53,9 +71,30 @@ pub trait FileSystem {
 
     /// Write the entire string to a file.
     fn write_from_string(&self, string: &str, path: &str) -> anyhow::Result<()> {
+        self.write_from_bytes(string.as_bytes(), path)
+    }
+
+    /// Write the entire byte buffer to a file.
+    fn write_from_bytes(&self, bytes: &[u8], path: &str) -> anyhow::Result<()> {
         let stream = self.open_write(path)?;
         let mut guard = stream.borrow_mut();
-        Ok(guard.write_all(string.as_bytes())?)
+        Ok(guard.write_all(bytes)?)
+    }
+
+    /// Write the entire string to a temporary file, then rename it to `path`, so readers never
+    /// see a half-written file.
+    fn write_from_string_atomic(&self, string: &str, path: &str) -> anyhow::Result<()> {
+        self.write_from_bytes_atomic(string.as_bytes(), path)
+    }
+
+    /// Same as write_from_string_atomic(), but for a byte buffer.
+    fn write_from_bytes_atomic(&self, bytes: &[u8], path: &str) -> anyhow::Result<()> {
+        let temp = self.make_temp(path)?;
+        if let Err(err) = self.write_from_bytes(bytes, &temp) {
+            let _ = self.unlink(&temp);
+            return Err(err);
+        }
+        self.rename(&temp, path)
     }
 }
 
@@ -79,7 +118,10 @@ pub use system::StdDatabase;
 /// Network interface.
 pub trait Network {
     /// Opens an URL. Empty data means HTTP GET, otherwise it means a HTTP POST.
-    fn urlopen(&self, url: &str, data: &str) -> anyhow::Result<String>;
+    fn urlopen(&self, url: &str, data: &str) -> Result<String, OsmGimmisnError>;
+
+    /// Downloads an URL with HTTP GET, as-is: no decompression and no decoding.
+    fn download(&self, url: &str) -> Result<Vec<u8>, OsmGimmisnError>;
 }
 
 pub use system::StdNetwork;
@@ -118,6 +160,14 @@ pub trait Subprocess {
 
 pub use system::StdSubprocess;
 
+/// Random number interface.
+pub trait Random {
+    /// Returns a random number in the [0, 1) range.
+    fn next_f64(&self) -> f64;
+}
+
+pub use system::StdRandom;
+
 /// Unit testing interface.
 pub trait Unit {
     /// Injects a fake error.
@@ -127,32 +177,109 @@ pub trait Unit {
 pub use system::StdUnit;
 
 /// The root of the workdir/wsgi.ini config file.
-#[derive(Default, serde::Deserialize)]
+#[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct IniConfig {
     /// The wsgi section in the config file.
     pub wsgi: WsgiConfig,
 }
 
 /// The wsgi section in the config file.
-#[derive(Default, serde::Deserialize)]
+#[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct WsgiConfig {
     /// Space-separated list of housenumber references.
+    #[serde(default)]
     pub reference_housenumbers: String,
     /// Street reference file path.
+    #[serde(default)]
     pub reference_street: String,
     /// City counts reference file path.
+    #[serde(default)]
     pub reference_citycounts: String,
     /// ZIP counts reference file path.
+    #[serde(default)]
     pub reference_zipcounts: String,
     uri_prefix: Option<String>,
     tcp_port: Option<String>,
     overpass_uri: Option<String>,
     cron_update_inactive: Option<String>,
+    stats_daily_artifact_retention_days: Option<String>,
+    workdir: Option<String>,
+    user_agent: Option<String>,
+    overpass_max_sleep: Option<String>,
+    stale_days: Option<String>,
+    cron_inactive_refresh_day: Option<String>,
+    site_name: Option<String>,
+    log_color: Option<String>,
+    overpass_global_failure_limit: Option<String>,
+    update_rate_per_minute: Option<String>,
+    static_content_types: Option<String>,
+    debug_enabled: Option<String>,
+    overpass_sleep_jitter: Option<String>,
+    robots_path: Option<String>,
+    default_interpolation: Option<String>,
+    connect_timeout: Option<String>,
+    read_timeout: Option<String>,
+    min_response_bytes: Option<String>,
+    regression_threshold: Option<String>,
+    max_redirects: Option<String>,
+    anonymize_users: Option<String>,
+    anonymize_users_secret: Option<String>,
+    reference_housenumbers_url: Option<String>,
+    reference_street_url: Option<String>,
+    reference_citycounts_url: Option<String>,
+    reference_zipcounts_url: Option<String>,
+    reference_ttl_hours: Option<String>,
+}
+
+/// The keys of the wsgi section with their documented defaults, which are also the fallbacks of
+/// the Ini getters. The reference paths have no useful default, they are empty. An empty workdir
+/// or user_agent means the default is derived at runtime.
+const INI_DEFAULTS: &[(&str, &str)] = &[
+    ("reference_housenumbers", ""),
+    ("reference_street", ""),
+    ("reference_citycounts", ""),
+    ("reference_zipcounts", ""),
+    ("uri_prefix", "/osm"),
+    ("tcp_port", "8000"),
+    ("overpass_uri", "https://overpass-api.de"),
+    ("cron_update_inactive", "False"),
+    ("stats_daily_artifact_retention_days", "0"),
+    ("overpass_max_sleep", "600"),
+    ("stale_days", "30"),
+    ("cron_inactive_refresh_day", "1"),
+    ("site_name", ""),
+    ("log_color", "never"),
+    ("overpass_global_failure_limit", "100"),
+    ("update_rate_per_minute", "10"),
+    ("static_content_types", ""),
+    ("debug_enabled", "False"),
+    ("overpass_sleep_jitter", "0"),
+    ("robots_path", "data/robots.txt"),
+    ("default_interpolation", ""),
+    ("connect_timeout", "30"),
+    ("read_timeout", "425"),
+    ("min_response_bytes", "100"),
+    ("regression_threshold", "1.0"),
+    ("max_redirects", "5"),
+    ("anonymize_users", "False"),
+    ("anonymize_users_secret", ""),
+    ("reference_ttl_hours", "24"),
+    ("workdir", ""),
+    ("user_agent", ""),
+];
+
+/// Gets the default of a wsgi key from INI_DEFAULTS.
+fn get_default(key: &str) -> &'static str {
+    match INI_DEFAULTS.iter().find(|i| i.0 == key) {
+        Some((_, value)) => value,
+        None => "",
+    }
 }
 
 /// Configuration file reader.
 pub struct Ini {
     config: IniConfig,
+    config_path: String,
     root: String,
 }
 
@@ -164,49 +291,130 @@ impl Ini {
     ) -> anyhow::Result<Self> {
         let mut config = IniConfig::default();
         if let Ok(data) = file_system.read_to_string(config_path) {
-            config = toml::from_str(&data)?;
+            config = toml::from_str(&data).map_err(|err| {
+                OsmGimmisnError::Config(format!("failed to parse {config_path}: {err}"))
+            })?;
         }
         Ok(Ini {
             config,
+            config_path: String::from(config_path),
             root: String::from(root),
         })
     }
 
-    /// Gets the directory which is writable.
+    /// Gets the keys of the wsgi section which have a default, with their default values.
+    pub fn get_defaults() -> &'static [(&'static str, &'static str)] {
+        INI_DEFAULTS
+    }
+
+    /// Gets the path of the config file itself.
+    pub fn get_config_path(&self) -> String {
+        self.config_path.to_string()
+    }
+
+    /// Gets the directory which is writable: wsgi.workdir, a relative value is resolved against the
+    /// root, defaults to {root}/workdir.
     pub fn get_workdir(&self) -> String {
-        format!("{}/workdir", self.root)
+        match &self.config.wsgi.workdir {
+            Some(value) if value.is_empty() => format!("{}/workdir", self.root),
+            Some(value) if Path::new(value).is_absolute() => value.to_string(),
+            Some(value) => format!("{}/{}", self.root, value),
+            None => format!("{}/workdir", self.root),
+        }
+    }
+
+    /// Make a path absolute, taking the repo root as a base dir, except for workdir/ paths.
+    pub fn get_abspath(&self, rel_path: &str) -> String {
+        if let Some(value) = rel_path.strip_prefix("workdir/") {
+            return format!("{}/{}", self.get_workdir(), value);
+        }
+
+        format!("{}/{}", self.root, rel_path)
     }
 
     /// Gets the abs paths of ref housenumbers.
     pub fn get_reference_housenumber_paths(&self) -> anyhow::Result<Vec<String>> {
         let value = &self.config.wsgi.reference_housenumbers;
         let relpaths = value.split(' ');
-        Ok(relpaths
-            .map(|relpath| format!("{}/{}", self.root, relpath))
-            .collect())
+        Ok(relpaths.map(|relpath| self.get_abspath(relpath)).collect())
     }
 
     /// Gets the abs path of ref streets.
     pub fn get_reference_street_path(&self) -> anyhow::Result<String> {
         let relpath = &self.config.wsgi.reference_street;
-        Ok(format!("{}/{}", self.root, relpath))
+        Ok(self.get_abspath(relpath))
     }
 
     /// Gets the abs path of ref citycounts.
     pub fn get_reference_citycounts_path(&self) -> anyhow::Result<String> {
         let relpath = &self.config.wsgi.reference_citycounts;
-        Ok(format!("{}/{}", self.root, relpath))
+        Ok(self.get_abspath(relpath))
     }
 
     /// Gets the abs path of ref zipcounts.
     pub fn get_reference_zipcounts_path(&self) -> anyhow::Result<String> {
         let relpath = &self.config.wsgi.reference_zipcounts;
-        Ok(format!("{}/{}", self.root, relpath))
+        Ok(self.get_abspath(relpath))
+    }
+
+    /// Gets the space-separated download URLs of ref housenumbers, in the order of the paths.
+    pub fn get_reference_housenumbers_urls(&self) -> Vec<String> {
+        match &self.config.wsgi.reference_housenumbers_url {
+            Some(value) => value.split_whitespace().map(|i| i.to_string()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Gets the download URL of ref streets.
+    pub fn get_reference_street_url(&self) -> Option<String> {
+        self.config.wsgi.reference_street_url.clone()
+    }
+
+    /// Gets the download URL of ref citycounts.
+    pub fn get_reference_citycounts_url(&self) -> Option<String> {
+        self.config.wsgi.reference_citycounts_url.clone()
+    }
+
+    /// Gets the download URL of ref zipcounts.
+    pub fn get_reference_zipcounts_url(&self) -> Option<String> {
+        self.config.wsgi.reference_zipcounts_url.clone()
+    }
+
+    /// Gets how old a downloaded reference file may be before it's downloaded again, in hours.
+    pub fn get_reference_ttl_hours(&self) -> anyhow::Result<i64> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.reference_ttl_hours,
+                get_default("reference_ttl_hours"),
+            )
+            .parse::<i64>()?)
     }
 
     /// Gets the global URI prefix.
     pub fn get_uri_prefix(&self) -> String {
-        self.get_with_fallback(&self.config.wsgi.uri_prefix, "/osm")
+        self.get_with_fallback(&self.config.wsgi.uri_prefix, get_default("uri_prefix"))
+    }
+
+    /// Gets if terminal logging should use colors: 'auto', 'always' or 'never'.
+    pub fn get_log_color(&self) -> anyhow::Result<simplelog::ColorChoice> {
+        let value = self.get_with_fallback(&self.config.wsgi.log_color, get_default("log_color"));
+        match value.as_str() {
+            "auto" => Ok(simplelog::ColorChoice::Auto),
+            "always" => Ok(simplelog::ColorChoice::Always),
+            "never" => Ok(simplelog::ColorChoice::Never),
+            _ => Err(anyhow::anyhow!(
+                "log_color should be 'auto', 'always' or 'never', got '{value}'"
+            )),
+        }
+    }
+
+    /// Gets the site name, shown in the page header and title, if it's set.
+    pub fn get_site_name(&self) -> Option<String> {
+        let value = self.get_with_fallback(&self.config.wsgi.site_name, get_default("site_name"));
+        if value.is_empty() {
+            return None;
+        }
+        Some(value)
     }
 
     fn get_with_fallback(&self, option: &Option<String>, fallback: &str) -> String {
@@ -219,65 +427,326 @@ impl Ini {
     /// Gets the TCP port to be used.
     pub fn get_tcp_port(&self) -> anyhow::Result<i64> {
         Ok(self
-            .get_with_fallback(&self.config.wsgi.tcp_port, "8000")
+            .get_with_fallback(&self.config.wsgi.tcp_port, get_default("tcp_port"))
             .parse::<i64>()?)
     }
 
     /// Gets the URI of the overpass instance to be used.
     pub fn get_overpass_uri(&self) -> String {
-        self.get_with_fallback(&self.config.wsgi.overpass_uri, "https://overpass-api.de")
+        self.get_with_fallback(&self.config.wsgi.overpass_uri, get_default("overpass_uri"))
+    }
+
+    /// Gets the maximum number of seconds to wait for overpass in one go, 0 means no limit.
+    pub fn get_overpass_max_sleep(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.overpass_max_sleep,
+                get_default("overpass_max_sleep"),
+            )
+            .parse::<u64>()?)
+    }
+
+    /// Gets the fraction of random jitter added to overpass sleeps, e.g. 0.1 means +/- 10%.
+    pub fn get_overpass_sleep_jitter(&self) -> anyhow::Result<f64> {
+        let value = self.get_with_fallback(
+            &self.config.wsgi.overpass_sleep_jitter,
+            get_default("overpass_sleep_jitter"),
+        );
+        let jitter = value.parse::<f64>()?;
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(anyhow::anyhow!(
+                "overpass_sleep_jitter should be between 0 and 1, got {jitter}"
+            ));
+        }
+        Ok(jitter)
+    }
+
+    /// Gets the interpolation of ranges for relations which don't specify one: 'all' or '', which
+    /// means even or odd numbers, depending on the start of the range.
+    pub fn get_default_interpolation(&self) -> anyhow::Result<String> {
+        let value = self.get_with_fallback(
+            &self.config.wsgi.default_interpolation,
+            get_default("default_interpolation"),
+        );
+        if !value.is_empty() && value != "all" {
+            return Err(anyhow::anyhow!(
+                "default_interpolation should be 'all' or '', got '{value}'"
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Gets how long to wait for a network connection to be established, in seconds.
+    pub fn get_connect_timeout_seconds(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.connect_timeout,
+                get_default("connect_timeout"),
+            )
+            .parse::<u64>()?)
+    }
+
+    /// Gets how long a whole network transfer may take, in seconds. The default matches the
+    /// [timeout:425] of our Overpass queries.
+    pub fn get_read_timeout_seconds(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .get_with_fallback(&self.config.wsgi.read_timeout, get_default("read_timeout"))
+            .parse::<u64>()?)
+    }
+
+    /// Gets the minimal size of an Overpass response: smaller ones are considered truncated.
+    pub fn get_min_response_bytes(&self) -> anyhow::Result<usize> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.min_response_bytes,
+                get_default("min_response_bytes"),
+            )
+            .parse::<usize>()?)
+    }
+
+    /// Gets how many percentage points the house number coverage of a relation has to drop between
+    /// two cron runs to be recorded as a regression.
+    pub fn get_regression_threshold(&self) -> anyhow::Result<f64> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.regression_threshold,
+                get_default("regression_threshold"),
+            )
+            .parse::<f64>()?)
+    }
+
+    /// Gets how many HTTP redirects to follow at most, 0 means no redirects are followed.
+    pub fn get_max_redirects(&self) -> anyhow::Result<u32> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.max_redirects,
+                get_default("max_redirects"),
+            )
+            .parse::<u32>()?)
+    }
+
+    /// Gets the number of consecutive overpass failures across relations, after which a cron phase
+    /// is aborted, 0 means no limit.
+    pub fn get_overpass_global_failure_limit(&self) -> anyhow::Result<u32> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.overpass_global_failure_limit,
+                get_default("overpass_global_failure_limit"),
+            )
+            .parse::<u32>()?)
+    }
+
+    /// Gets how many times per minute visitors can trigger an Overpass update, 0 means no limit.
+    pub fn get_update_rate_per_minute(&self) -> anyhow::Result<u32> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.update_rate_per_minute,
+                get_default("update_rate_per_minute"),
+            )
+            .parse::<u32>()?)
+    }
+
+    /// Gets extra extension -> content type pairs for static files, from a comma-separated
+    /// 'wasm=application/wasm' list.
+    pub fn get_static_content_types(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let value = self.get_with_fallback(
+            &self.config.wsgi.static_content_types,
+            get_default("static_content_types"),
+        );
+        let mut ret: Vec<(String, String)> = Vec::new();
+        for item in value.split(',').map(|i| i.trim()).filter(|i| !i.is_empty()) {
+            let (extension, content_type) = item.split_once('=').context(format!(
+                "static_content_types: expected ext=type, got '{item}'"
+            ))?;
+            ret.push((extension.trim().into(), content_type.trim().into()));
+        }
+        Ok(ret)
+    }
+
+    /// Gets the number of days after which the OSM data of a relation is considered stale.
+    pub fn get_stale_days(&self) -> anyhow::Result<i64> {
+        Ok(self
+            .get_with_fallback(&self.config.wsgi.stale_days, get_default("stale_days"))
+            .parse::<i64>()?)
+    }
+
+    /// Gets the abs path of robots.txt.
+    pub fn get_robots_path(&self) -> String {
+        let relpath =
+            self.get_with_fallback(&self.config.wsgi.robots_path, get_default("robots_path"));
+        self.get_abspath(&relpath)
+    }
+
+    /// Should the debug pages (e.g. /debug/config) be served?
+    pub fn get_debug_enabled(&self) -> bool {
+        let value = self.get_with_fallback(
+            &self.config.wsgi.debug_enabled,
+            get_default("debug_enabled"),
+        );
+        value == "True"
+    }
+
+    /// Gets the keys and values of the wsgi section which are set, with credentials redacted.
+    pub fn get_redacted_values(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let mut ret: Vec<(String, String)> = Vec::new();
+        let value = serde_json::to_value(&self.config.wsgi)?;
+        let object = value.as_object().context("wsgi section is not an object")?;
+        for (key, value) in object {
+            let value = match value.as_str() {
+                Some(value) => value,
+                None => continue,
+            };
+            let secret = ["password", "secret", "token", "credential"]
+                .iter()
+                .any(|i| key.contains(i));
+            if secret {
+                ret.push((key.into(), "***".into()));
+                continue;
+            }
+            match url::Url::parse(value) {
+                Ok(mut url) if url.password().is_some() => {
+                    // Not fatal if this fails, then the whole value is redacted.
+                    if url.set_password(Some("***")).is_err() {
+                        ret.push((key.into(), "***".into()));
+                        continue;
+                    }
+                    ret.push((key.into(), url.to_string()));
+                }
+                _ => ret.push((key.into(), value.into())),
+            }
+        }
+        Ok(ret)
     }
 
     /// Should the cron job update inactive relations?
     pub fn get_cron_update_inactive(&self) -> bool {
-        let value = self.get_with_fallback(&self.config.wsgi.cron_update_inactive, "False");
+        let value = self.get_with_fallback(
+            &self.config.wsgi.cron_update_inactive,
+            get_default("cron_update_inactive"),
+        );
+        value == "True"
+    }
+
+    /// Should user names be replaced with salted hashes in the stats?
+    pub fn get_anonymize_users(&self) -> bool {
+        let value = self.get_with_fallback(
+            &self.config.wsgi.anonymize_users,
+            get_default("anonymize_users"),
+        );
         value == "True"
     }
+
+    /// Gets the salt which is used when anonymizing user names.
+    pub fn get_anonymize_users_secret(&self) -> String {
+        self.get_with_fallback(
+            &self.config.wsgi.anonymize_users_secret,
+            get_default("anonymize_users_secret"),
+        )
+    }
+
+    /// Gets the day of the month when the cron job updates inactive relations as well.
+    pub fn get_cron_inactive_refresh_day(&self) -> anyhow::Result<u8> {
+        let value = self.get_with_fallback(
+            &self.config.wsgi.cron_inactive_refresh_day,
+            get_default("cron_inactive_refresh_day"),
+        );
+        let day = value.parse::<u8>()?;
+        if !(1..=28).contains(&day) {
+            return Err(anyhow::anyhow!(
+                "cron_inactive_refresh_day should be between 1 and 28, got {day}"
+            ));
+        }
+        Ok(day)
+    }
+
+    /// Gets the User-Agent header to be sent with HTTP requests.
+    pub fn get_user_agent(&self) -> String {
+        let value = self.get_with_fallback(&self.config.wsgi.user_agent, get_default("user_agent"));
+        if value.is_empty() {
+            return format!("osm-gimmisn/{}", env!("CARGO_PKG_VERSION"));
+        }
+        value
+    }
+
+    /// Gets the number of days to keep derived stats artifacts for, 0 means no pruning.
+    pub fn get_stats_daily_artifact_retention_days(&self) -> anyhow::Result<i64> {
+        Ok(self
+            .get_with_fallback(
+                &self.config.wsgi.stats_daily_artifact_retention_days,
+                get_default("stats_daily_artifact_retention_days"),
+            )
+            .parse::<i64>()?)
+    }
 }
 
 /// Context owns global state which is set up once and then read everywhere.
 pub struct Context {
-    root: String,
     ini: Ini,
     network: Rc<dyn Network>,
     time: Rc<dyn Time>,
     subprocess: Rc<dyn Subprocess>,
     unit: Rc<dyn Unit>,
+    random: Rc<dyn Random>,
     file_system: Rc<dyn FileSystem>,
     database: Rc<dyn Database>,
     connection: OnceCell<Rc<RefCell<rusqlite::Connection>>>,
 }
 
+/// Unwraps a config value needed to create a Context: a typo in wsgi.ini should not make every
+/// request fail, so this warns and falls back to the default from INI_DEFAULTS.
+fn value_or_default<T: std::str::FromStr + Default>(value: anyhow::Result<T>, key: &str) -> T {
+    match value {
+        Ok(value) => value,
+        Err(err) => {
+            log::warn!("Context::new: failed to parse {key}, using the default: {err:?}");
+            get_default(key).parse().unwrap_or_default()
+        }
+    }
+}
+
 impl Context {
     /// Creates a new Context.
     pub fn new(prefix: &str) -> anyhow::Result<Self> {
         let current_dir = std::env::current_dir()?;
         let current_dir_str = current_dir.to_str().context("current_dir() failed")?;
         let root = format!("{current_dir_str}/{prefix}");
-        let network = Rc::new(StdNetwork {});
         let time = Rc::new(StdTime {});
         let subprocess = Rc::new(StdSubprocess {});
         let unit = Rc::new(StdUnit {});
+        let random = Rc::new(StdRandom::new());
         let file_system: Rc<dyn FileSystem> = Rc::new(StdFileSystem {});
         let database: Rc<dyn Database> = Rc::new(StdDatabase {});
         let ini = Ini::new(&file_system, &format!("{root}/workdir/wsgi.ini"), &root)?;
+        let network = Rc::new(StdNetwork::new(
+            &ini.get_user_agent(),
+            Duration::from_secs(value_or_default(
+                ini.get_connect_timeout_seconds(),
+                "connect_timeout",
+            )),
+            Duration::from_secs(value_or_default(
+                ini.get_read_timeout_seconds(),
+                "read_timeout",
+            )),
+            value_or_default(ini.get_max_redirects(), "max_redirects"),
+        ));
         let connection = OnceCell::new();
         Ok(Context {
-            root,
             ini,
             network,
             time,
             subprocess,
             unit,
+            random,
             file_system,
             database,
             connection,
         })
     }
 
-    /// Make a path absolute, taking the repo root as a base dir.
+    /// Make a path absolute, taking the repo root as a base dir. workdir/ paths are resolved
+    /// using the configured workdir.
     pub fn get_abspath(&self, rel_path: &str) -> String {
-        format!("{}/{}", self.root, rel_path)
+        self.ini.get_abspath(rel_path)
     }
 
     /// Gets the ini file.
@@ -325,6 +794,16 @@ impl Context {
         self.unit = unit.clone();
     }
 
+    /// Gets the random number implementation.
+    pub fn get_random(&self) -> &Rc<dyn Random> {
+        &self.random
+    }
+
+    /// Sets the random number implementation.
+    pub fn set_random(&mut self, random: &Rc<dyn Random>) {
+        self.random = random.clone();
+    }
+
     /// Gets the file system implementation.
     pub fn get_file_system(&self) -> &Rc<dyn FileSystem> {
         &self.file_system
@@ -50,6 +50,11 @@ impl FileSystem for StdFileSystem {
         Ok(modified.to_offset(get_tz_offset()))
     }
 
+    fn file_size(&self, path: &str) -> anyhow::Result<u64> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(metadata.len())
+    }
+
     fn open_read(&self, path: &str) -> anyhow::Result<Rc<RefCell<dyn Read>>> {
         let ret: Rc<RefCell<dyn Read>> = Rc::new(RefCell::new(
             std::fs::File::open(path)
@@ -86,34 +91,153 @@ impl FileSystem for StdFileSystem {
         }
         Ok(contents)
     }
+
+    fn try_lock(&self, path: &str) -> anyhow::Result<Option<Box<dyn std::any::Any>>> {
+        // An OS advisory lock: the OS releases it when the process exits, even after a crash, so
+        // there is no stale lock to detect. The lock is released when the returned file is dropped.
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("failed to open {path} for locking"))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Box::new(file))),
+            Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+            Err(std::fs::TryLockError::Error(err)) => {
+                Err(err).with_context(|| format!("failed to lock {path}"))
+            }
+        }
+    }
+
+    fn make_temp(&self, prefix: &str) -> anyhow::Result<String> {
+        // Same directory as the prefix, so the final rename doesn't cross file systems.
+        static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        loop {
+            let counter = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let path = format!("{prefix}.tmp{}-{counter}", std::process::id());
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(path),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to create {path}"));
+                }
+            }
+        }
+    }
+
+    fn rename(&self, from: &str, to: &str) -> anyhow::Result<()> {
+        std::fs::rename(from, to).with_context(|| format!("failed to rename {from} to {to}"))
+    }
 }
 
 /// Network implementation, backed by a real HTTP library.
-pub struct StdNetwork {}
+pub struct StdNetwork {
+    user_agent: String,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    max_redirects: u32,
+}
+
+impl StdNetwork {
+    /// Creates a new StdNetwork, which identifies itself using the provided user agent.
+    pub fn new(
+        user_agent: &str,
+        connect_timeout: Duration,
+        read_timeout: Duration,
+        max_redirects: u32,
+    ) -> Self {
+        StdNetwork {
+            user_agent: user_agent.to_string(),
+            connect_timeout,
+            read_timeout,
+            max_redirects,
+        }
+    }
+
+    /// Follows at most max_redirects redirects: 0 means a 3xx response is an error.
+    fn get_redirect_policy(&self) -> isahc::config::RedirectPolicy {
+        match self.max_redirects {
+            0 => isahc::config::RedirectPolicy::None,
+            limit => isahc::config::RedirectPolicy::Limit(limit),
+        }
+    }
+}
 
 // Real network is intentionally mocked.
 impl Network for StdNetwork {
-    fn urlopen(&self, url: &str, data: &str) -> anyhow::Result<String> {
-        if !data.is_empty() {
-            let mut buf = isahc::Request::post(url)
-                .redirect_policy(isahc::config::RedirectPolicy::Limit(1))
-                .timeout(Duration::from_secs(425))
+    fn urlopen(&self, url: &str, data: &str) -> Result<String, OsmGimmisnError> {
+        let mut buf = if !data.is_empty() {
+            isahc::Request::post(url)
+                .header("User-Agent", &self.user_agent)
+                .header("Accept-Encoding", "gzip")
+                .automatic_decompression(false)
+                .redirect_policy(self.get_redirect_policy())
+                .connect_timeout(self.connect_timeout)
+                .timeout(self.read_timeout)
                 .body(data)?
-                .send()?;
-            let ret = buf.text()?;
-            return Ok(ret);
+                .send()?
+        } else {
+            isahc::Request::get(url)
+                .header("User-Agent", &self.user_agent)
+                .header("Accept-Encoding", "gzip")
+                .automatic_decompression(false)
+                .redirect_policy(self.get_redirect_policy())
+                .connect_timeout(self.connect_timeout)
+                .timeout(self.read_timeout)
+                .body(())?
+                .send()?
+        };
+        let status = buf.status();
+        let bytes = buf
+            .bytes()
+            .map_err(|err| OsmGimmisnError::Network(err.to_string()))?;
+        let ret = decode_response(&bytes)?;
+        if !status.is_success() {
+            return Err(OsmGimmisnError::HttpStatus(status.as_u16(), ret));
         }
+        Ok(ret)
+    }
 
+    fn download(&self, url: &str) -> Result<Vec<u8>, OsmGimmisnError> {
+        // No Accept-Encoding: a .gz file should arrive as-is, not decompressed.
         let mut buf = isahc::Request::get(url)
-            .redirect_policy(isahc::config::RedirectPolicy::Limit(1))
-            .timeout(Duration::from_secs(425))
+            .header("User-Agent", &self.user_agent)
+            .automatic_decompression(false)
+            .redirect_policy(self.get_redirect_policy())
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.read_timeout)
             .body(())?
             .send()?;
-        let ret = buf.text()?;
-        Ok(ret)
+        let status = buf.status();
+        let bytes = buf
+            .bytes()
+            .map_err(|err| OsmGimmisnError::Network(err.to_string()))?;
+        if !status.is_success() {
+            let body = String::from_utf8_lossy(&bytes).to_string();
+            return Err(OsmGimmisnError::HttpStatus(status.as_u16(), body));
+        }
+        Ok(bytes)
     }
 }
 
+/// Decodes a HTTP response body as UTF-8, decompressing it first in case it's gzip-compressed.
+pub fn decode_response(bytes: &[u8]) -> Result<String, OsmGimmisnError> {
+    let mut decompressed: Vec<u8> = Vec::new();
+    let bytes: &[u8] = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decoder = flate2::read::GzDecoder::new(bytes);
+        decoder.read_to_end(&mut decompressed)?;
+        &decompressed
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes.to_vec()).map_err(|err| OsmGimmisnError::Parse(err.to_string()))
+}
+
 /// Time implementation, backed by the the actual time.
 pub struct StdTime {}
 
@@ -155,6 +279,39 @@ impl Subprocess for StdSubprocess {
     }
 }
 
+/// Random number implementation, backed by the randomly seeded hasher of the Rust stdlib.
+pub struct StdRandom {
+    state: std::collections::hash_map::RandomState,
+    counter: std::cell::Cell<u64>,
+}
+
+impl StdRandom {
+    /// Creates a new StdRandom with a random seed.
+    pub fn new() -> Self {
+        StdRandom {
+            state: std::collections::hash_map::RandomState::new(),
+            counter: std::cell::Cell::new(0),
+        }
+    }
+}
+
+impl Default for StdRandom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Random for StdRandom {
+    fn next_f64(&self) -> f64 {
+        use std::hash::BuildHasher as _;
+        let counter = self.counter.get();
+        self.counter.set(counter.wrapping_add(1));
+        let value = self.state.hash_one(counter);
+        // Use the top 53 bits, which fit into the mantissa.
+        (value >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
 /// Unit implementation, which intentionally does nothing.
 pub struct StdUnit {}
 
@@ -12,7 +12,10 @@
 
 use crate::area_files;
 use crate::areas;
+use crate::cache;
 use crate::context;
+use crate::error::OsmGimmisnError;
+use crate::i18n;
 use crate::overpass_query;
 use crate::stats;
 use crate::util;
@@ -23,6 +26,7 @@ use std::collections::HashSet;
 use std::io::BufRead;
 use std::io::Write;
 use std::ops::DerefMut;
+use std::rc::Rc;
 
 #[cfg(not(test))]
 use log::{error, info, warn};
@@ -30,15 +34,45 @@ use log::{error, info, warn};
 #[cfg(test)]
 use std::{println as info, println as warn, println as error};
 
+/// Randomly shifts a sleep by the configured jitter fraction, so instances sharing an overpass
+/// don't retry at the same time.
+fn add_jitter(ctx: &context::Context, sleep: u64) -> u64 {
+    let jitter = match ctx.get_ini().get_overpass_sleep_jitter() {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("add_jitter: failed to get jitter: {err:?}");
+            0.0
+        }
+    };
+    if jitter <= 0.0 {
+        return sleep;
+    }
+
+    let factor = 1.0 + jitter * (2.0 * ctx.get_random().next_f64() - 1.0);
+    (sleep as f64 * factor).round() as u64
+}
+
 /// Sleeps to respect overpass rate limit.
 fn overpass_sleep(ctx: &context::Context) {
+    let max_sleep = match ctx.get_ini().get_overpass_max_sleep() {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("overpass_sleep: failed to get max sleep: {err:?}");
+            0
+        }
+    };
     loop {
-        let sleep = overpass_query::overpass_query_need_sleep(ctx);
+        let mut sleep = overpass_query::overpass_query_need_sleep(ctx) as u64;
         if sleep == 0 {
             break;
         }
+        if max_sleep > 0 && sleep > max_sleep {
+            info!("overpass_sleep: clamping {sleep} seconds to {max_sleep} seconds");
+            sleep = max_sleep;
+        }
+        sleep = add_jitter(ctx, sleep);
         info!("overpass_sleep: waiting for {sleep} seconds");
-        ctx.get_time().sleep(sleep as u64);
+        ctx.get_time().sleep(sleep);
     }
 }
 
@@ -47,21 +81,110 @@ fn should_retry(retry: i32) -> bool {
     retry < 20
 }
 
+/// Decides if a failed query is worth retrying, e.g. a malformed query is not.
+fn should_retry_error(err: &OsmGimmisnError) -> bool {
+    err.is_retryable()
+}
+
+/// Decides if an overpass response is suspiciously small, e.g. truncated, which would silently
+/// result in an empty street or house number list.
+fn is_response_too_small(ctx: &context::Context, buf: &str) -> anyhow::Result<bool> {
+    Ok(buf.len() < ctx.get_ini().get_min_response_bytes()?)
+}
+
+/// Tracks consecutive overpass failures across relations, so a phase can give up early when
+/// overpass is down.
+struct FailureBudget {
+    limit: u32,
+    failures: u32,
+}
+
+impl FailureBudget {
+    fn new(ctx: &context::Context) -> anyhow::Result<Self> {
+        let limit = ctx.get_ini().get_overpass_global_failure_limit()?;
+        Ok(FailureBudget { limit, failures: 0 })
+    }
+
+    fn record_failure(&mut self) {
+        self.failures += 1;
+    }
+
+    fn record_success(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Decides if we reached the limit of consecutive failures.
+    fn is_exhausted(&self) -> bool {
+        self.limit > 0 && self.failures >= self.limit
+    }
+}
+
+/// Tracks the wall-clock budget of a cron run, so the phases stop starting new relations once it's
+/// used up.
+struct RuntimeBudget {
+    time: Rc<dyn context::Time>,
+    deadline: Option<time::OffsetDateTime>,
+}
+
+impl RuntimeBudget {
+    /// Creates a budget of max_minutes from now, or an unlimited one.
+    fn new(ctx: &context::Context, max_minutes: Option<i64>) -> Self {
+        let time = ctx.get_time().clone();
+        let deadline = max_minutes.map(|i| time.now() + time::Duration::minutes(i));
+        RuntimeBudget { time, deadline }
+    }
+
+    /// Decides if the budget is used up, in which case the phase should stop early.
+    fn is_exceeded(&self, phase: &str) -> bool {
+        let exceeded = match self.deadline {
+            Some(deadline) => self.time.now() >= deadline,
+            None => false,
+        };
+        if exceeded {
+            warn!("{phase}: runtime budget exceeded, stopping early");
+        }
+        exceeded
+    }
+}
+
 /// Update the OSM street list of all relations.
 fn update_osm_streets(
     ctx: &context::Context,
     relations: &mut areas::Relations<'_>,
     update: bool,
-) -> anyhow::Result<()> {
-    let active_names = relations.get_active_names();
-    for relation_name in active_names.context("get_active_names() failed")? {
-        let relation = relations.get_relation(&relation_name)?;
-        if !update && stats::has_sql_mtime(ctx, &format!("streets/{}", relation_name))? {
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<PhaseSummary> {
+    let mut summary = PhaseSummary::default();
+    let mut budget = FailureBudget::new(ctx)?;
+    let active_names = relations
+        .get_active_names()
+        .context("get_active_names() failed")?;
+    for (index, relation_name) in active_names.iter().enumerate() {
+        if runtime.is_exceeded("update_osm_streets") {
+            break;
+        }
+        if budget.is_exhausted() {
+            error!(
+                "update_osm_streets: aborting after {} consecutive failures",
+                budget.failures
+            );
+            summary.failed.extend(active_names[index..].iter().cloned());
+            break;
+        }
+        let relation = relations.get_relation(relation_name)?;
+        // A relation pinned to a snapshot has no live data to refresh.
+        let pinned = relation.get_config().get_osm_snapshot().is_some();
+        let done = relations.is_done(&relation);
+        if (!update || pinned || done)
+            && stats::has_sql_mtime(ctx, &format!("streets/{}", relation_name))?
+        {
+            summary.skipped += 1;
             continue;
         }
         info!("update_osm_streets, json: start: {relation_name}");
+        let mut updated = false;
         let mut retry = 0;
-        while should_retry(retry) {
+        while should_retry(retry) && !budget.is_exhausted() {
             if retry > 0 {
                 info!("update_osm_streets, json: try #{retry}");
             }
@@ -72,19 +195,39 @@ fn update_osm_streets(
                 Ok(value) => value,
                 Err(err) => {
                     info!("update_osm_streets, json: http error: {err:?}");
+                    budget.record_failure();
+                    if !should_retry_error(&err) {
+                        break;
+                    }
                     continue;
                 }
             };
+            if is_response_too_small(ctx, &buf)? {
+                info!(
+                    "update_osm_streets, json: response is only {} bytes, ignoring",
+                    buf.len()
+                );
+                budget.record_failure();
+                continue;
+            }
             relation
                 .get_files()
                 .write_osm_json_streets(ctx, &buf)
                 .context("write_osm_json_streets() failed")?;
+            budget.record_success();
+            updated = true;
             break;
         }
+        if !updated {
+            error!("update_osm_streets, json: failed: {relation_name}");
+            summary.failed.push(relation_name.to_string());
+        } else {
+            summary.updated += 1;
+        }
         info!("update_osm_streets, json: end: {relation_name}");
     }
 
-    Ok(())
+    Ok(summary)
 }
 
 /// Update the OSM housenumber list of all relations.
@@ -92,15 +235,37 @@ fn update_osm_housenumbers(
     ctx: &context::Context,
     relations: &mut areas::Relations<'_>,
     update: bool,
-) -> anyhow::Result<()> {
-    for relation_name in relations.get_active_names()? {
-        let relation = relations.get_relation(&relation_name)?;
-        if !update && stats::has_sql_mtime(ctx, &format!("housenumbers/{}", relation_name))? {
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<PhaseSummary> {
+    let mut summary = PhaseSummary::default();
+    let mut budget = FailureBudget::new(ctx)?;
+    let active_names = relations.get_active_names()?;
+    for (index, relation_name) in active_names.iter().enumerate() {
+        if runtime.is_exceeded("update_osm_housenumbers") {
+            break;
+        }
+        if budget.is_exhausted() {
+            error!(
+                "update_osm_housenumbers: aborting after {} consecutive failures",
+                budget.failures
+            );
+            summary.failed.extend(active_names[index..].iter().cloned());
+            break;
+        }
+        let relation = relations.get_relation(relation_name)?;
+        // A relation pinned to a snapshot has no live data to refresh.
+        let pinned = relation.get_config().get_osm_snapshot().is_some();
+        let done = relations.is_done(&relation);
+        if (!update || pinned || done)
+            && stats::has_sql_mtime(ctx, &format!("housenumbers/{}", relation_name))?
+        {
+            summary.skipped += 1;
             continue;
         }
         info!("update_osm_housenumbers, json: start: {relation_name}");
+        let mut updated = false;
         let mut retry = 0;
-        while should_retry(retry) {
+        while should_retry(retry) && !budget.is_exhausted() {
             if retry > 0 {
                 info!("update_osm_housenumbers, json: try #{retry}");
             }
@@ -111,18 +276,38 @@ fn update_osm_housenumbers(
                 Ok(value) => value,
                 Err(err) => {
                     info!("update_osm_housenumbers, json: http error: {err:?}");
+                    budget.record_failure();
+                    if !should_retry_error(&err) {
+                        break;
+                    }
                     continue;
                 }
             };
+            if is_response_too_small(ctx, &buf)? {
+                info!(
+                    "update_osm_housenumbers, json: response is only {} bytes, ignoring",
+                    buf.len()
+                );
+                budget.record_failure();
+                continue;
+            }
             relation
                 .get_files()
                 .write_osm_json_housenumbers(ctx, &buf)?;
+            budget.record_success();
+            updated = true;
             break;
         }
+        if !updated {
+            error!("update_osm_housenumbers, json: failed: {relation_name}");
+            summary.failed.push(relation_name.to_string());
+        } else {
+            summary.updated += 1;
+        }
         info!("update_osm_housenumbers, json: end: {relation_name}");
     }
 
-    Ok(())
+    Ok(summary)
 }
 
 /// Update the reference housenumber list of all relations.
@@ -130,80 +315,346 @@ fn update_ref_housenumbers(
     ctx: &context::Context,
     relations: &mut areas::Relations<'_>,
     update: bool,
-) -> anyhow::Result<()> {
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<PhaseSummary> {
+    let mut summary = PhaseSummary::default();
     for relation_name in relations.get_active_names()? {
+        if runtime.is_exceeded("update_ref_housenumbers") {
+            break;
+        }
         let relation = relations.get_relation(&relation_name)?;
         if !update
             && ctx
                 .get_file_system()
                 .path_exists(&relation.get_files().get_ref_housenumbers_path())
         {
+            summary.skipped += 1;
             continue;
         }
         let streets = relation.get_config().should_check_missing_streets();
         if streets == "only" {
+            summary.skipped += 1;
             continue;
         }
 
         info!("update_ref_housenumbers: start: {relation_name}");
         relation.write_ref_housenumbers()?;
         info!("update_ref_housenumbers: end: {relation_name}");
+        summary.updated += 1;
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+/// A drop in the house number coverage of a relation, compared to the previous cron run.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Regression {
+    relation: String,
+    date: String,
+    old: f64,
+    new: f64,
+}
+
+/// Maximum number of entries kept in workdir/regressions.json, older ones are dropped.
+const REGRESSIONS_LIMIT: usize = 1000;
+
+/// Appends the found coverage regressions to workdir/regressions.json.
+fn write_regressions(ctx: &context::Context, regressions: Vec<Regression>) -> anyhow::Result<()> {
+    let path = format!("{}/regressions.json", ctx.get_ini().get_workdir());
+    let mut all: Vec<Regression> = Vec::new();
+    if ctx.get_file_system().path_exists(&path) {
+        let content = ctx.get_file_system().read_to_string(&path)?;
+        if !content.is_empty() {
+            all = serde_json::from_str(&content).context("failed to parse regressions.json")?;
+        }
+    }
+    all.extend(regressions);
+    if all.len() > REGRESSIONS_LIMIT {
+        all.drain(..all.len() - REGRESSIONS_LIMIT);
+    }
+    ctx.get_file_system()
+        .write_from_string_atomic(&serde_json::to_string(&all)?, &path)
 }
 
 /// Update the relation's house number coverage stats.
 fn update_missing_housenumbers(
+    ctx: &context::Context,
     relations: &mut areas::Relations<'_>,
     update: bool,
-) -> anyhow::Result<()> {
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<PhaseSummary> {
+    let mut summary = PhaseSummary::default();
     info!("update_missing_housenumbers: start");
+    let threshold = ctx.get_ini().get_regression_threshold()?;
+    let format = time::format_description::parse("[year]-[month]-[day]")?;
+    let today = ctx.get_time().now().format(&format)?;
+    let mut regressions: Vec<Regression> = Vec::new();
     let active_names = relations
         .get_active_names()
         .context("get_active_names() failed")?;
     for relation_name in active_names {
+        if runtime.is_exceeded("update_missing_housenumbers") {
+            break;
+        }
         let mut relation = relations
             .get_relation(&relation_name)
             .context("get_relation() failed")?;
         if !update && relation.has_osm_housenumber_coverage()? {
+            summary.skipped += 1;
             continue;
         }
         let streets = relation.get_config().should_check_missing_streets();
         if streets == "only" {
+            summary.skipped += 1;
             continue;
         }
 
-        relation
+        let input_hash = relation.input_hash().context("input_hash() failed")?;
+        if relation.get_osm_housenumber_coverage_input_hash()? == Some(input_hash.to_string()) {
+            // Nothing changed since the last run, just record that the coverage is still the same.
+            relation
+                .write_osm_housenumber_coverage_history()
+                .context("write_osm_housenumber_coverage_history() failed")?;
+            summary.skipped += 1;
+            continue;
+        }
+
+        // Remember the percent of the previous run before it's overwritten.
+        let old_percent: Option<f64> = if relation.has_osm_housenumber_coverage()? {
+            relation.get_osm_housenumber_coverage()?.parse::<f64>().ok()
+        } else {
+            None
+        };
+        let (_, _, _, new_percent, _) = relation
             .write_missing_housenumbers()
             .context("write_missing_housenumbers() failed")?;
+        relation.set_osm_housenumber_coverage_input_hash(&input_hash)?;
+        relation
+            .write_osm_housenumber_coverage_history()
+            .context("write_osm_housenumber_coverage_history() failed")?;
+        if let Some(old_percent) = old_percent {
+            // Compare the rounded value, that's what is stored.
+            let new_percent = format!("{new_percent:.2}").parse::<f64>()?;
+            if old_percent - new_percent >= threshold {
+                warn!(
+                    "update_missing_housenumbers: {relation_name}: coverage regressed from {old_percent:.2}% to {new_percent:.2}%"
+                );
+                regressions.push(Regression {
+                    relation: relation_name.to_string(),
+                    date: today.to_string(),
+                    old: old_percent,
+                    new: new_percent,
+                });
+            }
+        }
+        summary.updated += 1;
+    }
+    if !regressions.is_empty() {
+        write_regressions(ctx, regressions).context("write_regressions() failed")?;
     }
     info!("update_missing_housenumbers: end");
 
+    Ok(summary)
+}
+
+/// Regenerates the cached missing house numbers HTML of the relations in all languages which have
+/// one, e.g. after a translation change. The stored analysis and coverage percents are reused, not
+/// recomputed.
+fn update_missing_housenumbers_html(
+    ctx: &context::Context,
+    relations: &mut areas::Relations<'_>,
+) -> anyhow::Result<()> {
+    info!("update_missing_housenumbers_html: start");
+    let old_language = i18n::get_language();
+    for relation_name in relations.get_active_names()? {
+        let mut relation = relations.get_relation(&relation_name)?;
+        if relation.get_config().should_check_missing_streets() == "only" {
+            continue;
+        }
+        if !stats::has_sql_mtime(ctx, &format!("missing-housenumbers-cache/{relation_name}"))? {
+            // No analysis to render.
+            continue;
+        }
+
+        for language in cache::get_missing_housenumbers_html_languages(ctx, &relation_name)? {
+            i18n::set_language(ctx, &language);
+            cache::get_missing_housenumbers_html(ctx, &mut relation, /*html_only=*/ true)
+                .context("get_missing_housenumbers_html() failed")?;
+        }
+    }
+    i18n::set_language(ctx, &old_language);
+    info!("update_missing_housenumbers_html: end");
+    Ok(())
+}
+
+/// Pre-populates the caches of the relations, so the first visitor of a relation page doesn't have
+/// to wait for the analysis. Coverage percents are not recomputed, the HTML of the missing house
+/// numbers page is cached on its first visit.
+fn warm_cache(relations: &mut areas::Relations<'_>) -> anyhow::Result<()> {
+    info!("warm_cache: start");
+    for relation_name in relations.get_active_names()? {
+        let mut relation = relations.get_relation(&relation_name)?;
+        let streets = relation.get_config().should_check_missing_streets();
+        if streets == "only" {
+            continue;
+        }
+
+        cache::get_missing_housenumbers_json(&mut relation)
+            .context("get_missing_housenumbers_json() failed")?;
+        cache::get_additional_housenumbers_json(&mut relation)
+            .context("get_additional_housenumbers_json() failed")?;
+    }
+    info!("warm_cache: end");
+
     Ok(())
 }
 
+/// Warns about refstreets keys which are not in the fetched OSM street list of their relation.
+/// Returns the warnings.
+fn check_refstreets(
+    ctx: &context::Context,
+    relations: &mut areas::Relations<'_>,
+) -> anyhow::Result<Vec<String>> {
+    info!("check_refstreets: start");
+    let mut warnings: Vec<String> = Vec::new();
+    for relation_name in relations.get_active_names()? {
+        if !stats::has_sql_mtime(ctx, &format!("streets/{relation_name}"))? {
+            continue;
+        }
+        let relation = relations.get_relation(&relation_name)?;
+        if !relation.get_config().has_refsettlement() {
+            // No reference streets to compare with.
+            info!("check_refstreets: {relation_name}: no refsettlement, skipping");
+            continue;
+        }
+        let (osm_invalids, _ref_invalids) = relation.get_invalid_refstreets()?;
+        for osm_name in osm_invalids {
+            let warning = format!(
+                "{relation_name}: refstreets key '{osm_name}' is not in the OSM street list"
+            );
+            warn!("check_refstreets: {warning}");
+            warnings.push(warning);
+        }
+    }
+    info!("check_refstreets: end");
+
+    Ok(warnings)
+}
+
+/// Gets the (refcounty, refsettlement) pairs which have rows in a reference table.
+fn get_ref_settlements(
+    ctx: &context::Context,
+    table: &str,
+) -> anyhow::Result<HashSet<(String, String)>> {
+    let mut ret: HashSet<(String, String)> = HashSet::new();
+    let conn = ctx.get_database_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "select distinct county_code, settlement_code from {table}"
+    ))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        ret.insert((row.get(0)?, row.get(1)?));
+    }
+    Ok(ret)
+}
+
+/// Warns about active relations whose refcounty/refsettlement is not covered by the reference
+/// data, which would silently produce empty results. Returns the warnings.
+fn check_references(
+    ctx: &context::Context,
+    relations: &mut areas::Relations<'_>,
+) -> anyhow::Result<Vec<String>> {
+    info!("check_references: start");
+    let ref_housenumbers = get_ref_settlements(ctx, "ref_housenumbers")?;
+    let ref_streets = get_ref_settlements(ctx, "ref_streets")?;
+    let mut warnings: Vec<String> = Vec::new();
+    for relation_name in relations.get_active_names()? {
+        let relation = relations.get_relation(&relation_name)?;
+        let config = relation.get_config();
+        let key = (config.get_refcounty(), config.get_refsettlement());
+        let missing_streets = config.should_check_missing_streets();
+        let checks = [
+            (
+                "house numbers",
+                missing_streets != "only",
+                &ref_housenumbers,
+            ),
+            ("streets", missing_streets != "no", &ref_streets),
+        ];
+        for (kind, enabled, references) in checks {
+            if !enabled || references.contains(&key) {
+                continue;
+            }
+            let warning = format!(
+                "{relation_name}: no reference {kind} for refcounty '{}', refsettlement '{}'",
+                key.0, key.1
+            );
+            warn!("check_references: {warning}");
+            warnings.push(warning);
+        }
+    }
+    info!("check_references: end");
+
+    Ok(warnings)
+}
+
+/// Builds the Overpass queries of the active relations and checks their syntax, without talking to
+/// Overpass. Returns the found problems.
+fn check_queries(relations: &mut areas::Relations<'_>) -> anyhow::Result<Vec<String>> {
+    info!("check_queries: start");
+    let mut problems: Vec<String> = Vec::new();
+    for relation_name in relations.get_active_names()? {
+        let relation = relations.get_relation(&relation_name)?;
+        let queries = [
+            ("streets", relation.get_osm_streets_query()),
+            ("housenumbers", relation.get_osm_housenumbers_query()),
+        ];
+        for (kind, query) in queries {
+            let query_problems = match query {
+                Ok(value) => overpass_query::check_query_syntax(&value),
+                Err(err) => vec![format!("{err:?}")],
+            };
+            for query_problem in query_problems {
+                let problem = format!("{relation_name}: {kind} query: {query_problem}");
+                warn!("check_queries: {problem}");
+                problems.push(problem);
+            }
+        }
+    }
+    info!("check_queries: end");
+
+    Ok(problems)
+}
+
 /// Update the relation's street coverage stats.
 fn update_missing_streets(
     relations: &mut areas::Relations<'_>,
     update: bool,
-) -> anyhow::Result<()> {
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<PhaseSummary> {
+    let mut summary = PhaseSummary::default();
     info!("update_missing_streets: start");
     for relation_name in relations.get_active_names()? {
+        if runtime.is_exceeded("update_missing_streets") {
+            break;
+        }
         let relation = relations.get_relation(&relation_name)?;
         if !update && relation.has_osm_street_coverage()? {
+            summary.skipped += 1;
             continue;
         }
         let streets = relation.get_config().should_check_missing_streets();
         if streets == "no" {
+            summary.skipped += 1;
             continue;
         }
 
         relation.write_missing_streets()?;
+        summary.updated += 1;
     }
     info!("update_missing_streets: end");
 
-    Ok(())
+    Ok(summary)
 }
 
 /// Update the relation's "additional streets" stats.
@@ -211,29 +662,38 @@ fn update_additional_streets(
     ctx: &context::Context,
     relations: &mut areas::Relations<'_>,
     update: bool,
-) -> anyhow::Result<()> {
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<PhaseSummary> {
+    let mut summary = PhaseSummary::default();
     info!("update_additional_streets: start");
     for relation_name in relations.get_active_names()? {
+        if runtime.is_exceeded("update_additional_streets") {
+            break;
+        }
         let relation = relations.get_relation(&relation_name)?;
         if !update && stats::has_sql_count(ctx, "additional_streets_counts", &relation_name)? {
+            summary.skipped += 1;
             continue;
         }
         let streets = relation.get_config().should_check_missing_streets();
         if streets == "no" {
+            summary.skipped += 1;
             continue;
         }
 
         relation.write_additional_streets()?;
+        summary.updated += 1;
     }
     info!("update_additional_streets: end");
 
-    Ok(())
+    Ok(summary)
 }
 
 /// Writes a daily citycount rows into the stats_citycounts SQL table.
 fn write_city_count_path(
     ctx: &context::Context,
     cities: &HashMap<String, HashSet<String>>,
+    today: &str,
 ) -> anyhow::Result<()> {
     let mut cities: Vec<_> = cities.iter().collect();
     // Locale-aware sort, by key.
@@ -241,14 +701,11 @@ fn write_city_count_path(
     cities.dedup();
     let mut conn = ctx.get_database_connection()?;
     let tx = conn.transaction()?;
-    let now = ctx.get_time().now();
-    let format = time::format_description::parse("[year]-[month]-[day]")?;
-    let today = now.format(&format)?;
     for (key, value) in cities {
         tx.execute(
             r#"insert into stats_citycounts (date, city, count) values (?1, ?2, ?3)
             on conflict(date, city) do update set count = excluded.count"#,
-            [&today, key, &value.len().to_string()],
+            [today, key, &value.len().to_string()],
         )?;
     }
     Ok(tx.commit()?)
@@ -258,6 +715,7 @@ fn write_city_count_path(
 fn write_zip_count_path(
     ctx: &context::Context,
     zips: &HashMap<String, HashSet<String>>,
+    today: &str,
 ) -> anyhow::Result<()> {
     let mut zips: Vec<_> = zips.iter().collect();
     zips.sort_by_key(|(key, _value)| key.to_string());
@@ -265,14 +723,11 @@ fn write_zip_count_path(
 
     let mut conn = ctx.get_database_connection()?;
     let tx = conn.transaction()?;
-    let now = ctx.get_time().now();
-    let format = time::format_description::parse("[year]-[month]-[day]")?;
-    let today = now.format(&format)?;
     for (key, value) in zips {
         tx.execute(
             r#"insert into stats_zipcounts (date, zip, count) values (?1, ?2, ?3)
             on conflict(date, zip) do update set count = excluded.count"#,
-            [&today, key, &value.len().to_string()],
+            [today, key, &value.len().to_string()],
         )?;
     }
     Ok(tx.commit()?)
@@ -331,8 +786,8 @@ fn update_stats_count(ctx: &context::Context, today: &str) -> anyhow::Result<()>
         tx.commit()?;
     }
 
-    write_city_count_path(ctx, &cities).context("write_city_count_path() failed")?;
-    write_zip_count_path(ctx, &zips).context("write_zip_count_path() failed")
+    write_city_count_path(ctx, &cities, today).context("write_city_count_path() failed")?;
+    write_zip_count_path(ctx, &zips, today).context("write_zip_count_path() failed")
 }
 
 /// Counts the top housenumber editors as of today.
@@ -350,19 +805,29 @@ fn update_stats_topusers(ctx: &context::Context, today: &str) -> anyhow::Result<
     }
     {
         let mut users: Vec<_> = users.iter().collect();
-        users.sort_by_key(|i| Reverse(i.1));
+        // Break ties by username, so the cut at 20 users is stable between runs.
+        users.sort_by_key(|i| (Reverse(i.1), util::get_sort_key(i.0)));
         users.dedup();
         users = users[0..std::cmp::min(20, users.len())].to_vec();
+        let anonymize = ctx.get_ini().get_anonymize_users();
+        let salt = ctx.get_ini().get_anonymize_users_secret();
+        if anonymize && salt.is_empty() {
+            return Err(anyhow::anyhow!(
+                "anonymize_users is True, but anonymize_users_secret is empty"
+            ));
+        }
         let mut conn = ctx.get_database_connection()?;
         let tx = conn.transaction()?;
-        let now = ctx.get_time().now();
-        let format = time::format_description::parse("[year]-[month]-[day]")?;
-        let today = now.format(&format)?;
         for user in &users {
+            let name = if anonymize {
+                util::anonymize_user(&salt, user.0)
+            } else {
+                user.0.to_string()
+            };
             tx.execute(
                 r#"insert into stats_topusers (date, user, count) values (?1, ?2, ?3)
             on conflict(date, user) do update set count = excluded.count"#,
-                [&today, user.0, &user.1.to_string()],
+                [today, &name, &user.1.to_string()],
             )?;
         }
         tx.commit()?;
@@ -382,11 +847,10 @@ fn update_stats_topusers(ctx: &context::Context, today: &str) -> anyhow::Result<
 fn update_stats_refcount(ctx: &context::Context, state_dir: &str) -> anyhow::Result<()> {
     let mut count = 0;
     {
-        let stream = ctx
-            .get_file_system()
-            .open_read(&ctx.get_ini().get_reference_citycounts_path()?)?;
+        let path = ctx.get_ini().get_reference_citycounts_path()?;
+        let stream = ctx.get_file_system().open_read(&path)?;
         let mut guard = stream.borrow_mut();
-        let mut read = guard.deref_mut();
+        let mut read = util::make_reference_reader(&path, guard.deref_mut())?;
         let mut csv_reader = util::make_csv_reader(&mut read);
         for result in csv_reader.deserialize() {
             let row: util::CityCount = result?;
@@ -405,18 +869,77 @@ pub fn update_stats_overpass(ctx: &context::Context) -> anyhow::Result<()> {
     let query = ctx
         .get_file_system()
         .read_to_string(&ctx.get_abspath("data/street-housenumbers-hungary.overpassql"))?;
+    let json_query = get_stats_json_query(&query, None);
+    if let Some(response) = query_stats_overpass(ctx, &json_query) {
+        area_files::write_whole_country(ctx, &response)?;
+    }
+    Ok(())
+}
+
+/// Fetches the whole-country stats of the past days between `start` and `end` (inclusive,
+/// YYYY-MM-DD), one day at a time. Days which already have a count are skipped, unless `force`
+/// is set. Note that the whole_country table contains the data of the last fetched day afterwards.
+fn backfill_stats(
+    ctx: &context::Context,
+    start: &str,
+    end: &str,
+    force: bool,
+) -> anyhow::Result<()> {
+    let format = time::format_description::parse("[year]-[month]-[day]")?;
+    let mut date = time::Date::parse(start, &format).context("failed to parse start date")?;
+    let end = time::Date::parse(end, &format).context("failed to parse end date")?;
+    let query = ctx
+        .get_file_system()
+        .read_to_string(&ctx.get_abspath("data/street-housenumbers-hungary.overpassql"))?;
+    while date <= end {
+        let day = date.format(&format)?;
+        date = date.next_day().context("next_day() failed")?;
+        if !force && stats::has_count(ctx, &day)? {
+            info!("backfill_stats: {day} has a count already, skipping");
+            continue;
+        }
+
+        info!("backfill_stats: fetching {day}");
+        let json_query = get_stats_json_query(&query, Some(&day));
+        // This sleeps before each query to respect the overpass rate limit.
+        let response = match query_stats_overpass(ctx, &json_query) {
+            Some(value) => value,
+            None => {
+                warn!("backfill_stats: failed to fetch {day}");
+                continue;
+            }
+        };
+        area_files::write_whole_country(ctx, &response)?;
+        update_stats_count(ctx, &day).context("update_stats_count() failed")?;
+        update_stats_topusers(ctx, &day)?;
+    }
+
+    Ok(())
+}
+
+/// Turns the CSV stats query into a JSON one, optionally for the state at the start of a past day.
+fn get_stats_json_query(query: &str, date: Option<&str>) -> String {
     let mut i = 0;
     let mut lines = Vec::new();
     for line in query.lines() {
         i += 1;
         if i == 1 {
-            lines.push("[out:json]  [timeout:425];".to_string());
+            match date {
+                Some(date) => lines.push(format!(
+                    "[out:json] [date:\"{date}T00:00:00Z\"] [timeout:425];"
+                )),
+                None => lines.push("[out:json]  [timeout:425];".to_string()),
+            }
             continue;
         }
 
         lines.push(line.to_string());
     }
-    let json_query = lines.join("\n");
+    lines.join("\n")
+}
+
+/// Runs a stats query, with retries. Returns None if all tries failed.
+fn query_stats_overpass(ctx: &context::Context, json_query: &str) -> Option<String> {
     info!("update_stats_overpass: json, talking to overpass");
     let mut retry = 0;
     while should_retry(retry) {
@@ -425,29 +948,200 @@ pub fn update_stats_overpass(ctx: &context::Context) -> anyhow::Result<()> {
         }
         retry += 1;
         overpass_sleep(ctx);
-        let response = match overpass_query::overpass_query(ctx, &json_query) {
-            Ok(value) => value,
+        match overpass_query::overpass_query(ctx, json_query) {
+            Ok(value) => return Some(value),
             Err(err) => {
                 info!("update_stats_overpass: http error: {err}");
+                if !should_retry_error(&err) {
+                    break;
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Counts the # of house numbers in a single refcounty, without touching the whole-country
+/// state. The result is written to workdir/stats/<today>-<refcounty>.count.
+fn update_stats_refcounty(
+    ctx: &context::Context,
+    refcounty: &str,
+    statedir: &str,
+    today: &str,
+) -> anyhow::Result<()> {
+    let relations = areas::Relations::new(ctx)?;
+    let refcounty_name = relations.refcounty_get_name(refcounty);
+    if refcounty_name.is_empty() {
+        return Err(anyhow::anyhow!(
+            "unknown refcounty '{refcounty}', see data/refcounty-names.yaml"
+        ));
+    }
+    let query = ctx
+        .get_file_system()
+        .read_to_string(&ctx.get_abspath("data/street-housenumbers-refcounty.overpassql"))?;
+    let query = query.replace("@REFCOUNTY_NAME@", &refcounty_name);
+    let json_query = get_stats_json_query(&query, None);
+    let response = match query_stats_overpass(ctx, &json_query) {
+        Some(value) => value,
+        None => {
+            return Err(anyhow::anyhow!(
+                "failed to query the house numbers of refcounty '{refcounty}'"
+            ));
+        }
+    };
+    let count = area_files::count_housenumbers(&response)?;
+    let path = format!("{statedir}/{today}-{refcounty}.count");
+    ctx.get_file_system()
+        .write_from_string(&format!("{count}\n"), &path)
+}
+
+/// One row of the whole_country table, as exported to JSON Lines.
+#[derive(serde::Serialize)]
+struct WholeCountryRow {
+    postcode: String,
+    city: String,
+    street: String,
+    housenumber: String,
+    user: String,
+}
+
+/// Exports the whole_country table to workdir/stats/<today>.jsonl, one JSON object per line.
+/// Returns the number of exported rows.
+fn export_whole_country_jsonl(ctx: &context::Context, today: &str) -> anyhow::Result<usize> {
+    let path = ctx.get_abspath(&format!("workdir/stats/{today}.jsonl"));
+    let stream = ctx.get_file_system().open_write(&path)?;
+    let mut guard = stream.borrow_mut();
+    let write = guard.deref_mut();
+    let conn = ctx.get_database_connection()?;
+    let mut stmt =
+        conn.prepare("select postcode, city, street, housenumber, user from whole_country")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(WholeCountryRow {
+            postcode: row.get(0)?,
+            city: row.get(1)?,
+            street: row.get(2)?,
+            housenumber: row.get(3)?,
+            user: row.get(4)?,
+        })
+    })?;
+    let mut count = 0;
+    for row in rows {
+        let row = row.context("failed to read a whole_country row")?;
+        // Malformed row: nothing to address.
+        if row.housenumber.is_empty() {
+            continue;
+        }
+        let line = serde_json::to_string(&row)?;
+        write.write_all(format!("{line}\n").as_bytes())?;
+        count += 1;
+    }
+    info!("export_whole_country_jsonl: exported {count} rows to {path}");
+    Ok(count)
+}
+
+/// Removes derived artifacts from workdir/stats/ which are older than the retention window.
+fn prune_stats_artifacts(ctx: &context::Context, state_dir: &str) -> anyhow::Result<()> {
+    let retention_days = ctx.get_ini().get_stats_daily_artifact_retention_days()?;
+    if retention_days <= 0 {
+        return Ok(());
+    }
+
+    let now = ctx.get_time().now();
+    let json_path = format!("{state_dir}/stats.json");
+    for path in ctx.get_file_system().listdir(state_dir)? {
+        if path == json_path {
+            continue;
+        }
+
+        let mtime = ctx.get_file_system().getmtime(&path)?;
+        if (now - mtime).whole_days() < retention_days {
+            continue;
+        }
+
+        let size = ctx.get_file_system().file_size(&path)?;
+        info!("prune_stats_artifacts: removing {path} ({size} bytes)");
+        ctx.get_file_system().unlink(&path)?;
+    }
+
+    Ok(())
+}
+
+/// Downloads the reference files which have a configured URL, unless the local copy is fresh.
+fn fetch_reference(ctx: &context::Context) -> anyhow::Result<()> {
+    let ini = ctx.get_ini();
+    let mut downloads: Vec<(String, String)> = Vec::new();
+    let housenumbers_urls = ini.get_reference_housenumbers_urls();
+    if !housenumbers_urls.is_empty() {
+        let paths = ini.get_reference_housenumber_paths()?;
+        if housenumbers_urls.len() != paths.len() {
+            return Err(anyhow::anyhow!(
+                "reference_housenumbers_url has {} URLs, but reference_housenumbers has {} paths",
+                housenumbers_urls.len(),
+                paths.len()
+            ));
+        }
+        downloads.extend(housenumbers_urls.into_iter().zip(paths));
+    }
+    if let Some(url) = ini.get_reference_street_url() {
+        downloads.push((url, ini.get_reference_street_path()?));
+    }
+    if let Some(url) = ini.get_reference_citycounts_url() {
+        downloads.push((url, ini.get_reference_citycounts_path()?));
+    }
+    if let Some(url) = ini.get_reference_zipcounts_url() {
+        downloads.push((url, ini.get_reference_zipcounts_path()?));
+    }
+
+    let ttl_hours = ini.get_reference_ttl_hours()?;
+    let now = ctx.get_time().now();
+    for (url, path) in downloads {
+        if ctx.get_file_system().path_exists(&path) {
+            let mtime = ctx.get_file_system().getmtime(&path)?;
+            if (now - mtime).whole_hours() < ttl_hours {
+                info!("fetch_reference: {path} is up to date");
                 continue;
             }
-        };
+        }
 
-        area_files::write_whole_country(ctx, &response)?;
-        break;
+        info!("fetch_reference: downloading {url} to {path}");
+        // Raw bytes: the reference may be gzipped or in a legacy encoding.
+        let data = ctx
+            .get_network()
+            .download(&url)
+            .context(format!("failed to download {url}"))?;
+        ctx.get_file_system()
+            .write_from_bytes_atomic(&data, &path)?;
     }
+
     Ok(())
 }
 
 /// Performs the update of country-level stats.
-fn update_stats(ctx: &context::Context, overpass: bool) -> anyhow::Result<()> {
-    // Fetch house numbers for the whole country.
-    info!("update_stats: start, updating the whole_country table");
+fn update_stats(
+    ctx: &context::Context,
+    overpass: bool,
+    stats_refcounty: Option<&str>,
+) -> anyhow::Result<()> {
     let statedir = ctx.get_abspath("workdir/stats");
     let now = ctx.get_time().now();
     let format = time::format_description::parse("[year]-[month]-[day]")?;
     let today = now.format(&format)?;
 
+    if let Some(refcounty) = stats_refcounty {
+        if !overpass {
+            // The count comes from overpass, there is nothing else to do.
+            info!("update_stats: not counting refcounty {refcounty}, overpass is disabled");
+            return Ok(());
+        }
+        info!("update_stats: start, counting refcounty {refcounty}");
+        update_stats_refcounty(ctx, refcounty, &statedir, &today)?;
+        info!("update_stats: end");
+        return Ok(());
+    }
+
+    // Fetch house numbers for the whole country.
+    info!("update_stats: start, updating the whole_country table");
+
     if overpass {
         update_stats_overpass(ctx)?;
     }
@@ -459,6 +1153,8 @@ fn update_stats(ctx: &context::Context, overpass: bool) -> anyhow::Result<()> {
     info!("update_stats: updating refcount");
     update_stats_refcount(ctx, &statedir)?;
     stats::update_invalid_addr_cities(ctx)?;
+    info!("update_stats: pruning old artifacts");
+    prune_stats_artifacts(ctx, &statedir).context("prune_stats_artifacts() failed")?;
 
     info!("update_stats: generating json");
     let json_path = format!("{}/stats.json", &statedir);
@@ -469,6 +1165,47 @@ fn update_stats(ctx: &context::Context, overpass: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Counts what happened to the relations in one phase of a cron run.
+#[derive(Default, serde::Serialize)]
+struct PhaseSummary {
+    updated: usize,
+    skipped: usize,
+    failed: Vec<String>,
+}
+
+/// Counts what happened to the relations in the phases of a cron run.
+#[derive(Default, serde::Serialize)]
+struct RunSummary {
+    osm_streets: PhaseSummary,
+    osm_housenumbers: PhaseSummary,
+    ref_housenumbers: PhaseSummary,
+    missing_streets: PhaseSummary,
+    missing_housenumbers: PhaseSummary,
+    additional_streets: PhaseSummary,
+    /// Problems found by the check-queries or check-references mode.
+    check_problems: Vec<String>,
+}
+
+impl RunSummary {
+    /// Gets the sorted names of the relations which could not be updated in some phase.
+    fn get_failed(&self) -> Vec<String> {
+        let mut ret: Vec<String> = [
+            &self.osm_streets,
+            &self.osm_housenumbers,
+            &self.ref_housenumbers,
+            &self.missing_streets,
+            &self.missing_housenumbers,
+            &self.additional_streets,
+        ]
+        .iter()
+        .flat_map(|phase| phase.failed.clone())
+        .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+}
+
 /// Performs the actual nightly task.
 fn our_main_inner(
     ctx: &context::Context,
@@ -476,17 +1213,38 @@ fn our_main_inner(
     mode: &String,
     update: bool,
     overpass: bool,
-) -> anyhow::Result<()> {
+    stats_refcounty: Option<&str>,
+    runtime: &RuntimeBudget,
+) -> anyhow::Result<RunSummary> {
+    let mut summary = RunSummary::default();
     if mode == "all" || mode == "stats" {
-        update_stats(ctx, overpass).context("update_stats failed")?;
+        update_stats(ctx, overpass, stats_refcounty).context("update_stats failed")?;
     }
     if mode == "all" || mode == "relations" {
-        update_osm_streets(ctx, relations, update)?;
-        update_osm_housenumbers(ctx, relations, update)?;
-        update_ref_housenumbers(ctx, relations, update)?;
-        update_missing_streets(relations, update)?;
-        update_missing_housenumbers(relations, update)?;
-        update_additional_streets(ctx, relations, update)?;
+        summary.osm_streets = update_osm_streets(ctx, relations, update, runtime)?;
+        summary.osm_housenumbers = update_osm_housenumbers(ctx, relations, update, runtime)?;
+        summary.ref_housenumbers = update_ref_housenumbers(ctx, relations, update, runtime)?;
+        summary.missing_streets = update_missing_streets(relations, update, runtime)?;
+        summary.missing_housenumbers =
+            update_missing_housenumbers(ctx, relations, update, runtime)?;
+        summary.additional_streets = update_additional_streets(ctx, relations, update, runtime)?;
+    }
+    if mode == "warm-cache" {
+        warm_cache(relations)?;
+    }
+    if mode == "check-refstreets" {
+        check_refstreets(ctx, relations)?;
+    }
+    if mode == "check-queries" {
+        summary.check_problems = check_queries(relations)?;
+    }
+    if mode == "check-references" {
+        summary.check_problems = check_references(ctx, relations)?;
+    }
+    if mode == "export-jsonl" {
+        let format = time::format_description::parse("[year]-[month]-[day]")?;
+        let today = ctx.get_time().now().format(&format)?;
+        export_whole_country_jsonl(ctx, &today)?;
     }
 
     let pid = std::process::id();
@@ -501,20 +1259,40 @@ fn our_main_inner(
         }
     }
 
-    ctx.get_unit().make_error()
+    ctx.get_unit().make_error()?;
+    Ok(summary)
+}
+
+/// Decides if inactive relations should be updated as well: only once a month, by default.
+fn should_update_inactive(ctx: &context::Context) -> anyhow::Result<bool> {
+    if ctx.get_ini().get_cron_update_inactive() {
+        return Ok(true);
+    }
+
+    let now = ctx.get_time().now();
+    Ok(now.date().day() == ctx.get_ini().get_cron_inactive_refresh_day()?)
 }
 
-/// Inner main() that is allowed to fail.
+/// Exit code of a run which finished, but failed to update some of the relations.
+pub const EXIT_RELATIONS_FAILED: i32 = 2;
+
+/// Exit code of a check-queries or check-references run which found problems.
+pub const EXIT_CHECK_PROBLEMS: i32 = 3;
+
+/// Inner main() that is allowed to fail, returns the exit code.
 pub fn our_main(
     argv: &[String],
-    _stream: &mut dyn Write,
+    stream: &mut dyn Write,
     ctx: &context::Context,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i32> {
     let mut relations = areas::Relations::new(ctx)?;
 
     let refcounty = clap::Arg::new("refcounty")
         .long("refcounty")
         .help("limit the list of relations to a given refcounty");
+    let stats_refcounty = clap::Arg::new("stats-refcounty")
+        .long("stats-refcounty")
+        .help("when updating stats, only count the house numbers of the given refcounty");
     let refsettlement = clap::Arg::new("refsettlement")
         .long("refsettlement")
         .help("limit the list of relations to a given refsettlement");
@@ -529,29 +1307,96 @@ pub fn our_main(
     let mode = clap::Arg::new("mode")
         .long("mode")
         .default_value("relations")
-        .help("only perform the given sub-task or all of them [all, stats or relations]");
+        .help("only perform the given sub-task or all of them [all, stats or relations], or just fill the caches [warm-cache], or export the whole-country data as JSON Lines [export-jsonl], or warn about refstreets keys missing from OSM [check-refstreets], or check the syntax of the Overpass queries [check-queries], or warn about relations not covered by the reference data [check-references]");
     let no_overpass = clap::Arg::new("no-overpass") // default: true
         .long("no-overpass")
         .action(clap::ArgAction::SetTrue)
         .help("when updating stats, don't perform any overpass update");
+    let invalidate_cache = clap::Arg::new("invalidate-cache")
+        .long("invalidate-cache")
+        .help("only drop the cached output of the given relation");
+    let below = clap::Arg::new("below")
+        .long("below")
+        .value_parser(clap::value_parser!(f64))
+        .help("only list the relations with a house number coverage below the given percent");
+    let backfill = clap::Arg::new("backfill")
+        .long("backfill")
+        .num_args(2)
+        .value_names(["START", "END"])
+        .help("only fetch the stats of the past days between START and END (YYYY-MM-DD)");
+    let force = clap::Arg::new("force")
+        .long("force")
+        .action(clap::ArgAction::SetTrue)
+        .help("update relations marked as done, too; when backfilling stats, fetch the days which have a count already");
+    let fetch_reference_arg = clap::Arg::new("fetch-reference")
+        .long("fetch-reference")
+        .action(clap::ArgAction::SetTrue)
+        .help("download the reference files from their configured URLs first");
+    let max_runtime_minutes = clap::Arg::new("max-runtime-minutes")
+        .long("max-runtime-minutes")
+        .value_parser(clap::value_parser!(i64))
+        .help("don't start updating new relations after the given number of minutes");
+    let html_only = clap::Arg::new("html-only")
+        .long("html-only")
+        .action(clap::ArgAction::SetTrue)
+        .help("only regenerate the cached missing house numbers HTML, reusing the analysis");
+    let write_summary = clap::Arg::new("write-summary")
+        .long("write-summary")
+        .action(clap::ArgAction::SetTrue)
+        .help("write the summary of the run to workdir/cron-summary.json");
     let args = [
         refcounty,
+        stats_refcounty,
         refsettlement,
         refarea,
         no_update,
         mode,
         no_overpass,
+        invalidate_cache,
+        write_summary,
+        html_only,
+        fetch_reference_arg,
+        max_runtime_minutes,
+        below,
+        backfill,
+        force,
     ];
     let app = clap::Command::new("osm-gimmisn");
     let args = app.args(&args).try_get_matches_from(argv)?;
 
+    if let Some(threshold) = args.get_one::<f64>("below") {
+        for relation_name in relations.get_relations_below_percent(*threshold)? {
+            stream.write_all(format!("{relation_name}\n").as_bytes())?;
+        }
+        return Ok(0);
+    }
+
+    if let Some(relation_name) = args.get_one::<String>("invalidate-cache") {
+        let relation = relations.get_relation(relation_name)?;
+        cache::invalidate_relation(&relation)?;
+        info!("main: invalidated the cache of {relation_name}");
+        return Ok(0);
+    }
+
+    if let Some(dates) = args.get_many::<String>("backfill") {
+        let dates: Vec<&String> = dates.collect();
+        let force = args.get_one::<bool>("force").unwrap();
+        backfill_stats(ctx, dates[0], dates[1], *force)?;
+        return Ok(0);
+    }
+
     let start = ctx.get_time().now();
-    // Query inactive relations once a month.
-    let now = ctx.get_time().now();
-    let first_day_of_month = now.date().day() == 1;
-    relations.activate_all(ctx.get_ini().get_cron_update_inactive() || first_day_of_month);
+    let max_runtime_minutes: Option<&i64> = args.get_one("max-runtime-minutes");
+    let runtime = RuntimeBudget::new(ctx, max_runtime_minutes.copied());
+    if *args.get_one::<bool>("fetch-reference").unwrap() {
+        fetch_reference(ctx).context("fetch_reference failed")?;
+    }
+    relations.activate_all(should_update_inactive(ctx)?);
     relations.activate_new();
     relations.activate_invalid();
+    if *args.get_one::<bool>("force").unwrap() {
+        relations.ignore_done();
+    }
     let refcounty: Option<&String> = args.get_one("refcounty");
     relations.limit_to_refcounty(&refcounty)?;
     // Use map(), which handles optional values.
@@ -559,14 +1404,21 @@ pub fn our_main(
     relations.limit_to_refsettlement(&refsettlement)?;
     let refarea: Option<&String> = args.get_one("refarea");
     relations.limit_to_refarea(&refarea)?;
+    if *args.get_one::<bool>("html-only").unwrap() {
+        update_missing_housenumbers_html(ctx, &mut relations)?;
+        return Ok(0);
+    }
     let update = !args.get_one::<bool>("no-update").unwrap();
     let overpass = !args.get_one::<bool>("no-overpass").unwrap();
-    our_main_inner(
+    let summary = our_main_inner(
         ctx,
         &mut relations,
         args.get_one("mode").unwrap(),
         update,
         overpass,
+        args.get_one::<String>("stats-refcounty")
+            .map(|i| i.as_str()),
+        &runtime,
     )
     .context("our_main_inner failed")?;
     let duration = ctx.get_time().now() - start;
@@ -575,14 +1427,47 @@ pub fn our_main(
     let hours = duration.whole_hours();
     let duration = format!("{hours}:{minutes:0>2}:{seconds:0>2}");
     info!("main: finished in {duration}");
+    let summary_json = serde_json::to_string(&summary)?;
+    info!("main: summary: {summary_json}");
+    if *args.get_one::<bool>("write-summary").unwrap() {
+        let path = format!("{}/cron-summary.json", ctx.get_ini().get_workdir());
+        ctx.get_file_system()
+            .write_from_string(&summary_json, &path)?;
+    }
+    let failed = summary.get_failed();
+    if !failed.is_empty() {
+        error!("main: failed to update {}", failed.join(", "));
+        return Ok(EXIT_RELATIONS_FAILED);
+    }
+    if !summary.check_problems.is_empty() {
+        error!("main: found {} problems", summary.check_problems.len());
+        return Ok(EXIT_CHECK_PROBLEMS);
+    }
 
-    Ok(())
+    Ok(0)
+}
+
+/// Tries to make sure that only one cron instance works with the workdir at the same time.
+fn try_lock_instance(ctx: &context::Context) -> anyhow::Result<Option<Box<dyn std::any::Any>>> {
+    let path = format!("{}/cron.lock", ctx.get_ini().get_workdir());
+    ctx.get_file_system().try_lock(&path)
 }
 
 /// Similar to plain main(), but with an interface that allows testing.
 pub fn main(argv: &[String], stream: &mut dyn Write, ctx: &context::Context) -> i32 {
+    let _lock = match try_lock_instance(ctx) {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            info!("main: another instance is running");
+            return 0;
+        }
+        Err(err) => {
+            error!("main: failed to lock: {err:?}");
+            return 1;
+        }
+    };
     match our_main(argv, stream, ctx) {
-        Ok(_) => 0,
+        Ok(value) => value,
         Err(err) => {
             error!("main: unhandled error: {err:?}");
             1
@@ -13,6 +13,7 @@
 use super::*;
 use context::FileSystem;
 use std::cell::RefCell;
+use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::rc::Rc;
@@ -40,6 +41,24 @@ fn test_overpass_sleep_no_sleep() {
     assert_eq!(time.get_sleep(), 0);
 }
 
+/// Tests add_jitter(): the jittered sleep is deterministic for a seed and within bounds.
+#[test]
+fn test_add_jitter() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    assert_eq!(add_jitter(&ctx, 100), 100);
+
+    context::tests::set_test_ini_extra(&mut ctx, "overpass_sleep_jitter = '0.5'\n").unwrap();
+    let mut sleeps: Vec<u64> = Vec::new();
+    for _ in 0..2 {
+        let random: Rc<dyn context::Random> = Rc::new(context::tests::TestRandom::new(42));
+        ctx.set_random(&random);
+        let sleep = add_jitter(&ctx, 100);
+        assert_eq!((50..=150).contains(&sleep), true);
+        sleeps.push(sleep);
+    }
+    assert_eq!(sleeps[0], sleeps[1]);
+}
+
 /// Tests overpass_sleep(): the case when sleep is needed.
 #[test]
 fn test_overpass_sleep_need_sleep() {
@@ -70,6 +89,48 @@ fn test_overpass_sleep_need_sleep() {
     assert_eq!(time.get_sleep(), 12);
 }
 
+/// Tests overpass_sleep(): the case when the status reports an unreasonably long wait.
+#[test]
+fn test_overpass_sleep_need_sleep_clamped() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-wait-huge.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+
+    overpass_sleep(&ctx);
+
+    let time = ctx
+        .get_time()
+        .as_any()
+        .downcast_ref::<context::tests::TestTime>()
+        .unwrap();
+    // 86401 is clamped to the default limit.
+    assert_eq!(time.get_sleep(), 600);
+}
+
+/// Tests should_retry_error(): network errors are retried, parse errors are not.
+#[test]
+fn test_should_retry_error() {
+    assert!(should_retry_error(&OsmGimmisnError::Network(
+        "connection refused".into()
+    )));
+    assert!(!should_retry_error(&OsmGimmisnError::Parse(
+        "unexpected token".into()
+    )));
+}
+
 /// Tests update_ref_housenumbers().
 #[test]
 fn test_update_ref_housenumbers() {
@@ -138,12 +199,24 @@ fn test_update_ref_housenumbers() {
     }
     let mut relations = areas::Relations::new(&ctx).unwrap();
 
-    update_ref_housenumbers(&ctx, &mut relations, /*update=*/ true).unwrap();
+    update_ref_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let mtime = ctx.get_file_system().getmtime(&path).unwrap();
     assert!(mtime > time::OffsetDateTime::UNIX_EPOCH);
 
-    update_ref_housenumbers(&ctx, &mut relations, /*update=*/ false).unwrap();
+    update_ref_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     assert_eq!(ctx.get_file_system().getmtime(&path).unwrap(), mtime);
     let actual = context::tests::TestFileSystem::get_content(&ref_file1);
@@ -254,12 +327,24 @@ fn test_update_missing_housenumbers() {
     let expected: String = "0.00".into();
     let relation = relations.get_relation("gazdagret").unwrap();
 
-    update_missing_housenumbers(&mut relations, /*update=*/ true).unwrap();
+    update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let expected_mtime = relation.get_osm_housenumber_coverage_mtime().unwrap();
     assert!(expected_mtime > time::OffsetDateTime::UNIX_EPOCH);
 
-    update_missing_housenumbers(&mut relations, /*update=*/ false).unwrap();
+    update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let actual_mtime = relation.get_osm_housenumber_coverage_mtime().unwrap();
     assert_eq!(actual_mtime, expected_mtime);
@@ -270,6 +355,267 @@ fn test_update_missing_housenumbers() {
     assert_eq!(relation2.has_osm_housenumber_coverage().unwrap(), false);
 }
 
+/// Tests update_missing_housenumbers(): runs on different days are recorded in the history.
+#[test]
+fn test_update_missing_housenumbers_history() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_housenumbers = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_housenumbers,
+            ),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    file_system
+        .write_from_string(
+            "Tűzkő utca\t1\t",
+            &ctx.get_abspath("workdir/street-housenumbers-reference-gazdagret.lst"),
+        )
+        .unwrap();
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            "insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            ["gazdagret", "1", "Tűzkő utca", "9", "", "", "", "", "", "", "", "", "", "node"],
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap();
+
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        update_missing_housenumbers(
+            &ctx,
+            &mut relations,
+            /*update=*/ true,
+            &RuntimeBudget::new(&ctx, None),
+        )
+        .unwrap();
+        // Same day: overwrites.
+        update_missing_housenumbers(
+            &ctx,
+            &mut relations,
+            /*update=*/ true,
+            &RuntimeBudget::new(&ctx, None),
+        )
+        .unwrap();
+    }
+    let time = context::tests::TestTime::new(2020, 5, 11);
+    let time_rc: Rc<dyn context::Time> = Rc::new(time);
+    ctx.set_time(&time_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    let relation = relations.get_relation("gazdagret").unwrap();
+    let history = relation.get_osm_housenumber_coverage_history().unwrap();
+    assert_eq!(
+        history,
+        vec![
+            ("2020-05-10".to_string(), "0.00".to_string()),
+            ("2020-05-11".to_string(), "0.00".to_string())
+        ]
+    );
+    let content = ctx
+        .get_file_system()
+        .read_to_string(&ctx.get_abspath("workdir/gazdagret.percent.log"))
+        .unwrap();
+    assert_eq!(content, "2020-05-10\t0.00\n2020-05-11\t0.00\n");
+}
+
+/// Tests update_missing_housenumbers(): a relation with unchanged inputs is skipped.
+#[test]
+fn test_update_missing_housenumbers_unchanged() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_housenumbers = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_housenumbers,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    let ref_path = ctx.get_abspath("workdir/street-housenumbers-reference-gazdagret.lst");
+    file_system
+        .write_from_string("Tűzkő utca\t1\t", &ref_path)
+        .unwrap();
+    ctx.set_file_system(&file_system);
+    stats::set_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    let summary = update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+    assert_eq!(summary.updated, 1);
+
+    let summary = update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    assert_eq!(summary.updated, 0);
+    assert_eq!(summary.skipped, 1);
+
+    // A changed reference is an input change.
+    file_system
+        .write_from_string("Tűzkő utca\t1\t\nTűzkő utca\t2\t", &ref_path)
+        .unwrap();
+    let summary = update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+    assert_eq!(summary.updated, 1);
+}
+
+/// Tests update_missing_housenumbers(): a drop in the coverage is recorded as a regression.
+#[test]
+fn test_update_missing_housenumbers_regression() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_housenumbers = context::tests::TestFileSystem::make_file();
+    let regressions = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_housenumbers,
+            ),
+            ("workdir/regressions.json", &regressions),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    file_system
+        .write_from_string(
+            "Tűzkő utca\t1\t",
+            &ctx.get_abspath("workdir/street-housenumbers-reference-gazdagret.lst"),
+        )
+        .unwrap();
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            "insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            ["gazdagret", "1", "Tűzkő utca", "9", "", "", "", "", "", "", "", "", "", "node"],
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    // The previous run had 50%, now the only house number is missing.
+    relations
+        .get_relation("gazdagret")
+        .unwrap()
+        .set_osm_housenumber_coverage("50.00")
+        .unwrap();
+
+    update_missing_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    let actual: serde_json::Value =
+        serde_json::from_str(&context::tests::TestFileSystem::get_content(&regressions)).unwrap();
+    assert_eq!(
+        actual,
+        serde_json::json!([
+            {"relation": "gazdagret", "date": "2020-05-10", "old": 50.0, "new": 0.0},
+        ])
+    );
+}
+
+/// Tests write_regressions(): the oldest entries are dropped once the limit is reached.
+#[test]
+fn test_write_regressions_limit() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let old: Vec<serde_json::Value> = (0..REGRESSIONS_LIMIT)
+        .map(|i| serde_json::json!({"relation": format!("r{i}"), "date": "2020-05-09", "old": 50.0, "new": 0.0}))
+        .collect();
+    let regressions = context::tests::TestFileSystem::write_json_to_file(&serde_json::json!(old));
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/regressions.json", &regressions)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    write_regressions(
+        &ctx,
+        vec![Regression {
+            relation: "gazdagret".into(),
+            date: "2020-05-10".into(),
+            old: 50.0,
+            new: 0.0,
+        }],
+    )
+    .unwrap();
+
+    let actual: Vec<serde_json::Value> =
+        serde_json::from_str(&context::tests::TestFileSystem::get_content(&regressions)).unwrap();
+    assert_eq!(actual.len(), REGRESSIONS_LIMIT);
+    assert_eq!(actual[0]["relation"], "r1");
+    assert_eq!(actual[REGRESSIONS_LIMIT - 1]["relation"], "gazdagret");
+}
+
 /// Tests update_missing_streets().
 #[test]
 fn test_update_missing_streets() {
@@ -333,12 +679,22 @@ fn test_update_missing_streets() {
     let expected: String = "50.00".into();
     let relation = relations.get_relation("gazdagret").unwrap();
 
-    update_missing_streets(&mut relations, /*update=*/ true).unwrap();
+    update_missing_streets(
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let expected_mtime = relation.get_osm_street_coverage_mtime().unwrap();
     assert!(expected_mtime > time::OffsetDateTime::UNIX_EPOCH);
 
-    update_missing_streets(&mut relations, /*update=*/ false).unwrap();
+    update_missing_streets(
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let actual_mtime = relation.get_osm_street_coverage_mtime().unwrap();
     assert_eq!(actual_mtime, expected_mtime);
@@ -447,10 +803,22 @@ fn test_update_additional_streets() {
     }
     let mut relations = areas::Relations::new(&ctx).unwrap();
     let expected: String = "1".into();
-    update_additional_streets(&ctx, &mut relations, /*update=*/ true).unwrap();
+    update_additional_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
     let mtime = file_system_rc.getmtime(&path1).unwrap();
 
-    update_additional_streets(&ctx, &mut relations, /*update=*/ false).unwrap();
+    update_additional_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     assert_eq!(file_system_rc.getmtime(&path1).unwrap(), mtime);
     let conn = ctx.get_database_connection().unwrap();
@@ -570,14 +938,26 @@ fn test_update_osm_housenumbers() {
         .unwrap()
         .len();
 
-    update_osm_housenumbers(&ctx, &mut relations, /*update=*/ true).unwrap();
+    update_osm_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let mtime = stats::get_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap();
 
-    update_osm_housenumbers(&ctx, &mut relations, /*update=*/ false).unwrap();
-
-    assert_eq!(
-        stats::get_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap(),
+    update_osm_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    assert_eq!(
+        stats::get_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap(),
         mtime
     );
     let actual = relations
@@ -646,7 +1026,13 @@ fn test_update_osm_housenumbers_http_error() {
     let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
     ctx.set_file_system(&file_system_rc);
     let mut relations = areas::Relations::new(&ctx).unwrap();
-    update_osm_housenumbers(&ctx, &mut relations, /*update=*/ true).unwrap();
+    update_osm_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
     // Make sure that in case we keep getting errors we give up at some stage and
     // leave the last state unchanged.
     assert_eq!(
@@ -661,6 +1047,82 @@ fn test_update_osm_housenumbers_http_error() {
     );
 }
 
+/// Tests update_osm_housenumbers(): a near-empty response is rejected and retried.
+#[test]
+fn test_update_osm_housenumbers_too_small() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    overpass_template
+        .borrow_mut()
+        .write_all(b"housenr aaa @RELATION@ bbb @AREA@ ccc\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "data/street-housenumbers-template.overpassql",
+                &overpass_template,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-truncated.json",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-housenumbers-gazdagret.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let summary = update_osm_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    // The first, truncated response was not written, the retry was.
+    assert_eq!(summary.updated, 1);
+    assert_eq!(summary.failed.is_empty(), true);
+    let conn = ctx.get_database_connection().unwrap();
+    let count: i64 = conn
+        .query_row("select count(*) from osm_housenumbers", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(count > 0, true);
+}
+
 /// Tests update_osm_housenumbers(): the case when we ask for JSON but get XML.
 #[test]
 fn test_update_osm_housenumbers_xml_as_json() {
@@ -715,7 +1177,13 @@ fn test_update_osm_housenumbers_xml_as_json() {
         .unwrap();
     }
 
-    update_osm_housenumbers(&ctx, &mut relations, /*update=*/ true).unwrap();
+    update_osm_housenumbers(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     // Wanted JSON, got XML, make sure the db is left unchanged.
     let conn = ctx.get_database_connection().unwrap();
@@ -773,12 +1241,24 @@ fn test_update_osm_streets() {
     ctx.set_file_system(&file_system_rc);
     let mut relations = areas::Relations::new(&ctx).unwrap();
 
-    update_osm_streets(&ctx, &mut relations, /*update=*/ true).unwrap();
+    update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     let mtime = stats::get_sql_mtime(&ctx, "streets/gazdagret").unwrap();
     assert!(mtime > time::OffsetDateTime::UNIX_EPOCH);
 
-    update_osm_streets(&ctx, &mut relations, /*update=*/ false).unwrap();
+    update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
     assert_eq!(
         stats::get_sql_mtime(&ctx, "streets/gazdagret").unwrap(),
@@ -797,9 +1277,9 @@ fn test_update_osm_streets() {
     );
 }
 
-/// Tests update_osm_streets(): the case when we keep getting HTTP errors.
+/// Tests update_osm_streets(): relations marked as done are skipped, unless forced.
 #[test]
-fn test_update_osm_streets_http_error() {
+fn test_update_osm_streets_done() {
     let mut ctx = context::tests::make_test_context().unwrap();
     let routes = vec![
         context::tests::URLRoute::new(
@@ -810,178 +1290,912 @@ fn test_update_osm_streets_http_error() {
         context::tests::URLRoute::new(
             /*url=*/ "https://overpass-api.de/api/interpreter",
             /*data_path=*/ "",
-            /*result_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
         ),
     ];
     let network = context::tests::TestNetwork::new(&routes);
     let network_rc: Rc<dyn context::Network> = Rc::new(network);
     ctx.set_network(network_rc);
-    {
-        let conn = ctx.get_database_connection().unwrap();
-        conn.execute(
-            r#"insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
-            ["gazdagret", "1", "Tűzkő utca", "", "", "", "", ""],
-        )
-        .unwrap();
-    }
     let yamls_cache = serde_json::json!({
         "relations.yaml": {
             "gazdagret": {
                 "osmrelation": 42,
+                "done": true,
             },
         },
     });
     let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
-    let overpass_template = context::tests::TestFileSystem::make_file();
+    let template_value = context::tests::TestFileSystem::make_file();
+    template_value
+        .borrow_mut()
+        .write_all(b"aaa @RELATION@ bbb @AREA@ ccc\n")
+        .unwrap();
     let files = context::tests::TestFileSystem::make_files(
         &ctx,
         &[
             ("data/yamls.cache", &yamls_cache_value),
-            ("data/streets-template.overpassql", &overpass_template),
+            ("data/streets-template.overpassql", &template_value),
         ],
     );
-    let mut file_system = context::tests::TestFileSystem::new();
-    file_system.set_files(&files);
-    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
-    ctx.set_file_system(&file_system_rc);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into mtimes (page, last_modified) values ('streets/gazdagret', '0');",
+        )
+        .unwrap();
+    }
     let mut relations = areas::Relations::new(&ctx).unwrap();
 
-    update_osm_streets(&ctx, &mut relations, /*update=*/ true).unwrap();
+    let summary = update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
 
-    // Make sure that in case we keep getting errors we give up at some stage and
-    // leave the last state unchanged.
+    // Done, so not updated.
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(summary.updated, 0);
     assert_eq!(
-        relations
-            .get_relation("gazdagret")
-            .unwrap()
-            .get_files()
-            .get_osm_json_streets(&ctx)
-            .unwrap()
-            .len(),
-        1
+        stats::get_sql_mtime(&ctx, "streets/gazdagret").unwrap(),
+        time::OffsetDateTime::UNIX_EPOCH
+    );
+
+    relations.ignore_done();
+    let summary = update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    // Forced, so updated.
+    assert_eq!(summary.updated, 1);
+    assert!(
+        stats::get_sql_mtime(&ctx, "streets/gazdagret").unwrap() > time::OffsetDateTime::UNIX_EPOCH
     );
 }
 
-/// Tests update_osm_streets(): the case when we ask for JSON but get XML.
+/// Tests check_queries().
 #[test]
-fn test_update_osm_streets_xml_as_json() {
+fn test_check_queries() {
     let mut ctx = context::tests::make_test_context().unwrap();
     let yamls_cache = serde_json::json!({
         "relations.yaml": {
             "gazdagret": {
                 "osmrelation": 42,
             },
+            "budafok": {
+                "osmrelation": 43,
+            },
+        },
+        "relation-budafok.yaml": {
+            "osm-streets-query": "streets-template-budafok.overpassql",
         },
     });
     let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
-    let template_value = context::tests::TestFileSystem::make_file();
-    template_value
+    let streets_template = context::tests::TestFileSystem::make_file();
+    streets_template
         .borrow_mut()
-        .write_all(b"aaa @RELATION@ bbb @AREA@ ccc\n")
+        .write_all(&std::fs::read("data/streets-template.overpassql").unwrap())
+        .unwrap();
+    let housenumbers_template = context::tests::TestFileSystem::make_file();
+    housenumbers_template
+        .borrow_mut()
+        .write_all(&std::fs::read("data/street-housenumbers-template.overpassql").unwrap())
+        .unwrap();
+    let bad_template = context::tests::TestFileSystem::make_file();
+    bad_template
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)];\narea(@AREA@->.searchArea;\nout;\n")
         .unwrap();
     let files = context::tests::TestFileSystem::make_files(
         &ctx,
         &[
             ("data/yamls.cache", &yamls_cache_value),
-            ("data/streets-template.overpassql", &template_value),
+            ("data/streets-template.overpassql", &streets_template),
+            (
+                "data/street-housenumbers-template.overpassql",
+                &housenumbers_template,
+            ),
+            ("data/streets-template-budafok.overpassql", &bad_template),
         ],
     );
     let file_system = context::tests::TestFileSystem::from_files(&files);
     ctx.set_file_system(&file_system);
-    let routes = vec![
-        context::tests::URLRoute::new(
-            /*url=*/ "https://overpass-api.de/api/status",
-            /*data_path=*/ "",
-            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
-        ),
-        context::tests::URLRoute::new(
-            /*url=*/ "https://overpass-api.de/api/interpreter",
-            /*data_path=*/ "",
-            /*result_path=*/ "src/fixtures/network/overpass.xml",
-        ),
-    ];
-    let network = context::tests::TestNetwork::new(&routes);
-    let network_rc: Rc<dyn context::Network> = Rc::new(network);
-    ctx.set_network(network_rc);
     let mut relations = areas::Relations::new(&ctx).unwrap();
-    {
-        let conn = ctx.get_database_connection().unwrap();
-        conn.execute(
-            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            ["myrelation", &"1".to_string(), "myname", "myhighway", "myservice", "mysurface", "myleisure", "way"],
-        )
-        .unwrap();
-    }
 
-    update_osm_streets(&ctx, &mut relations, /*update=*/ true).unwrap();
+    let problems = check_queries(&mut relations).unwrap();
 
-    // Wanted JSON, got XML, make sure the db is left unchanged.
-    let conn = ctx.get_database_connection().unwrap();
-    let mut stmt = conn.prepare("select count(*) from osm_streets").unwrap();
-    let mut rows = stmt.query([]).unwrap();
-    let row = rows.next().unwrap().unwrap();
-    let count: i64 = row.get(0).unwrap();
-    assert_eq!(count, 1);
+    // The generated queries of gazdagret are fine, the custom streets query of budafok is not.
+    assert_eq!(
+        problems,
+        vec!["budafok: streets query: unclosed '(' at offset 21".to_string()]
+    );
 }
 
-/// Tests update_stats().
+/// Tests main(): problems found by check-queries result in a non-zero exit code.
 #[test]
-fn test_update_stats() {
+fn test_main_check_queries() {
     let mut ctx = context::tests::make_test_context().unwrap();
-    let routes = vec![
-        context::tests::URLRoute::new(
-            /*url=*/ "https://overpass-api.de/api/status",
-            /*data_path=*/ "",
-            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
-        ),
-        context::tests::URLRoute::new(
-            /*url=*/ "https://overpass-api.de/api/interpreter",
-            /*data_path=*/ "",
-            /*result_path=*/ "src/fixtures/network/overpass-stats.json",
-        ),
-    ];
-    let network = context::tests::TestNetwork::new(&routes);
-    let network_rc: Rc<dyn context::Network> = Rc::new(network);
-    ctx.set_network(network_rc);
-
-    let ref_count = context::tests::TestFileSystem::make_file();
-    let stats_json = context::tests::TestFileSystem::make_file();
-    let overpass_template = context::tests::TestFileSystem::make_file();
-    overpass_template
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 43,
+            },
+        },
+        "relation-budafok.yaml": {
+            "osm-streets-query": "streets-template-budafok.overpassql",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let housenumbers_template = context::tests::TestFileSystem::make_file();
+    housenumbers_template
         .borrow_mut()
-        .write_all("first line\nsecond line\n".as_bytes())
+        .write_all(&std::fs::read("data/street-housenumbers-template.overpassql").unwrap())
+        .unwrap();
+    let bad_template = context::tests::TestFileSystem::make_file();
+    bad_template
+        .borrow_mut()
+        .write_all(b"[out:csv(::id)];\narea(@AREA@->.searchArea;\nout;\n")
         .unwrap();
     let files = context::tests::TestFileSystem::make_files(
         &ctx,
         &[
-            ("workdir/stats/ref.count", &ref_count),
-            ("workdir/stats/stats.json", &stats_json),
+            ("data/yamls.cache", &yamls_cache_value),
             (
-                "data/street-housenumbers-hungary.overpassql",
-                &overpass_template,
+                "data/street-housenumbers-template.overpassql",
+                &housenumbers_template,
             ),
+            ("data/streets-template-budafok.overpassql", &bad_template),
         ],
     );
-    let mut file_system = context::tests::TestFileSystem::new();
-    file_system.set_files(&files);
-    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
-    ctx.set_file_system(&file_system_rc);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let argv = vec![
+        "".to_string(),
+        "--mode".to_string(),
+        "check-queries".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
 
-    update_stats(&ctx, /*overpass=*/ true).unwrap();
+    let ret = main(&argv, &mut buf, &ctx);
 
-    let conn = ctx.get_database_connection().unwrap();
-    let last_modified: String = conn
-        .query_row(
-            "select last_modified from mtimes where page = ?1",
-            ["whole-country/osm-base"],
-            |row| row.get(0),
-        )
-        .unwrap();
-    assert!(!last_modified.is_empty());
+    assert_eq!(ret, EXIT_CHECK_PROBLEMS);
+}
 
-    let num_ref: i64 = ctx
-        .get_file_system()
-        .read_to_string(&ctx.get_abspath("workdir/stats/ref.count"))
+/// Tests main(): problems found by check-references result in a non-zero exit code.
+#[test]
+fn test_main_check_references() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 43,
+                "refcounty": "01",
+                "refsettlement": "999",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let argv = vec![
+        "".to_string(),
+        "--mode".to_string(),
+        "check-references".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, EXIT_CHECK_PROBLEMS);
+}
+
+/// Tests check_references().
+#[test]
+fn test_check_references() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+            "budafok": {
+                "osmrelation": 43,
+                "refcounty": "01",
+                "refsettlement": "999",
+            },
+            "ujbuda": {
+                "osmrelation": 44,
+                "refcounty": "01",
+                "refsettlement": "012",
+                "missing-streets": "no",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('01', '011', 'Tűzkő utca', '1', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('01', '012', 'Hamzsabégi út', '1', '');
+             insert into ref_streets (county_code, settlement_code, street) values ('01', '011', 'Tűzkő utca');",
+        )
+        .unwrap();
+    }
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let warnings = check_references(&ctx, &mut relations).unwrap();
+
+    // gazdagret is covered, budafok references an unknown settlement, ujbuda has no reference
+    // streets, but doesn't check them.
+    assert_eq!(
+        warnings,
+        vec![
+            "budafok: no reference house numbers for refcounty '01', refsettlement '999'"
+                .to_string(),
+            "budafok: no reference streets for refcounty '01', refsettlement '999'".to_string(),
+        ]
+    );
+}
+
+/// Tests check_refstreets().
+#[test]
+fn test_check_refstreets() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "refstreets": {
+                "Bogus utca": "Tűzkő utca",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            r#"insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+            ["gazdagret", "1", "Tűzkő utca", "", "", "", "", ""],
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&ctx, "streets/gazdagret").unwrap();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let warnings = check_refstreets(&ctx, &mut relations).unwrap();
+
+    assert_eq!(
+        warnings,
+        vec!["gazdagret: refstreets key 'Bogus utca' is not in the OSM street list".to_string()]
+    );
+}
+
+/// Tests check_refstreets(): relations without a refsettlement are skipped.
+#[test]
+fn test_check_refstreets_no_refsettlement() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "refstreets": {
+                "Bogus utca": "Tűzkő utca",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    stats::set_sql_mtime(&ctx, "streets/gazdagret").unwrap();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let warnings = check_refstreets(&ctx, &mut relations).unwrap();
+
+    assert_eq!(warnings.is_empty(), true);
+}
+
+/// Tests update_osm_streets(): the summary counts an up to date relation as skipped.
+#[test]
+fn test_update_osm_streets_summary() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let routes = vec![context::tests::URLRoute::new(
+        /*url=*/ "https://overpass-api.de/api/interpreter",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+    )];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 42,
+            },
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    stats::set_sql_mtime(&ctx, "streets/budafok").unwrap();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let summary = update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ false,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    assert_eq!(summary.updated, 1);
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(summary.failed.is_empty(), true);
+}
+
+/// Tests the runtime budget: once it's exceeded, the next phase stops early, keeping the work of
+/// the previous one.
+#[test]
+fn test_runtime_budget_exceeded() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let test_time = Rc::new(context::tests::TestTime::new(2020, 5, 10));
+    let time_rc: Rc<dyn context::Time> = test_time.clone();
+    ctx.set_time(&time_rc);
+    let routes = vec![context::tests::URLRoute::new(
+        /*url=*/ "https://overpass-api.de/api/interpreter",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+    )];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    let runtime = RuntimeBudget::new(&ctx, Some(60));
+    let summary = update_osm_streets(&ctx, &mut relations, /*update=*/ true, &runtime).unwrap();
+    assert_eq!(summary.updated, 1);
+
+    test_time.advance(61 * 60);
+    let summary =
+        update_osm_housenumbers(&ctx, &mut relations, /*update=*/ true, &runtime).unwrap();
+
+    // No relation was started: no update and no failure, either.
+    assert_eq!(summary.updated, 0);
+    assert_eq!(summary.skipped, 0);
+    assert_eq!(summary.failed.is_empty(), true);
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "streets/gazdagret").unwrap(),
+        true
+    );
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "housenumbers/gazdagret").unwrap(),
+        false
+    );
+}
+
+/// Tests RuntimeBudget: no limit is given.
+#[test]
+fn test_runtime_budget_unlimited() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let test_time = Rc::new(context::tests::TestTime::new(2020, 5, 10));
+    let time_rc: Rc<dyn context::Time> = test_time.clone();
+    ctx.set_time(&time_rc);
+    let runtime = RuntimeBudget::new(&ctx, None);
+
+    test_time.advance(365 * 24 * 3600);
+
+    assert_eq!(runtime.is_exceeded("test"), false);
+}
+
+/// Tests update_osm_streets(): the case when we keep getting HTTP errors.
+#[test]
+fn test_update_osm_streets_http_error() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            r#"insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+            ["gazdagret", "1", "Tűzkő utca", "", "", "", "", ""],
+        )
+        .unwrap();
+    }
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &overpass_template),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    // Make sure that in case we keep getting errors we give up at some stage and
+    // leave the last state unchanged.
+    assert_eq!(
+        relations
+            .get_relation("gazdagret")
+            .unwrap()
+            .get_files()
+            .get_osm_json_streets(&ctx)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+/// Tests update_osm_streets(): the case when overpass keeps failing across relations.
+#[test]
+fn test_update_osm_streets_failure_budget() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "overpass_global_failure_limit = '3'\n").unwrap();
+    let mut routes = Vec::new();
+    for _ in 0..3 {
+        let mut unavailable = context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+        );
+        unavailable.set_status(503);
+        routes.push(unavailable);
+    }
+    routes.push(context::tests::URLRoute::new(
+        /*url=*/ "https://overpass-api.de/api/interpreter",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+    ));
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 42,
+            },
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+            "ujbuda": {
+                "osmrelation": 43,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &overpass_template),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let summary = update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    // Without the budget, the 4th query of budafok would succeed; instead the phase is aborted
+    // after 3 failures and the remaining relations are not queried.
+    assert_eq!(summary.updated, 0);
+    assert_eq!(
+        summary.failed,
+        vec![
+            "budafok".to_string(),
+            "gazdagret".to_string(),
+            "ujbuda".to_string()
+        ]
+    );
+}
+
+/// Tests update_osm_streets(): the case when the query is rejected with a 400 status code.
+#[test]
+fn test_update_osm_streets_bad_request() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let mut bad_request = context::tests::URLRoute::new(
+        /*url=*/ "https://overpass-api.de/api/interpreter",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+    );
+    bad_request.set_status(400);
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        bad_request,
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            r#"insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+            ["gazdagret", "1", "Tűzkő utca", "", "", "", "", ""],
+        )
+        .unwrap();
+    }
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &overpass_template),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let summary = update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    assert_eq!(summary.updated, 0);
+    assert_eq!(summary.failed, vec!["gazdagret".to_string()]);
+    // Make sure that a client error is not retried: the second, successful response is not used
+    // and the last state is unchanged.
+    assert_eq!(
+        relations
+            .get_relation("gazdagret")
+            .unwrap()
+            .get_files()
+            .get_osm_json_streets(&ctx)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+/// Tests update_osm_streets(): the case when the response fails with a non-retryable error.
+#[test]
+fn test_update_osm_streets_parse_error() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        // Not UTF-8, so a parse error.
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-invalid-utf8.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            r#"insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+            ["gazdagret", "1", "Tűzkő utca", "", "", "", "", ""],
+        )
+        .unwrap();
+    }
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &overpass_template),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let summary = update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    assert_eq!(summary.updated, 0);
+    assert_eq!(summary.failed, vec!["gazdagret".to_string()]);
+    // Make sure that a parse error is not retried: the second, successful response is not used and
+    // the last state is unchanged.
+    assert_eq!(
+        relations
+            .get_relation("gazdagret")
+            .unwrap()
+            .get_files()
+            .get_osm_json_streets(&ctx)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+/// Tests update_osm_streets(): the case when we ask for JSON but get XML.
+#[test]
+fn test_update_osm_streets_xml_as_json() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    template_value
+        .borrow_mut()
+        .write_all(b"aaa @RELATION@ bbb @AREA@ ccc\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass.xml",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            ["myrelation", &"1".to_string(), "myname", "myhighway", "myservice", "mysurface", "myleisure", "way"],
+        )
+        .unwrap();
+    }
+
+    update_osm_streets(
+        &ctx,
+        &mut relations,
+        /*update=*/ true,
+        &RuntimeBudget::new(&ctx, None),
+    )
+    .unwrap();
+
+    // Wanted JSON, got XML, make sure the db is left unchanged.
+    let conn = ctx.get_database_connection().unwrap();
+    let mut stmt = conn.prepare("select count(*) from osm_streets").unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let row = rows.next().unwrap().unwrap();
+    let count: i64 = row.get(0).unwrap();
+    assert_eq!(count, 1);
+}
+
+/// Tests update_stats().
+#[test]
+fn test_update_stats() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-stats.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+
+    let ref_count = context::tests::TestFileSystem::make_file();
+    let stats_json = context::tests::TestFileSystem::make_file();
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    overpass_template
+        .borrow_mut()
+        .write_all("first line\nsecond line\n".as_bytes())
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("workdir/stats/ref.count", &ref_count),
+            ("workdir/stats/stats.json", &stats_json),
+            (
+                "data/street-housenumbers-hungary.overpassql",
+                &overpass_template,
+            ),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    update_stats(
+        &ctx, /*overpass=*/ true, /*stats_refcounty=*/ None,
+    )
+    .unwrap();
+
+    let conn = ctx.get_database_connection().unwrap();
+    let last_modified: String = conn
+        .query_row(
+            "select last_modified from mtimes where page = ?1",
+            ["whole-country/osm-base"],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(!last_modified.is_empty());
+
+    let num_ref: i64 = ctx
+        .get_file_system()
+        .read_to_string(&ctx.get_abspath("workdir/stats/ref.count"))
         .unwrap()
         .trim()
         .parse()
@@ -989,6 +2203,203 @@ fn test_update_stats() {
     assert_eq!(num_ref, 300);
 }
 
+/// Tests backfill_stats(): days without a count are fetched with a date-scoped query.
+#[test]
+fn test_backfill_stats() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let mut routes = Vec::new();
+    for day in ["2020-05-07", "2020-05-09"] {
+        routes.push(context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ));
+        routes.push(context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ &format!("src/fixtures/network/overpass-stats-{day}.overpassql"),
+            /*result_path=*/ "src/fixtures/network/overpass-stats.json",
+        ));
+    }
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    overpass_template
+        .borrow_mut()
+        .write_all("first line\nsecond line\n".as_bytes())
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[(
+            "data/street-housenumbers-hungary.overpassql",
+            &overpass_template,
+        )],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute(
+            "insert into stats_counts (date, count) values (?1, ?2)",
+            ["2020-05-08", "42"],
+        )
+        .unwrap();
+    }
+
+    backfill_stats(&ctx, "2020-05-07", "2020-05-09", /*force=*/ false).unwrap();
+
+    let conn = ctx.get_database_connection().unwrap();
+    let mut stmt = conn
+        .prepare("select date, count from stats_counts order by date")
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut counts: Vec<(String, String)> = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        counts.push((row.get(0).unwrap(), row.get(1).unwrap()));
+    }
+    // 2020-05-08 had a count, so it was not fetched again.
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[0].0, "2020-05-07");
+    assert_eq!(counts[1], ("2020-05-08".to_string(), "42".to_string()));
+    assert_eq!(counts[2].0, "2020-05-09");
+    assert_eq!(counts[0].1, counts[2].1);
+    let topusers: i64 = conn
+        .query_row(
+            "select count(*) from stats_topusers where date = ?1",
+            ["2020-05-07"],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(topusers > 0, true);
+}
+
+/// Tests update_stats(): the case when the stats are limited to a refcounty.
+#[test]
+fn test_update_stats_refcounty() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/status",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-status-happy.txt",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "src/fixtures/network/overpass-stats-refcounty.overpassql",
+            /*result_path=*/ "src/fixtures/network/overpass-stats.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+
+    let yamls_cache = serde_json::json!({
+        "refcounty-names.yaml": {
+            "01": "Budapest",
+        },
+        "relations.yaml": {},
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let count_value = context::tests::TestFileSystem::make_file();
+    let overpass_template = context::tests::TestFileSystem::make_file();
+    overpass_template
+        .borrow_mut()
+        .write_all("first line\narea[name=\"@REFCOUNTY_NAME@\"];\n".as_bytes())
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/stats/2020-05-10-01.count", &count_value),
+            (
+                "data/street-housenumbers-refcounty.overpassql",
+                &overpass_template,
+            ),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    update_stats(
+        &ctx,
+        /*overpass=*/ true,
+        /*stats_refcounty=*/ Some("01"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&count_value),
+        "2\n"
+    );
+    // The whole-country state is not touched.
+    let conn = ctx.get_database_connection().unwrap();
+    let count: i64 = conn
+        .query_row("select count(*) from whole_country", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+/// Tests update_stats(): an unknown refcounty is an error, not an empty area name in the query.
+#[test]
+fn test_update_stats_refcounty_unknown() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "refcounty-names.yaml": {
+            "01": "Budapest",
+        },
+        "relations.yaml": {},
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = update_stats(
+        &ctx,
+        /*overpass=*/ true,
+        /*stats_refcounty=*/ Some("99"),
+    );
+
+    assert_eq!(
+        ret.unwrap_err().to_string(),
+        "unknown refcounty '99', see data/refcounty-names.yaml"
+    );
+}
+
+/// Tests update_stats(): a refcounty is not counted when overpass is disabled.
+#[test]
+fn test_update_stats_refcounty_no_overpass() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    // No routes: any network access would fail.
+    let network = context::tests::TestNetwork::new(&[]);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let count_value = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/stats/2020-05-10-01.count", &count_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    update_stats(
+        &ctx,
+        /*overpass=*/ false,
+        /*stats_refcounty=*/ Some("01"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&count_value),
+        ""
+    );
+}
+
 /// Tests update_stats(): the case when we keep getting HTTP errors.
 #[test]
 fn test_update_stats_http_error() {
@@ -1024,7 +2435,10 @@ fn test_update_stats_http_error() {
     let file_system = context::tests::TestFileSystem::from_files(&files);
     ctx.set_file_system(&file_system);
 
-    update_stats(&ctx, /*overpass=*/ true).unwrap();
+    update_stats(
+        &ctx, /*overpass=*/ true, /*stats_refcounty=*/ None,
+    )
+    .unwrap();
 
     {
         let mut guard = stats_json.borrow_mut();
@@ -1069,7 +2483,10 @@ fn test_update_stats_no_overpass() {
     let file_system = context::tests::TestFileSystem::from_files(&files);
     ctx.set_file_system(&file_system);
 
-    update_stats(&ctx, /*overpass=*/ false).unwrap();
+    update_stats(
+        &ctx, /*overpass=*/ false, /*stats_refcounty=*/ None,
+    )
+    .unwrap();
 
     let time = ctx
         .get_time()
@@ -1085,6 +2502,179 @@ fn test_update_stats_no_overpass() {
     assert_eq!(actual, "300\n");
 }
 
+/// Tests prune_stats_artifacts(): old artifacts are removed, stats.json is kept.
+#[test]
+fn test_prune_stats_artifacts() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "stats_daily_artifact_retention_days = '7'\n")
+        .unwrap();
+    let old_topusers = context::tests::TestFileSystem::make_file();
+    let new_topusers = context::tests::TestFileSystem::make_file();
+    let stats_json = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("workdir/stats/2020-04-01.topusers", &old_topusers),
+            ("workdir/stats/2020-05-09.topusers", &new_topusers),
+            ("workdir/stats/stats.json", &stats_json),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        ctx.get_abspath("workdir/stats/2020-04-01.topusers"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    mtimes.insert(
+        ctx.get_abspath("workdir/stats/2020-05-09.topusers"),
+        Rc::new(RefCell::new(ctx.get_time().now())),
+    );
+    mtimes.insert(
+        ctx.get_abspath("workdir/stats/stats.json"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    prune_stats_artifacts(&ctx, &ctx.get_abspath("workdir/stats")).unwrap();
+
+    let file_system = ctx.get_file_system();
+    assert!(!file_system.path_exists(&ctx.get_abspath("workdir/stats/2020-04-01.topusers")));
+    assert!(file_system.path_exists(&ctx.get_abspath("workdir/stats/2020-05-09.topusers")));
+    assert!(file_system.path_exists(&ctx.get_abspath("workdir/stats/stats.json")));
+}
+
+/// Tests fetch_reference(): an outdated reference file is downloaded again.
+#[test]
+fn test_fetch_reference() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(
+        &mut ctx,
+        "reference_street_url = 'https://example.com/utcak.tsv'\n",
+    )
+    .unwrap();
+    let routes = vec![context::tests::URLRoute::new(
+        /*url=*/ "https://example.com/utcak.tsv",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/reference-street.tsv",
+    )];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let ref_streets = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/refs/utcak_20190514.tsv", &ref_streets)],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        ctx.get_abspath("workdir/refs/utcak_20190514.tsv"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    fetch_reference(&ctx).unwrap();
+
+    let expected = std::fs::read_to_string("src/fixtures/network/reference-street.tsv").unwrap();
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&ref_streets),
+        expected
+    );
+}
+
+/// Tests fetch_reference(): a gzipped reference is written as-is, not decompressed.
+#[test]
+fn test_fetch_reference_gz() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_values(
+        &mut ctx,
+        &[
+            ("reference_street", "workdir/refs/utcak_20190514.tsv.gz"),
+            ("reference_street_url", "https://example.com/utcak.tsv.gz"),
+        ],
+    )
+    .unwrap();
+    let routes = vec![context::tests::URLRoute::new(
+        /*url=*/ "https://example.com/utcak.tsv.gz",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/reference-street.tsv.gz",
+    )];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let ref_streets = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/refs/utcak_20190514.tsv.gz", &ref_streets)],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        ctx.get_abspath("workdir/refs/utcak_20190514.tsv.gz"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    fetch_reference(&ctx).unwrap();
+
+    let expected = std::fs::read("src/fixtures/network/reference-street.tsv.gz").unwrap();
+    let content = ref_streets.borrow().get_ref().clone();
+    assert_eq!(content, expected);
+    // And it can be read back.
+    let path = ctx.get_abspath("workdir/refs/utcak_20190514.tsv.gz");
+    let mut input: &[u8] = &content;
+    let mut read = util::make_reference_reader(&path, &mut input).unwrap();
+    let mut actual = String::new();
+    read.read_to_string(&mut actual).unwrap();
+    assert_eq!(
+        actual,
+        std::fs::read_to_string("src/fixtures/network/reference-street.tsv").unwrap()
+    );
+}
+
+/// Tests fetch_reference(): a fresh reference file is not downloaded again.
+#[test]
+fn test_fetch_reference_fresh() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(
+        &mut ctx,
+        "reference_street_url = 'https://example.com/utcak.tsv'\n",
+    )
+    .unwrap();
+    let ref_streets = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/refs/utcak_20190514.tsv", &ref_streets)],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        ctx.get_abspath("workdir/refs/utcak_20190514.tsv"),
+        Rc::new(RefCell::new(ctx.get_time().now())),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    // No network routes: this would fail if a download was attempted.
+    fetch_reference(&ctx).unwrap();
+
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&ref_streets),
+        ""
+    );
+}
+
 /// Tests our_main().
 #[test]
 fn test_our_main() {
@@ -1187,6 +2777,8 @@ fn test_our_main() {
         /*mode=*/ &"relations".to_string(),
         /*update=*/ true,
         /*overpass=*/ true,
+        /*stats_refcounty=*/ None,
+        &RuntimeBudget::new(&ctx, None),
     )
     .unwrap();
 
@@ -1227,6 +2819,122 @@ fn test_our_main() {
     }
 }
 
+/// Tests main(): the case when a relation fails to update, but others are still processed.
+#[test]
+fn test_main_relation_failed() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let ref_streets = ctx.get_ini().get_reference_street_path().unwrap();
+    util::build_street_reference_index(&ctx, &ref_streets).unwrap();
+    let references = ctx.get_ini().get_reference_housenumber_paths().unwrap();
+    util::build_reference_index(&ctx, &references).unwrap();
+    let mut bad_request = context::tests::URLRoute::new(
+        /*url=*/ "https://overpass-api.de/api/interpreter",
+        /*data_path=*/ "",
+        /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+    );
+    bad_request.set_status(400);
+    let routes = vec![
+        // For update_osm_streets(), budafok.
+        bad_request,
+        // For update_osm_streets(), gazdagret.
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+        ),
+        // For update_osm_housenumbers(), budafok.
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-housenumbers-gazdagret.json",
+        ),
+        // For update_osm_housenumbers(), gazdagret.
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-housenumbers-gazdagret.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    ctx.set_network(network_rc);
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 42,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let summary_value = context::tests::TestFileSystem::make_file();
+    let budafok_ref_value = context::tests::TestFileSystem::make_file();
+    let gazdagret_ref_value = context::tests::TestFileSystem::make_file();
+    let template_value = context::tests::TestFileSystem::make_file();
+    let housenr_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/cron-summary.json", &summary_value),
+            (
+                "workdir/street-housenumbers-reference-budafok.lst",
+                &budafok_ref_value,
+            ),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &gazdagret_ref_value,
+            ),
+            ("data/streets-template.overpassql", &template_value),
+            (
+                "data/street-housenumbers-template.overpassql",
+                &housenr_template,
+            ),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let argv = vec![
+        "".to_string(),
+        "--mode".to_string(),
+        "relations".to_string(),
+        "--write-summary".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, EXIT_RELATIONS_FAILED);
+    let summary = context::tests::TestFileSystem::get_content(&summary_value);
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    assert_eq!(summary["osm_streets"]["updated"], 1);
+    assert_eq!(
+        summary["osm_streets"]["failed"],
+        serde_json::json!(["budafok"])
+    );
+    assert_eq!(summary["osm_housenumbers"]["updated"], 2);
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "streets/budafok").unwrap(),
+        false
+    );
+    // The failure of budafok didn't prevent the update of gazdagret.
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "streets/gazdagret").unwrap(),
+        true
+    );
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+    assert_eq!(relation.has_osm_housenumber_coverage().unwrap(), true);
+}
+
 /// Tests our_main(): the stats case.
 #[test]
 fn test_our_main_stats() {
@@ -1272,6 +2980,8 @@ fn test_our_main_stats() {
         /*mode=*/ &"stats".to_string(),
         /*update=*/ false,
         /*overpass=*/ true,
+        /*stats_refcounty=*/ None,
+        &RuntimeBudget::new(&ctx, None),
     )
     .unwrap();
 
@@ -1324,6 +3034,74 @@ fn test_main() {
     assert_eq!(actual, "300\n");
 }
 
+/// Tests should_update_inactive(): the refresh triggers only on the configured day.
+#[test]
+fn test_should_update_inactive() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    // 2020-05-10 is not the 1st of the month.
+    assert_eq!(should_update_inactive(&ctx).unwrap(), false);
+
+    context::tests::set_test_ini_extra(&mut ctx, "cron_inactive_refresh_day = '10'\n").unwrap();
+    assert_eq!(should_update_inactive(&ctx).unwrap(), true);
+
+    let time = context::tests::TestTime::new(2020, 5, 15);
+    let time_rc: Rc<dyn context::Time> = Rc::new(time);
+    ctx.set_time(&time_rc);
+    assert_eq!(should_update_inactive(&ctx).unwrap(), false);
+
+    context::tests::set_test_ini_extra(
+        &mut ctx,
+        "cron_inactive_refresh_day = '10'\ncron_update_inactive = 'True'\n",
+    )
+    .unwrap();
+    assert_eq!(should_update_inactive(&ctx).unwrap(), true);
+}
+
+/// Tests should_update_inactive(): the refresh triggers once the clock reaches the 1st of the month.
+#[test]
+fn test_should_update_inactive_advance() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let test_time = Rc::new(context::tests::TestTime::new(2020, 4, 30));
+    let time_rc: Rc<dyn context::Time> = test_time.clone();
+    ctx.set_time(&time_rc);
+    assert_eq!(should_update_inactive(&ctx).unwrap(), false);
+
+    test_time.advance(24 * 3600);
+
+    assert_eq!(should_update_inactive(&ctx).unwrap(), true);
+
+    test_time.set_now(time::macros::datetime!(2020-05-02 0:00 UTC));
+
+    assert_eq!(should_update_inactive(&ctx).unwrap(), false);
+}
+
+/// Tests try_lock_instance(): acquiring the lock twice is detected.
+#[test]
+fn test_try_lock_instance() {
+    let ctx = context::tests::make_test_context().unwrap();
+
+    let lock = try_lock_instance(&ctx).unwrap();
+
+    assert!(lock.is_some());
+    assert!(try_lock_instance(&ctx).unwrap().is_none());
+    drop(lock);
+    assert!(try_lock_instance(&ctx).unwrap().is_some());
+}
+
+/// Tests main(): the path when an other instance is running.
+#[test]
+fn test_main_locked() {
+    let ctx = context::tests::make_test_context().unwrap();
+    let _lock = try_lock_instance(&ctx).unwrap();
+    let argv = vec!["".to_string(), "--mode".to_string(), "stats".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    // Doing the actual work would fail, as workdir/stats/ref.count is not writable here.
+    assert_eq!(ret, 0);
+}
+
 /// Tests main(): the path when our_main() returns an error.
 #[test]
 fn test_main_error() {
@@ -1414,6 +3192,42 @@ fn test_update_stats_count() {
     assert!(zipcount.is_some());
 }
 
+/// Tests export_whole_country_jsonl().
+#[test]
+fn test_export_whole_country_jsonl() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let jsonl_value = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/stats/2020-05-10.jsonl", &jsonl_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '1', 'myuser1', '42', 'way', '2020-05-10T22:02:25Z', '', '', '', '');
+            insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '2', 'myuser2', '43', 'way', '2020-05-10T22:02:25Z', '', '', '', '');
+            insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '', 'myuser2', '44', 'way', '2020-05-10T22:02:25Z', '', '', '', '');",
+        )
+        .unwrap();
+    }
+
+    let count = export_whole_country_jsonl(&ctx, "2020-05-10").unwrap();
+
+    // The row without a housenumber is skipped.
+    assert_eq!(count, 2);
+    let content = context::tests::TestFileSystem::get_content(&jsonl_value);
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let row: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+    assert_eq!(row["postcode"], "1234");
+    assert_eq!(row["city"], "mycity");
+    assert_eq!(row["street"], "mystreet1");
+    assert_eq!(row["housenumber"], "2");
+    assert_eq!(row["user"], "myuser2");
+}
+
 /// Tests update_stats_topusers().
 #[test]
 fn test_update_stats_topusers() {
@@ -1473,6 +3287,109 @@ fn test_update_stats_topusers() {
     }
 }
 
+/// Tests update_stats_topusers(): anonymization without a secret is refused.
+#[test]
+fn test_update_stats_topusers_anonymize_no_secret() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "anonymize_users = 'True'\n").unwrap();
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '1', 'myuser1', '42', 'way', '2020-05-10T22:02:25Z', '', '', '', '');",
+        )
+        .unwrap();
+    }
+
+    let ret = update_stats_topusers(&ctx, "2020-05-10");
+
+    assert_eq!(
+        ret.unwrap_err().to_string(),
+        "anonymize_users is True, but anonymize_users_secret is empty"
+    );
+    let conn = ctx.get_database_connection().unwrap();
+    let count: i64 = conn
+        .query_row("select count(*) from stats_topusers", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+/// Tests update_stats_topusers(): user names are hashed when anonymization is enabled.
+#[test]
+fn test_update_stats_topusers_anonymize() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(
+        &mut ctx,
+        "anonymize_users = 'True'\nanonymize_users_secret = 'mysalt'\n",
+    )
+    .unwrap();
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '1', 'myuser1', '42', 'way', '2020-05-10T22:02:25Z', '', '', '', '');
+            insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '2', 'myuser1', '43', 'way', '2020-05-10T22:02:25Z', '', '', '', '');
+            insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', '3', 'myuser2', '44', 'way', '2020-05-10T22:02:25Z', '', '', '', '');",
+        )
+        .unwrap();
+    }
+
+    update_stats_topusers(&ctx, "2020-05-10").unwrap();
+
+    let conn = ctx.get_database_connection().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "select user, count from stats_topusers where date = ?1 order by cast(count as integer) desc",
+        )
+        .unwrap();
+    let mut rows = stmt.query(["2020-05-10"]).unwrap();
+    let row = rows.next().unwrap().unwrap();
+    let user: String = row.get(0).unwrap();
+    assert_eq!(user, util::anonymize_user("mysalt", "myuser1"));
+    let count: String = row.get(1).unwrap();
+    assert_eq!(count, "2");
+    let row = rows.next().unwrap().unwrap();
+    let user: String = row.get(0).unwrap();
+    assert_eq!(user, util::anonymize_user("mysalt", "myuser2"));
+    assert!(rows.next().unwrap().is_none());
+    let mut stmt = conn
+        .prepare("select count from stats_usercounts where date = ?1")
+        .unwrap();
+    let mut rows = stmt.query(["2020-05-10"]).unwrap();
+    let row = rows.next().unwrap().unwrap();
+    let count: String = row.get(0).unwrap();
+    assert_eq!(count, "2");
+}
+
+/// Tests update_stats_topusers(): users with the same count are ordered by name.
+#[test]
+fn test_update_stats_topusers_ties() {
+    let ctx = context::tests::make_test_context().unwrap();
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        for i in (1..=21).rev() {
+            conn.execute(
+                "insert into whole_country (postcode, city, street, housenumber, user, osm_id, osm_type, timestamp, place, unit, name, fixme) values ('1234', 'mycity', 'mystreet1', ?1, ?2, ?1, 'way', '2020-05-10T22:02:25Z', '', '', '', '')",
+                [i.to_string(), format!("myuser{i:02}")],
+            )
+            .unwrap();
+        }
+    }
+
+    update_stats_topusers(&ctx, "2020-05-10").unwrap();
+
+    let conn = ctx.get_database_connection().unwrap();
+    let mut stmt = conn
+        .prepare("select user from stats_topusers where date = ?1 order by rowid")
+        .unwrap();
+    let mut rows = stmt.query(["2020-05-10"]).unwrap();
+    let mut actual: Vec<String> = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        actual.push(row.get(0).unwrap());
+    }
+    // All users have 1 edit, so the last one in alphabetical order is cut.
+    let expected: Vec<String> = (1..=20).map(|i| format!("myuser{i:02}")).collect();
+    assert_eq!(actual, expected);
+}
+
 /// Tests write_city_count_path().
 #[test]
 fn test_write_city_count_path() {
@@ -1486,7 +3403,7 @@ fn test_write_city_count_path() {
     .into_iter()
     .collect();
 
-    write_city_count_path(&ctx, &cities).unwrap();
+    write_city_count_path(&ctx, &cities, "2020-05-10").unwrap();
 
     let conn = ctx.get_database_connection().unwrap();
     let mut stmt = conn
@@ -1517,7 +3434,7 @@ fn test_write_zip_count_path() {
             .into_iter()
             .collect();
 
-    write_zip_count_path(&ctx, &cities).unwrap();
+    write_zip_count_path(&ctx, &cities, "2020-05-10").unwrap();
 
     let conn = ctx.get_database_connection().unwrap();
     let mut stmt = conn
@@ -1536,3 +3453,240 @@ fn test_write_zip_count_path() {
     assert_eq!(count, "2");
     assert!(rows.next().unwrap().is_none());
 }
+
+/// Tests our_main(): --invalidate-cache only drops the cache of the given relation.
+#[test]
+fn test_our_main_invalidate_cache() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+            "budafok": {
+                "osmrelation": 2702687,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let gazdagret_cache = context::tests::TestFileSystem::make_file();
+    let budafok_cache = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/additional-cache-gazdagret.json", &gazdagret_cache),
+            ("workdir/additional-cache-budafok.json", &budafok_cache),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            r#"insert into missing_housenumbers_cache (relation, json) values ('gazdagret', '{}');
+            insert into missing_housenumbers_cache (relation, json) values ('budafok', '{}');"#,
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&ctx, "missing-housenumbers-cache/gazdagret").unwrap();
+    stats::set_sql_mtime(&ctx, "missing-housenumbers-cache/budafok").unwrap();
+    let argv = vec![
+        "".to_string(),
+        "--invalidate-cache".to_string(),
+        "gazdagret".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    our_main(&argv, &mut buf, &ctx).unwrap();
+
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "missing-housenumbers-cache/gazdagret").unwrap(),
+        false
+    );
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "missing-housenumbers-cache/budafok").unwrap(),
+        true
+    );
+    let file_system = ctx.get_file_system();
+    assert_eq!(
+        file_system.path_exists(&ctx.get_abspath("workdir/additional-cache-gazdagret.json")),
+        false
+    );
+    assert_eq!(
+        file_system.path_exists(&ctx.get_abspath("workdir/additional-cache-budafok.json")),
+        true
+    );
+}
+
+/// Tests warm_cache().
+#[test]
+fn test_warm_cache() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+            "ujbuda": {
+                "osmrelation": 2702687,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+        "relation-ujbuda.yaml": {
+            "missing-streets": "only",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_housenumbers = context::tests::TestFileSystem::make_file();
+    ref_housenumbers
+        .borrow_mut()
+        .write_all("Tűzkő utca\t1\t\n".as_bytes())
+        .unwrap();
+    let additional_cache = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_housenumbers,
+            ),
+            ("workdir/additional-cache-gazdagret.json", &additional_cache),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    // The additional cache is outdated, so it gets regenerated.
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        ctx.get_abspath("workdir/additional-cache-gazdagret.json"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    warm_cache(&mut relations).unwrap();
+
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "missing-housenumbers-cache/gazdagret").unwrap(),
+        true
+    );
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&additional_cache).is_empty(),
+        false
+    );
+    // ujbuda only checks streets.
+    assert_eq!(
+        stats::has_sql_mtime(&ctx, "missing-housenumbers-cache/ujbuda").unwrap(),
+        false
+    );
+    // The coverage percent is not recomputed.
+    let relation = relations.get_relation("gazdagret").unwrap();
+    assert_eq!(relation.has_osm_housenumber_coverage().unwrap(), false);
+}
+
+/// Tests our_main(): --html-only regenerates the HTML cache, but keeps the analysis and the percent.
+#[test]
+fn test_our_main_html_only() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            r#"insert into missing_housenumbers_cache (relation, json) values ('gazdagret', '{"ongoing-streets": [], "done-streets": []}');
+            insert into missing_housenumbers_html_cache (relation, language, html) values ('gazdagret', 'en', 'old html');"#,
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&ctx, "missing-housenumbers-cache/gazdagret").unwrap();
+    stats::set_sql_mtime(&ctx, "missing-housenumbers-html/en/gazdagret").unwrap();
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+    relation.set_osm_housenumber_coverage("50.00").unwrap();
+    let percent_mtime = relation.get_osm_housenumber_coverage_mtime().unwrap();
+    let json_mtime = stats::get_sql_mtime(&ctx, "missing-housenumbers-cache/gazdagret").unwrap();
+    let argv = vec!["".to_string(), "--html-only".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    our_main(&argv, &mut buf, &ctx).unwrap();
+
+    let html: String = ctx
+        .get_database_connection()
+        .unwrap()
+        .query_row(
+            "select html from missing_housenumbers_html_cache where relation = 'gazdagret' and language = 'en'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(
+        html.contains("OpenStreetMap is possibly missing the below 0 house numbers"),
+        true
+    );
+    // The analysis and the coverage percent are not recomputed.
+    assert_eq!(
+        relation.get_osm_housenumber_coverage_mtime().unwrap(),
+        percent_mtime
+    );
+    assert_eq!(relation.get_osm_housenumber_coverage().unwrap(), "50.00");
+    assert_eq!(
+        stats::get_sql_mtime(&ctx, "missing-housenumbers-cache/gazdagret").unwrap(),
+        json_mtime
+    );
+}
+
+/// Tests our_main(): --below lists the relations with a low house number coverage.
+#[test]
+fn test_our_main_below() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+            "budafok": {
+                "osmrelation": 2702687,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+    relations
+        .get_relation("gazdagret")
+        .unwrap()
+        .set_osm_housenumber_coverage("90.00")
+        .unwrap();
+    let argv = vec!["".to_string(), "--below".to_string(), "80".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    let ret = our_main(&argv, &mut buf, &ctx).unwrap();
+
+    assert_eq!(ret, 0);
+    let actual = String::from_utf8(buf.into_inner()).unwrap();
+    assert_eq!(actual, "budafok\n");
+}
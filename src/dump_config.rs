@@ -0,0 +1,44 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Dumps the effective config of all relations as a single YAML document.
+
+use crate::areas;
+use crate::context;
+use std::io::Write;
+
+/// Inner main() that is allowed to fail.
+pub fn our_main(
+    argv: &[String],
+    stream: &mut dyn Write,
+    ctx: &context::Context,
+) -> anyhow::Result<()> {
+    let app = clap::Command::new("osm-gimmisn").override_usage("osm-gimmisn dump-config");
+    app.try_get_matches_from(argv)?;
+
+    let relations = areas::Relations::new(ctx)?;
+    let configs = relations.get_effective_configs();
+    stream.write_all(serde_yaml::to_string(&configs)?.as_bytes())?;
+    Ok(())
+}
+
+/// Similar to plain main(), but with an interface that allows testing.
+pub fn main(argv: &[String], stream: &mut dyn Write, ctx: &context::Context) -> i32 {
+    match our_main(argv, stream, ctx) {
+        Ok(_) => 0,
+        Err(err) => {
+            stream.write_all(format!("{err:?}\n").as_bytes()).unwrap();
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Tests for the dump_config module.
+
+use super::*;
+use std::collections::HashMap;
+
+/// Tests main(): the output is valid YAML, with relation-<name>.yaml overriding relations.yaml.
+#[test]
+fn test_main() {
+    let argv = vec!["".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+            "ujbuda": {
+                "osmrelation": 221998,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "refsettlement": "012",
+            "street-match": "casefold",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    let dump: HashMap<String, areas::RelationDict> = serde_yaml::from_slice(buf.get_ref()).unwrap();
+    assert_eq!(dump.len(), 2);
+    let gazdagret = &dump["gazdagret"];
+    assert_eq!(
+        gazdagret.osmrelation,
+        Some(areas::OsmRelation::Single(2713748))
+    );
+    assert_eq!(gazdagret.refcounty, Some("01".to_string()));
+    assert_eq!(gazdagret.refsettlement, Some("012".to_string()));
+    assert_eq!(gazdagret.street_match, Some("casefold".to_string()));
+    assert_eq!(dump["ujbuda"].refsettlement, Some("011".to_string()));
+}
@@ -0,0 +1,75 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! The error module contains the error type which allows matching on the kind of a failure.
+
+/// Error kinds which callers may want to handle differently, e.g. retry only network errors.
+#[derive(Debug)]
+pub enum OsmGimmisnError {
+    /// Failed to talk to a remote server.
+    Network(String),
+    /// The remote server responded with a non-success HTTP status code.
+    HttpStatus(u16, String),
+    /// Failed to read or write a local file.
+    Io(String),
+    /// Failed to parse some input.
+    Parse(String),
+    /// Invalid configuration, e.g. a malformed wsgi.ini or relation YAML file.
+    Config(String),
+}
+
+impl std::fmt::Display for OsmGimmisnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsmGimmisnError::Network(message) => write!(f, "network error: {message}"),
+            OsmGimmisnError::HttpStatus(status, message) => {
+                write!(f, "http error {status}: {message}")
+            }
+            OsmGimmisnError::Io(message) => write!(f, "io error: {message}"),
+            OsmGimmisnError::Parse(message) => write!(f, "parse error: {message}"),
+            OsmGimmisnError::Config(message) => write!(f, "config error: {message}"),
+        }
+    }
+}
+
+impl OsmGimmisnError {
+    /// Decides if the failed request is worth retrying: network problems and rate limiting are,
+    /// client errors (e.g. a malformed query) and config errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            OsmGimmisnError::Network(_) => true,
+            OsmGimmisnError::HttpStatus(status, _) => matches!(status, 429 | 502 | 503 | 504),
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for OsmGimmisnError {}
+
+impl From<std::io::Error> for OsmGimmisnError {
+    fn from(err: std::io::Error) -> Self {
+        OsmGimmisnError::Io(err.to_string())
+    }
+}
+
+impl From<isahc::Error> for OsmGimmisnError {
+    fn from(err: isahc::Error) -> Self {
+        OsmGimmisnError::Network(err.to_string())
+    }
+}
+
+impl From<isahc::http::Error> for OsmGimmisnError {
+    fn from(err: isahc::http::Error) -> Self {
+        OsmGimmisnError::Network(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests;
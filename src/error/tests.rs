@@ -0,0 +1,38 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Tests for the error module.
+
+use super::*;
+
+/// Tests the Display impl of OsmGimmisnError.
+#[test]
+fn test_display() {
+    let err = OsmGimmisnError::Network("connection refused".into());
+    assert_eq!(err.to_string(), "network error: connection refused");
+}
+
+/// Tests that an std::io::Error is converted to an Io error.
+#[test]
+fn test_from_io() {
+    let err: OsmGimmisnError = std::io::Error::other("no such file").into();
+    assert!(matches!(err, OsmGimmisnError::Io(_)));
+}
+
+/// Tests OsmGimmisnError::is_retryable().
+#[test]
+fn test_is_retryable() {
+    assert!(OsmGimmisnError::Network("timeout".into()).is_retryable());
+    assert!(OsmGimmisnError::HttpStatus(429, "".into()).is_retryable());
+    assert!(OsmGimmisnError::HttpStatus(504, "".into()).is_retryable());
+    assert!(!OsmGimmisnError::HttpStatus(400, "".into()).is_retryable());
+    assert!(!OsmGimmisnError::Parse("unexpected token".into()).is_retryable());
+    assert!(!OsmGimmisnError::Config("invalid wsgi.ini".into()).is_retryable());
+}
@@ -0,0 +1,63 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Writes the missing keys of workdir/wsgi.ini with their defaults, keeping the set values.
+
+use crate::context;
+use anyhow::Context;
+use std::io::Write;
+
+/// Inner main() that is allowed to fail.
+pub fn our_main(
+    argv: &[String],
+    stream: &mut dyn Write,
+    ctx: &context::Context,
+) -> anyhow::Result<()> {
+    let app = clap::Command::new("osm-gimmisn").override_usage("osm-gimmisn init-config");
+    app.try_get_matches_from(argv)?;
+
+    let path = ctx.get_ini().get_config_path();
+    let file_system = ctx.get_file_system();
+    let mut config = toml::Table::new();
+    if file_system.path_exists(&path) {
+        let data = file_system.read_to_string(&path)?;
+        config = toml::from_str(&data).context(format!("failed to parse '{path}'"))?;
+    }
+    let wsgi = config
+        .entry("wsgi")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .context("the wsgi section is not a table")?;
+    for (key, value) in context::Ini::get_defaults() {
+        if wsgi.contains_key(*key) {
+            continue;
+        }
+
+        wsgi.insert(key.to_string(), toml::Value::String(value.to_string()));
+        stream.write_all(format!("added {key} = '{value}'\n").as_bytes())?;
+    }
+
+    file_system.write_from_string(&toml::to_string(&config)?, &path)?;
+    ctx.get_unit().make_error()
+}
+
+/// Similar to plain main(), but with an interface that allows testing.
+pub fn main(argv: &[String], stream: &mut dyn Write, ctx: &context::Context) -> i32 {
+    match our_main(argv, stream, ctx) {
+        Ok(_) => 0,
+        Err(err) => {
+            stream.write_all(format!("{err:?}\n").as_bytes()).unwrap();
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
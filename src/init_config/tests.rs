@@ -0,0 +1,72 @@
+/*
+ * Copyright 2024 Miklos Vajna
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![deny(warnings)]
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+//! Tests for the init_config module.
+
+use super::*;
+use std::io::Read;
+use std::io::Seek;
+
+/// Tests main(): a partial config gains the missing keys, the present ones are kept.
+#[test]
+fn test_main() {
+    let argv = vec!["".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let ini = context::tests::TestFileSystem::make_file();
+    ini.borrow_mut()
+        .write_all(b"[wsgi]\nreference_street = 'workdir/refs/utcak.tsv'\ntcp_port = '8001'\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(&ctx, &[("workdir/wsgi.ini", &ini)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut output: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("added uri_prefix = '/osm'\n"));
+    assert!(!output.contains("tcp_port"));
+    let config: toml::Table =
+        toml::from_str(&context::tests::TestFileSystem::get_content(&ini)).unwrap();
+    let wsgi = config["wsgi"].as_table().unwrap();
+    assert_eq!(
+        wsgi["reference_street"].as_str(),
+        Some("workdir/refs/utcak.tsv")
+    );
+    assert_eq!(wsgi["tcp_port"].as_str(), Some("8001"));
+    assert_eq!(wsgi["uri_prefix"].as_str(), Some("/osm"));
+    assert_eq!(wsgi["reference_housenumbers"].as_str(), Some(""));
+    assert_eq!(wsgi.len(), context::Ini::get_defaults().len());
+}
+
+/// Tests main(): running it twice doesn't change anything.
+#[test]
+fn test_main_idempotent() {
+    let argv = vec!["".to_string()];
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let ini = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(&ctx, &[("workdir/wsgi.ini", &ini)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    assert_eq!(main(&argv, &mut buf, &ctx), 0);
+    let first = context::tests::TestFileSystem::get_content(&ini);
+
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let ret = main(&argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    assert!(buf.get_ref().is_empty());
+    assert_eq!(context::tests::TestFileSystem::get_content(&ini), first);
+}
@@ -11,13 +11,18 @@
 
 //! Finds objects missing from the OSM DB.
 
+pub mod additional_housenumbers;
 mod area_files;
 mod areas;
 mod cache;
 pub mod cache_yamls;
+pub mod compare_relations;
 pub mod context;
 pub mod cron;
+pub mod dump_config;
+pub mod error;
 mod i18n;
+pub mod init_config;
 pub mod missing_housenumbers;
 mod overpass_query;
 pub mod parse_access_log;
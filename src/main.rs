@@ -67,6 +67,10 @@ fn cron_setup_logging(ctx: &osm_gimmisn::context::Context) {
         .set_time_offset_to_local()
         .unwrap()
         .build();
+    let (color, color_error) = match ctx.get_ini().get_log_color() {
+        Ok(value) => (value, None),
+        Err(err) => (simplelog::ColorChoice::Never, Some(err)),
+    };
     let logpath = ctx.get_abspath("workdir/cron.log");
     let file = std::fs::File::create(logpath).expect("failed to create cron.log");
     simplelog::CombinedLogger::init(vec![
@@ -74,11 +78,16 @@ fn cron_setup_logging(ctx: &osm_gimmisn::context::Context) {
             simplelog::LevelFilter::Info,
             config.clone(),
             simplelog::TerminalMode::Stdout,
-            simplelog::ColorChoice::Never,
+            color,
         ),
+        // The file logger never uses colors.
         simplelog::WriteLogger::new(simplelog::LevelFilter::Info, config, file),
     ])
     .expect("failed to init the combined logger");
+    // Only now the logger is set up to report this.
+    if let Some(err) = color_error {
+        log::warn!("cron_setup_logging: failed to get log_color, using 'never': {err:?}");
+    }
 }
 
 fn cron_main(args: &[String], stream: &mut dyn Write, ctx: &osm_gimmisn::context::Context) -> i32 {
@@ -89,8 +98,15 @@ fn cron_main(args: &[String], stream: &mut dyn Write, ctx: &osm_gimmisn::context
 lazy_static::lazy_static! {
     static ref HANDLERS: HashMap<String, Handler> = {
         let mut ret: HashMap<String, Handler> = HashMap::new();
+        ret.insert(
+            "additional-housenumbers".into(),
+            osm_gimmisn::additional_housenumbers::main,
+        );
         ret.insert("cache-yamls".into(), osm_gimmisn::cache_yamls::main);
+        ret.insert("compare".into(), osm_gimmisn::compare_relations::main);
         ret.insert("cron".into(), cron_main);
+        ret.insert("dump-config".into(), osm_gimmisn::dump_config::main);
+        ret.insert("init-config".into(), osm_gimmisn::init_config::main);
         ret.insert("missing-housenumbers".into(), osm_gimmisn::missing_housenumbers::main);
         ret.insert("parse-access-log".into(), osm_gimmisn::parse_access_log::main);
         ret.insert("rouille".into(), rouille_main);
@@ -103,9 +119,17 @@ lazy_static::lazy_static! {
 fn main() {
     let mut args: Vec<String> = std::env::args().collect();
     let ctx = osm_gimmisn::context::Context::new("").unwrap();
+    let additional_housenumbers = clap::Command::new("additional-housenumbers")
+        .about("Compares OSM house numbers with reference ones and shows the ones only in OSM");
     let cache_yamls =
         clap::Command::new("cache-yamls").about("Caches YAML files from the data/ directory");
+    let compare = clap::Command::new("compare")
+        .about("Compares the OSM streets and house numbers of two relations");
     let cron = clap::Command::new("cron").about("Performs nightly tasks");
+    let dump_config = clap::Command::new("dump-config")
+        .about("Dumps the effective config of all relations as a single YAML document");
+    let init_config = clap::Command::new("init-config")
+        .about("Adds the missing keys of workdir/wsgi.ini with their defaults");
     let missing_housenumbers = clap::Command::new("missing-housenumbers")
         .about("Compares reference house numbers with OSM ones and shows the diff");
     let parse_access_log = clap::Command::new("parse-access-log")
@@ -115,8 +139,12 @@ fn main() {
         .about("Synchronizes the reference data from a public instance to a local dev instance");
     let validator = clap::Command::new("validator").about("Validates yaml files under data/");
     let subcommands = vec![
+        additional_housenumbers,
         cache_yamls,
+        compare,
         cron,
+        dump_config,
+        init_config,
         missing_housenumbers,
         parse_access_log,
         rouille,
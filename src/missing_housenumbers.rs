@@ -13,36 +13,185 @@
 use crate::areas;
 use crate::context;
 use crate::util;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::io::Write;
 
-/// Inner main() that is allowed to fail.
-pub fn our_main(
-    argv: &[String],
+/// Formats house numbers either as ranges from the reference or as collapsed runs.
+fn get_range_strings(house_numbers: &[util::HouseNumber], collapse: bool) -> Vec<String> {
+    if collapse {
+        util::collapse_ranges(house_numbers)
+    } else {
+        let range_list = util::get_housenumber_ranges(house_numbers);
+        let mut range_strings: Vec<String> = range_list
+            .iter()
+            .map(|i| i.get_number().to_string())
+            .collect();
+        range_strings.sort_by_key(|i| util::split_house_number(i));
+        range_strings
+    }
+}
+
+/// Writes the missing house numbers of one street.
+fn write_street(
     stream: &mut dyn Write,
-    ctx: &context::Context,
+    result: &util::NumberedStreet,
+    collapse: bool,
 ) -> anyhow::Result<()> {
-    let relation_name = argv[1].clone();
-
-    let mut relations = areas::Relations::new(ctx)?;
-    let mut relation = relations.get_relation(&relation_name)?;
-    let ongoing_streets = relation.get_missing_housenumbers()?.ongoing_streets;
+    let range_strings = get_range_strings(&result.house_numbers, collapse);
+    // House number, # of only_in_reference items.
+    stream.write_all(
+        format!(
+            "{}\t{}\n",
+            result.street.get_osm_name(),
+            range_strings.len()
+        )
+        .as_bytes(),
+    )?;
+    // only_in_reference items.
+    stream.write_all(format!("{range_strings:?}\n").as_bytes())?;
+    Ok(())
+}
 
-    for result in ongoing_streets {
-        // House number, # of only_in_reference items.
-        let range_list = util::get_housenumber_ranges(&result.house_numbers);
-        let mut range_strings: Vec<&String> = range_list.iter().map(|i| i.get_number()).collect();
-        range_strings.sort_by_key(|i| util::split_house_number(i));
+/// Writes the missing house numbers of one street, the even and the odd side separately.
+fn write_street_by_side(
+    stream: &mut dyn Write,
+    result: &util::NumberedStreet,
+    collapse: bool,
+) -> anyhow::Result<()> {
+    let (even, odd) = util::partition_by_parity(&result.house_numbers);
+    for (side, house_numbers) in [("even", even), ("odd", odd)] {
+        if house_numbers.is_empty() {
+            continue;
+        }
+        let range_strings = get_range_strings(&house_numbers, collapse);
         stream.write_all(
             format!(
-                "{}\t{}\n",
+                "{}\t{side}\t{}\n",
                 result.street.get_osm_name(),
                 range_strings.len()
             )
             .as_bytes(),
         )?;
-        // only_in_reference items.
         stream.write_all(format!("{range_strings:?}\n").as_bytes())?;
     }
+    Ok(())
+}
+
+/// Inner main() that is allowed to fail.
+pub fn our_main(
+    argv: &[String],
+    stream: &mut dyn Write,
+    ctx: &context::Context,
+) -> anyhow::Result<()> {
+    let relation = clap::Arg::new("relation")
+        .required(true)
+        .help("name of the relation");
+    let collapse = clap::Arg::new("collapse")
+        .long("collapse")
+        .action(clap::ArgAction::SetTrue)
+        .help("collapse runs of house numbers, e.g. '2-20 (even)'");
+    let by_postcode = clap::Arg::new("by-postcode")
+        .long("by-postcode")
+        .action(clap::ArgAction::SetTrue)
+        .help("group streets by their postcode, instead of a flat list");
+    let explain_filters = clap::Arg::new("explain-filters")
+        .long("explain-filters")
+        .action(clap::ArgAction::SetTrue)
+        .help("instead of the missing house numbers, count the matches of each filter range");
+    let by_side = clap::Arg::new("by-side")
+        .long("by-side")
+        .action(clap::ArgAction::SetTrue)
+        .help("list the even and the odd side of each street separately");
+    let min_missing = clap::Arg::new("min-missing")
+        .long("min-missing")
+        .value_parser(clap::value_parser!(usize))
+        .help("omit streets with less missing house numbers than this, defaults to the min-missing key of the relation");
+    let sort = clap::Arg::new("sort")
+        .long("sort")
+        .value_parser(["name", "count"])
+        .default_value("name")
+        .help("order streets by name or by descending count of missing house numbers");
+    let args = [
+        relation,
+        collapse,
+        by_postcode,
+        explain_filters,
+        by_side,
+        min_missing,
+        sort,
+    ];
+    let app = clap::Command::new("osm-gimmisn").override_usage(
+        "osm-gimmisn missing-housenumbers [--collapse] [--by-postcode] [--explain-filters] [--by-side] [--min-missing N] [--sort name|count] <relation>",
+    );
+    let args = app.args(&args).try_get_matches_from(argv)?;
+    let relation_name: &String = args.get_one("relation").unwrap();
+    let collapse = *args.get_one::<bool>("collapse").unwrap();
+    let by_postcode = *args.get_one::<bool>("by-postcode").unwrap();
+    let explain_filters = *args.get_one::<bool>("explain-filters").unwrap();
+    let by_side = *args.get_one::<bool>("by-side").unwrap();
+    let write_result = if by_side {
+        write_street_by_side
+    } else {
+        write_street
+    };
+
+    let mut relations = areas::Relations::new(ctx)?;
+    let mut relation = relations.get_relation(relation_name)?;
+    if explain_filters {
+        for (street, range, count) in relation.get_filter_range_matches()? {
+            // Ranges without matches are probably not needed.
+            let dead = if count == 0 { "\tdead" } else { "" };
+            stream.write_all(format!("{street}\t{range}\t{count}{dead}\n").as_bytes())?;
+        }
+        return ctx.get_unit().make_error();
+    }
+    let min_missing = match args.get_one::<usize>("min-missing") {
+        Some(value) => *value,
+        None => relation.get_config().get_min_missing(),
+    };
+    // Only the listing is filtered, nearly complete streets are still missing from the coverage.
+    let mut ongoing_streets: Vec<util::NumberedStreet> = relation
+        .get_missing_housenumbers()?
+        .ongoing_streets
+        .into_iter()
+        .filter(|i| i.house_numbers.len() >= min_missing)
+        .collect();
+    if args.get_one::<String>("sort").unwrap() == "count" {
+        // Worst streets first, break ties by name.
+        ongoing_streets.sort_by_key(|i| {
+            (
+                Reverse(i.house_numbers.len()),
+                util::get_sort_key(i.street.get_osm_name()),
+            )
+        });
+    }
+
+    if by_postcode {
+        // The reference has no postcodes, so use the postcodes of the streets in OSM.
+        let postcodes = relation.get_files().get_osm_street_postcodes(ctx)?;
+        let mut groups: BTreeMap<String, Vec<&util::NumberedStreet>> = BTreeMap::new();
+        let mut unknowns: Vec<&util::NumberedStreet> = Vec::new();
+        for result in &ongoing_streets {
+            match postcodes.get(result.street.get_osm_name()) {
+                Some(postcode) => groups.entry(postcode.to_string()).or_default().push(result),
+                None => unknowns.push(result),
+            }
+        }
+        if !unknowns.is_empty() {
+            groups.insert("unknown".into(), unknowns);
+        }
+        for (postcode, results) in groups {
+            stream.write_all(format!("{postcode}\n").as_bytes())?;
+            for result in results {
+                write_result(stream, result, collapse)?;
+            }
+        }
+    } else {
+        for result in &ongoing_streets {
+            write_result(stream, result, collapse)?;
+        }
+    }
 
     ctx.get_unit().make_error()
 }
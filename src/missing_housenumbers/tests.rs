@@ -69,6 +69,417 @@ fn test_main() {
     );
 }
 
+/// Tests main(): --collapse turns runs of house numbers into ranges.
+#[test]
+fn test_main_collapse() {
+    let argv = vec![
+        "".to_string(),
+        "--collapse".to_string(),
+        "gh195".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '25', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '27-37', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', 'asphalt', '', '');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t1\n[\"25-37 (odd)\"]\n"
+    );
+}
+
+/// Tests main(): --by-side lists the even and the odd side separately.
+#[test]
+fn test_main_by_side() {
+    let argv = vec!["".to_string(), "--by-side".to_string(), "gh195".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '25', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '26', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '27', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', 'asphalt', '', '');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\teven\t1\n[\"26\"]\nKalotaszeg utca\todd\t2\n[\"25\", \"27\"]\n"
+    );
+}
+
+/// Tests main(): --min-missing hides streets with less missing house numbers.
+#[test]
+fn test_main_min_missing() {
+    let argv = vec![
+        "".to_string(),
+        "--min-missing".to_string(),
+        "2".to_string(),
+        "gh195".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '25', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '27', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Törökugrató utca', '1', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', 'asphalt', '', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746224', 'Törökugrató utca', 'residential', '', 'asphalt', '', '');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    // Törökugrató utca has a single missing house number, so it's hidden.
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t2\n[\"25\", \"27\"]\n"
+    );
+}
+
+/// Tests main(): the min-missing key of the relation is the default of --min-missing.
+#[test]
+fn test_main_min_missing_relation() {
+    let argv = vec!["".to_string(), "gh195".to_string()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+        "relation-gh195.yaml": {
+            "min-missing": 2,
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '25', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '27', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Törökugrató utca', '1', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', 'asphalt', '', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746224', 'Törökugrató utca', 'residential', '', 'asphalt', '', '');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    // Törökugrató utca has a single missing house number, so it's hidden.
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t2\n[\"25\", \"27\"]\n"
+    );
+}
+
+/// Tests main(): --sort count lists the street with the most missing house numbers first.
+#[test]
+fn test_main_sort_count() {
+    let argv = vec![
+        "".to_string(),
+        "--sort".to_string(),
+        "count".to_string(),
+        "gh195".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '25', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '27', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Alsó utca', '1', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', 'asphalt', '', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746224', 'Alsó utca', 'residential', '', 'asphalt', '', '');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    // Kalotaszeg utca is first, even if Alsó utca would be first by name.
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t2\n[\"25\", \"27\"]\nAlsó utca\t1\n[\"1\"]\n"
+    );
+}
+
+/// Tests main(): --explain-filters counts the matches of each filter range.
+#[test]
+fn test_main_explain_filters() {
+    let argv = vec![
+        "".to_string(),
+        "--explain-filters".to_string(),
+        "gh195".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gh195": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+        "relation-gh195.yaml": {
+            "filters": {
+                "Kalotaszeg utca": {
+                    "ranges": [
+                        {"start": "1", "end": "5"},
+                        {"start": "101", "end": "199"},
+                    ],
+                },
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/street-housenumbers-reference-gh195.lst", &ref_file),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '1', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '3', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '5', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Kalotaszeg utca', '11', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gh195', '24746223', 'Kalotaszeg utca', 'residential', '', 'asphalt', '', '');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gh195").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "Kalotaszeg utca\t1-5\t3\nKalotaszeg utca\t101-199\t0\tdead\n"
+    );
+}
+
+/// Tests main(): --by-postcode groups the streets under their postcode.
+#[test]
+fn test_main_by_postcode() {
+    let argv = vec![
+        "".to_string(),
+        "--by-postcode".to_string(),
+        "gazdagret".to_string(),
+    ];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_file,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Törökugrató utca', '1', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Törökugrató utca', '2', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Tűzkő utca', '1', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '2', 'Törökugrató utca', '', '', '', '', '');
+             insert into osm_housenumbers (relation, osm_id, street, housenumber, postcode, place, housename, conscriptionnumber, flats, floor, door, unit, name, osm_type) values ('gazdagret', '1', 'Törökugrató utca', '1', '1118', '', '', '', '', '', '', '', '', 'node');").unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&ctx).unwrap();
+        let relation = relations.get_relation("gazdagret").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let ret = main(&argv, &mut buf, &mut ctx);
+
+    assert_eq!(ret, 0);
+    buf.rewind().unwrap();
+    let mut actual: Vec<u8> = Vec::new();
+    buf.read_to_end(&mut actual).unwrap();
+    // Tűzkő utca has no house numbers in OSM, so its postcode is unknown.
+    assert_eq!(
+        String::from_utf8(actual).unwrap(),
+        "1118\nTörökugrató utca\t1\n[\"2\"]\nunknown\nTűzkő utca\t1\n[\"1\"]\n"
+    );
+}
+
 /// Tests main(), the failing case.
 #[test]
 fn test_main_error() {
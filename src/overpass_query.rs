@@ -11,28 +11,31 @@
 //! The overpass_query module allows getting data out of the OSM DB without a full download.
 
 use crate::context;
+use crate::error::OsmGimmisnError;
 
 /// Posts the query string to the overpass API and returns the result string.
-pub fn overpass_query(ctx: &context::Context, query: &str) -> anyhow::Result<String> {
+pub fn overpass_query(ctx: &context::Context, query: &str) -> Result<String, OsmGimmisnError> {
     let url = ctx.get_ini().get_overpass_uri() + "/api/interpreter";
 
     ctx.get_network().urlopen(&url, query)
 }
 
-/// Checks if we need to sleep before executing an overpass query.
-pub fn overpass_query_need_sleep(ctx: &context::Context) -> i32 {
-    let url = ctx.get_ini().get_overpass_uri() + "/api/status";
-    let status = match ctx.get_network().urlopen(&url, "") {
-        Ok(value) => value,
-        _ => {
-            return 0;
-        }
-    };
+/// The parsed form of the overpass status page.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StatusInfo {
+    /// The number of query slots which are available now.
+    pub available_slots: u32,
+    /// How many seconds to wait before the next slot is available, 0 if a slot is available now.
+    pub seconds_to_wait: i32,
+}
+
+/// Parses the output of the overpass status page.
+pub fn parse_status(status: &str) -> StatusInfo {
     let mut sleep = 0;
-    let mut available = false;
+    let mut available_slots = 0;
+    let re = regex::Regex::new(r".*in (-?\d+) seconds.*").unwrap();
     for line in status.lines() {
         if line.starts_with("Slot available after:") {
-            let re = regex::Regex::new(r".*in (-?\d+) seconds.*").unwrap();
             for cap in re.captures_iter(line) {
                 // This should neve fail since the regex only allows numbers.
                 sleep = cap[1].parse::<i32>().expect("parse() to i32 failed");
@@ -45,13 +48,89 @@ pub fn overpass_query_need_sleep(ctx: &context::Context) -> i32 {
             break;
         }
         if line.contains("available now") {
-            available = true;
+            // E.g. '2 slots available now.'
+            available_slots = line
+                .split(' ')
+                .next()
+                .and_then(|i| i.parse::<u32>().ok())
+                .unwrap_or(1);
+        }
+    }
+    let seconds_to_wait = if available_slots > 0 { 0 } else { sleep };
+    StatusInfo {
+        available_slots,
+        seconds_to_wait,
+    }
+}
+
+/// Checks if we need to sleep before executing an overpass query.
+pub fn overpass_query_need_sleep(ctx: &context::Context) -> i32 {
+    let url = ctx.get_ini().get_overpass_uri() + "/api/status";
+    let status = match ctx.get_network().urlopen(&url, "") {
+        Ok(value) => value,
+        _ => {
+            return 0;
         }
+    };
+    parse_status(&status).seconds_to_wait
+}
+
+/// Performs a lightweight syntax check of an Overpass QL query, without talking to Overpass: the
+/// query should start with the output settings and its brackets should be balanced. Returns the
+/// found problems.
+pub fn check_query_syntax(query: &str) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+    let first_line = query.lines().next().unwrap_or("").trim();
+    if !first_line.starts_with("[out:") || !first_line.ends_with(';') {
+        problems.push("the first line is not an '[out:...];' settings statement".into());
     }
-    if available {
-        return 0;
+
+    let mut opens: Vec<(char, usize)> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut chars = query.chars().enumerate().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        if let Some(value) = quote {
+            if ch == '\\' {
+                chars.next();
+            } else if ch == value {
+                quote = None;
+            }
+            continue;
+        }
+
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '/' if chars.peek().map(|i| i.1) == Some('/') => {
+                // Line comment.
+                for (_, ch) in chars.by_ref() {
+                    if ch == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' | '[' | '{' => opens.push((ch, offset)),
+            ')' | ']' | '}' => {
+                let expected = match ch {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                match opens.pop() {
+                    Some((open, _)) if open == expected => (),
+                    _ => problems.push(format!("unexpected '{ch}' at offset {offset}")),
+                }
+            }
+            _ => (),
+        }
+    }
+    if quote.is_some() {
+        problems.push("unterminated string".into());
     }
-    sleep
+    for (open, offset) in opens {
+        problems.push(format!("unclosed '{open}' at offset {offset}"));
+    }
+
+    problems
 }
 
 #[cfg(test)]
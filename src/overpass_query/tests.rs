@@ -61,6 +61,50 @@ fn test_overpass_query_need_sleep_wait_negative() {
     assert_eq!(overpass_query_need_sleep(&ctx), 1);
 }
 
+/// Tests parse_status(): the happy path.
+#[test]
+fn test_parse_status() {
+    let status = std::fs::read_to_string("src/fixtures/network/overpass-status-happy.txt").unwrap();
+
+    let info = parse_status(&status);
+
+    assert_eq!(
+        info,
+        StatusInfo {
+            available_slots: 2,
+            seconds_to_wait: 0
+        }
+    );
+}
+
+/// Tests parse_status(): the wait path, the first slot counts.
+#[test]
+fn test_parse_status_wait() {
+    let status = std::fs::read_to_string("src/fixtures/network/overpass-status-wait.txt").unwrap();
+
+    let info = parse_status(&status);
+
+    assert_eq!(
+        info,
+        StatusInfo {
+            available_slots: 0,
+            seconds_to_wait: 12
+        }
+    );
+}
+
+/// Tests parse_status(): a negative wait still waits a bit, a huge one is not clamped here.
+#[test]
+fn test_parse_status_wait_negative_huge() {
+    let negative =
+        std::fs::read_to_string("src/fixtures/network/overpass-status-wait-negative.txt").unwrap();
+    let huge =
+        std::fs::read_to_string("src/fixtures/network/overpass-status-wait-huge.txt").unwrap();
+
+    assert_eq!(parse_status(&negative).seconds_to_wait, 1);
+    assert_eq!(parse_status(&huge).seconds_to_wait, 86401);
+}
+
 /// Tests overpass_query().
 #[test]
 fn test_overpass_query() {
@@ -82,3 +126,19 @@ fn test_overpass_query() {
 
     assert_eq!(buf.starts_with("@id"), true);
 }
+
+/// Tests check_query_syntax().
+#[test]
+fn test_check_query_syntax() {
+    let query = "[out:csv(::id, name)] [timeout:425];\n// comment with a (\nway[\"highway\"!~\"^(footway|path)$\"](area.searchArea);\nout;\n";
+    assert_eq!(check_query_syntax(query).is_empty(), true);
+
+    let query = "area(42->.searchArea;\nway(area.searchArea)];\n";
+    assert_eq!(
+        check_query_syntax(query),
+        [
+            "the first line is not an '[out:...];' settings statement",
+            "unexpected ']' at offset 42",
+        ]
+    );
+}
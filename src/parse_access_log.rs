@@ -13,12 +13,12 @@
 use anyhow::Context;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::io::BufRead;
 use std::io::Write;
 
 use crate::areas;
 use crate::context;
 use crate::stats;
+use crate::util;
 
 /// Does this relation have 100% house number coverage?
 fn is_complete_relation(
@@ -59,35 +59,34 @@ fn get_frequent_relations(
     log_file: &str,
 ) -> anyhow::Result<HashSet<String>> {
     let mut counts: HashMap<String, u64> = HashMap::new();
-    let log_stream = std::io::BufReader::new(std::fs::File::open(log_file)?);
+    let regex = regex::Regex::new(".*\"GET ([^ ]+) .*")?;
     // Example line:
     // a.b.c.d - - [01/Jul/2020:00:08:01 +0200] "GET /osm/street-housenumbers/budapest_12/update-result HTTP/1.1" 200 1747 "-" "Mozilla/5.0 ..."
-    for line in log_stream.lines() {
-        let line = line?;
-        if is_search_bot(&line) {
-            continue;
+    util::for_each_line(ctx, log_file, |line| {
+        if is_search_bot(line) {
+            return Ok(());
         }
-        let regex = regex::Regex::new(".*\"GET ([^ ]+) .*")?;
-        let mut captures_iter = regex.captures_iter(&line);
+        let mut captures_iter = regex.captures_iter(line);
         let group = captures_iter.next();
         if group.is_none() {
             // Not GET.
-            continue;
+            return Ok(());
         }
         let request_uri = &group.unwrap()[1];
         if !request_uri.starts_with("/osm") {
-            continue;
+            return Ok(());
         }
 
         // Expect: /osm/missing-streets/budapest_01/view-turbo
         let tokens: Vec<String> = request_uri.split('/').map(|i| i.to_string()).collect();
         if tokens.len() != 5 {
-            continue;
+            return Ok(());
         }
         let relation_name = tokens[3].to_string();
         let entry = counts.entry(relation_name).or_insert(0);
         (*entry) += 1;
-    }
+        Ok(())
+    })?;
     let mut count_list: Vec<_> = counts.iter().collect();
     // Reverse, by value.
     count_list.sort_by(|a, b| b.1.cmp(a.1));
@@ -125,8 +124,9 @@ fn get_relation_create_dates(
     ])?;
     let mut timestamp = 0_i64;
 
+    let regex = regex::Regex::new("\t([^ :]+):")?;
+    let author_regex = regex::Regex::new("author-time ([0-9]+)")?;
     for line in process_stdout.lines() {
-        let regex = regex::Regex::new("\t([^ :]+):")?;
         let mut captures_iter = regex.captures_iter(line);
         let group = captures_iter.next();
         if let Some(matches) = group {
@@ -138,7 +138,6 @@ fn get_relation_create_dates(
             continue;
         }
 
-        let author_regex = regex::Regex::new("author-time ([0-9]+)")?;
         let mut captures_iter = author_regex.captures_iter(line);
         let group = captures_iter.next();
         if let Some(matches) = group {
@@ -162,8 +161,8 @@ fn is_relation_recently_added(
 
 /// Update frequent_relations based on get_topcities():
 /// 1) The top 5 edited cities count as frequent, even if they have ~no visitors.
-/// 2) If a relation got <5 house numbers in the last 30 days, then they are not frequent, even with
-/// lots of visitors.
+/// 2) If a relation got <5 house numbers in the last 30 days, then they are not frequent, even
+///    with lots of visitors.
 fn check_top_edited_relations(
     ctx: &context::Context,
     frequent_relations: &mut HashSet<String>,
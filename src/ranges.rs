@@ -27,7 +27,7 @@ impl Range {
         Range { start, end, is_odd }
     }
 
-    fn contains(&self, item: i64) -> bool {
+    pub fn contains(&self, item: i64) -> bool {
         if let Some(is_odd) = self.is_odd {
             if is_odd != (item % 2 == 1) {
                 return false;
@@ -42,6 +42,12 @@ impl Range {
     }
 }
 
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
 impl PartialEq for Range {
     fn eq(&self, other: &Self) -> bool {
         if self.start != other.start {
@@ -71,6 +77,11 @@ impl Ranges {
         Ranges { items }
     }
 
+    /// Gets the individual ranges.
+    pub fn get_items(&self) -> &[Range] {
+        &self.items
+    }
+
     pub fn contains(&self, item: i64) -> bool {
         for i in &self.items {
             if i.contains(item) {
@@ -300,7 +300,61 @@ pub fn init(conn: &mut rusqlite::Connection) -> anyhow::Result<()> {
         )?;
     }
 
-    tx.execute("pragma user_version = 16", [])?;
+    if user_version < 17 {
+        // Tracks the last editor of housenumbers from OSM, so their edits can be excluded.
+        tx.execute_batch("alter table osm_housenumbers add column user text not null default '';")?;
+    }
+
+    if user_version < 18 {
+        // Reference house numbers which can't be interpreted, so the source can be fixed.
+        tx.execute_batch(
+            "create table relation_unparseable_refs (
+                    relation_name text not null,
+                    street_name text not null,
+                    housenumber text not null
+                );
+            create index idx_relation_unparseable_refs
+                on relation_unparseable_refs(relation_name);",
+        )?;
+    }
+
+    if user_version < 19 {
+        // Tracks the house number coverage of a relation over time, one row per day.
+        tx.execute_batch(
+            "create table osm_housenumber_coverage_history (
+                    relation_name text not null,
+                    date text not null,
+                    coverage text not null,
+                    unique(relation_name, date)
+                );",
+        )?;
+    }
+
+    if user_version < 20 {
+        // Tracks the inputs of the house number coverage, so unchanged relations can be skipped.
+        tx.execute_batch(
+            "alter table osm_housenumber_coverages add column input_hash text not null default '';",
+        )?;
+    }
+
+    if user_version < 21 {
+        // The coverage history moved to workdir/<relation>.percent.log.
+        tx.execute_batch("drop table osm_housenumber_coverage_history;")?;
+    }
+
+    if user_version < 22 {
+        // Per-relation, per-language cache for the missing-housenumbers HTML output.
+        tx.execute_batch(
+            "create table missing_housenumbers_html_cache (
+                    relation text not null,
+                    language text not null,
+                    html text not null,
+                    unique(relation, language)
+                );",
+        )?;
+    }
+
+    tx.execute("pragma user_version = 22", [])?;
     tx.commit()?;
     Ok(())
 }
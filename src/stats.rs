@@ -77,7 +77,7 @@ fn handle_capital_progress(
         let ref_path = ctx.get_ini().get_reference_citycounts_path()?;
         let stream = ctx.get_file_system().open_read(&ref_path)?;
         let mut guard = stream.borrow_mut();
-        let mut read = guard.deref_mut();
+        let mut read = util::make_reference_reader(&ref_path, guard.deref_mut())?;
         let mut csv_reader = util::make_csv_reader(&mut read);
         for result in csv_reader.deserialize() {
             let row: util::CityCount = result?;
@@ -451,10 +451,10 @@ pub fn generate_json(
         .context("handle_monthly_total failed")?;
     handle_invalid_addr_cities(ctx, &mut j, /*day_range=*/ 14)
         .context("invalid_addr_cities failed")?;
-    let stream = ctx.get_file_system().open_write(json_path)?;
-    let mut guard = stream.borrow_mut();
-    let write = guard.deref_mut();
-    serde_json::to_writer(write, &j)?;
+    // Serialize first, so a failure doesn't leave a half-written stats.json behind.
+    let json = serde_json::to_string(&j)?;
+    ctx.get_file_system()
+        .write_from_string_atomic(&json, json_path)?;
 
     Ok(())
 }
@@ -495,6 +495,14 @@ pub fn has_sql_mtime(ctx: &context::Context, page: &str) -> anyhow::Result<bool>
     Ok(rows.next()?.is_some())
 }
 
+/// Decides if the whole-country house number count of a day (YYYY-MM-DD) is known.
+pub fn has_count(ctx: &context::Context, date: &str) -> anyhow::Result<bool> {
+    let conn = ctx.get_database_connection()?;
+    let mut stmt = conn.prepare("select count from stats_counts where date = ?1")?;
+    let mut rows = stmt.query([date])?;
+    Ok(rows.next()?.is_some())
+}
+
 pub fn set_sql_count(
     ctx: &context::Context,
     table: &str,
@@ -633,5 +641,45 @@ pub fn update_invalid_addr_cities(ctx: &context::Context) -> anyhow::Result<()>
     Ok(())
 }
 
+/// Only this many of the most recent coverage history points are used to estimate completion.
+const COMPLETION_RECENT_POINTS: usize = 30;
+
+/// Estimates when the coverage reaches 100%, based on a linear regression of the recent (date,
+/// percent) points. Returns None if the trend is flat or negative, or there is too little data.
+pub fn estimate_completion(series: &[(time::Date, f64)]) -> Option<time::Date> {
+    let recent = &series[series.len().saturating_sub(COMPLETION_RECENT_POINTS)..];
+    let (first_date, _) = recent.first()?;
+    let (last_date, last_percent) = recent.last()?;
+    if recent.len() < 2 || *last_percent >= 100_f64 {
+        return None;
+    }
+
+    let points: Vec<(f64, f64)> = recent
+        .iter()
+        .map(|(date, percent)| ((*date - *first_date).whole_days() as f64, *percent))
+        .collect();
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|i| i.0).sum::<f64>() / count;
+    let mean_y = points.iter().map(|i| i.1).sum::<f64>() / count;
+    let sxx: f64 = points.iter().map(|i| (i.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|i| (i.0 - mean_x) * (i.1 - mean_y)).sum();
+    if sxx == 0_f64 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    if slope <= 0_f64 {
+        return None;
+    }
+
+    let intercept = mean_y - slope * mean_x;
+    let last_x = (*last_date - *first_date).whole_days() as f64;
+    let days = ((100_f64 - intercept) / slope - last_x).ceil().max(0_f64);
+    // Don't promise anything beyond a century.
+    if days > 36500_f64 {
+        return None;
+    }
+    last_date.checked_add(time::Duration::days(days as i64))
+}
+
 #[cfg(test)]
 mod tests;
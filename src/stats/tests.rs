@@ -11,6 +11,7 @@
 //! Tests for the stats module.
 
 use super::*;
+use std::io::Write as _;
 use std::rc::Rc;
 
 use crate::context::FileSystem as _;
@@ -493,3 +494,81 @@ fn test_update_invalid_addr_cities() {
         assert_eq!(count, 1);
     }
 }
+
+/// Tests generate_json(): a failed write keeps the previous stats.json.
+#[test]
+fn test_generate_json_error() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let ref_count = context::tests::TestFileSystem::make_file();
+    ref_count.borrow_mut().write_all(b"300\n").unwrap();
+    let stats_json = context::tests::TestFileSystem::make_file();
+    stats_json
+        .borrow_mut()
+        .write_all(b"{\"old\":true}")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            ("workdir/stats/ref.count", &ref_count),
+            ("workdir/stats/stats.json", &stats_json),
+        ],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    file_system.set_fail_writes(true);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let state_dir = ctx.get_abspath("workdir/stats");
+    let json_path = ctx.get_abspath("workdir/stats/stats.json");
+
+    let ret = generate_json(&ctx, &state_dir, &json_path);
+
+    // The stats were generated, but writing them failed.
+    assert_eq!(
+        ret.unwrap_err().to_string(),
+        format!("open_write: failed to write '{json_path}.tmp1'")
+    );
+    assert_eq!(
+        context::tests::TestFileSystem::get_content(&stats_json),
+        "{\"old\":true}"
+    );
+}
+
+/// Tests estimate_completion(): an improving trend.
+#[test]
+fn test_estimate_completion() {
+    let series = [
+        (time::macros::date!(2020 - 05 - 01), 50_f64),
+        (time::macros::date!(2020 - 05 - 02), 51_f64),
+        (time::macros::date!(2020 - 05 - 03), 52_f64),
+    ];
+
+    let ret = estimate_completion(&series);
+
+    // 1% per day, 48% is missing.
+    assert_eq!(ret, Some(time::macros::date!(2020 - 06 - 20)));
+}
+
+/// Tests estimate_completion(): a flat trend.
+#[test]
+fn test_estimate_completion_flat() {
+    let series = [
+        (time::macros::date!(2020 - 05 - 01), 50_f64),
+        (time::macros::date!(2020 - 05 - 02), 50_f64),
+        (time::macros::date!(2020 - 05 - 03), 50_f64),
+    ];
+
+    assert_eq!(estimate_completion(&series), None);
+}
+
+/// Tests estimate_completion(): a negative trend or a single point.
+#[test]
+fn test_estimate_completion_negative() {
+    let series = [
+        (time::macros::date!(2020 - 05 - 01), 52_f64),
+        (time::macros::date!(2020 - 05 - 02), 51_f64),
+    ];
+
+    assert_eq!(estimate_completion(&series), None);
+    assert_eq!(estimate_completion(&series[..1]), None);
+}
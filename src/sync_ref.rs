@@ -64,13 +64,13 @@ pub fn download(
             if dests.contains(&path) {
                 continue;
             }
-            let relpath = path.strip_prefix(&ctx.get_abspath("")).unwrap();
+            let relpath = path.strip_prefix(&ctx.get_abspath("")).unwrap_or(&path);
             stream.write_all(format!("sync-ref: removing '{relpath}'...\n").as_bytes())?;
             ctx.get_file_system().unlink(&path)?;
         }
 
         ctx.get_file_system()
-            .write_from_string(&config_data, &ctx.get_abspath("workdir/wsgi.ini"))?;
+            .write_from_string(&config_data, &ctx.get_ini().get_config_path())?;
     }
 
     stream.write_all("sync-ref: removing old index...\n".as_bytes())?;
@@ -22,6 +22,7 @@ use anyhow::Context;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::io::BufRead;
 use std::io::Read;
 use std::ops::DerefMut;
 
@@ -36,6 +37,8 @@ lazy_static! {
     static ref LETTER_SUFFIX: regex::Regex = regex::Regex::new(r".*([A-Za-z]+)\*?").unwrap();
     static ref NUMBER_SUFFIX: regex::Regex = regex::Regex::new(r"^.*/([0-9])\*?$").unwrap();
     static ref NULL_END: regex::Regex = regex::Regex::new(r" null$").unwrap();
+    static ref STREET_AND_NUMBER: regex::Regex =
+        regex::Regex::new(r"^(.+?)\s+([0-9][\w/-]*)$").unwrap();
     static ref GIT_HASH: regex::Regex = regex::Regex::new(r".*-g([0-9a-f]+)(-modified)?").unwrap();
 }
 
@@ -438,15 +441,182 @@ pub struct ZipCount {
     pub count: u64,
 }
 
-/// Creates a new typed CSV reader.
-pub fn make_csv_reader(read: &mut dyn Read) -> csv::Reader<&mut dyn Read> {
+/// Calls f for each line of the file at path, streaming it, so large files are not read into
+/// memory at once.
+pub fn for_each_line(
+    ctx: &context::Context,
+    path: &str,
+    mut f: impl FnMut(&str) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let stream = ctx
+        .get_file_system()
+        .open_read(path)
+        .context("open_read() failed")?;
+    let mut guard = stream.borrow_mut();
+    let reader = std::io::BufReader::new(guard.deref_mut());
+    for line in reader.lines() {
+        f(&line?)?;
+    }
+    Ok(())
+}
+
+/// Creates a new typed CSV reader. A leading UTF-8 BOM is skipped, so it doesn't end up in the
+/// first header.
+pub fn make_csv_reader(read: &mut dyn Read) -> csv::Reader<std::io::BufReader<&mut dyn Read>> {
+    let mut buf_read = std::io::BufReader::new(read);
+    if let Ok(bytes) = buf_read.fill_buf() {
+        if bytes.starts_with(b"\xEF\xBB\xBF") {
+            buf_read.consume(3);
+        }
+    }
     let reader = csv::ReaderBuilder::new()
         .delimiter(b'\t')
         .double_quote(true)
-        .from_reader(read);
+        .from_reader(buf_read);
     reader
 }
 
+/// Maps the 0x80..=0xFF bytes of Windows-1250 to Unicode code points, 0 means undefined.
+const WINDOWS_1250: [u16; 128] = [
+    0x20AC, 0, 0x201A, 0, 0x201E, 0x2026, 0x2020, 0x2021, 0, 0x2030, 0x0160, 0x2039, 0x015A,
+    0x0164, 0x017D, 0x0179, 0, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0, 0x2122,
+    0x0161, 0x203A, 0x015B, 0x0165, 0x017E, 0x017A, 0x00A0, 0x02C7, 0x02D8, 0x0141, 0x00A4, 0x0104,
+    0x00A6, 0x00A7, 0x00A8, 0x00A9, 0x015E, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x017B, 0x00B0, 0x00B1,
+    0x02DB, 0x0142, 0x00B4, 0x00B5, 0x00B6, 0x00B7, 0x00B8, 0x0105, 0x015F, 0x00BB, 0x013D, 0x02DD,
+    0x013E, 0x017C, 0x0154, 0x00C1, 0x00C2, 0x0102, 0x00C4, 0x0139, 0x0106, 0x00C7, 0x010C, 0x00C9,
+    0x0118, 0x00CB, 0x011A, 0x00CD, 0x00CE, 0x010E, 0x0110, 0x0143, 0x0147, 0x00D3, 0x00D4, 0x0150,
+    0x00D6, 0x00D7, 0x0158, 0x016E, 0x00DA, 0x0170, 0x00DC, 0x00DD, 0x0162, 0x00DF, 0x0155, 0x00E1,
+    0x00E2, 0x0103, 0x00E4, 0x013A, 0x0107, 0x00E7, 0x010D, 0x00E9, 0x0119, 0x00EB, 0x011B, 0x00ED,
+    0x00EE, 0x010F, 0x0111, 0x0144, 0x0148, 0x00F3, 0x00F4, 0x0151, 0x00F6, 0x00F7, 0x0159, 0x016F,
+    0x00FA, 0x0171, 0x00FC, 0x00FD, 0x0163, 0x02D9,
+];
+
+/// Decodes text line by line, which is UTF-8 in most cases, but may be in the legacy Windows-1250
+/// encoding, which covers all Hungarian letters. Falling back to Windows-1250 is only allowed while
+/// no valid UTF-8 multi-byte sequence was seen, so mixed input is an error, not mojibake.
+#[derive(Default)]
+struct LenientDecoder {
+    /// The offset of the next line in the input.
+    offset: usize,
+    /// If a line had non-ASCII UTF-8 content.
+    utf8: bool,
+    /// If a line had Windows-1250 content.
+    windows_1250: bool,
+}
+
+impl LenientDecoder {
+    fn decode_line(&mut self, line: &[u8]) -> anyhow::Result<String> {
+        let offset = self.offset;
+        self.offset += line.len();
+        let err = match std::str::from_utf8(line) {
+            Ok(value) => {
+                if !value.is_ascii() {
+                    if self.windows_1250 {
+                        return Err(anyhow!(
+                            "UTF-8 line at offset {offset} after Windows-1250 content"
+                        ));
+                    }
+                    self.utf8 = true;
+                }
+                return Ok(value.to_string());
+            }
+            Err(err) => err,
+        };
+
+        if self.utf8 {
+            return Err(anyhow!(
+                "invalid UTF-8 at offset {} after UTF-8 content",
+                offset + err.valid_up_to()
+            ));
+        }
+        self.windows_1250 = true;
+        let mut ret = String::with_capacity(line.len());
+        for (index, byte) in line.iter().enumerate() {
+            if byte.is_ascii() {
+                ret.push(*byte as char);
+                continue;
+            }
+
+            let code_point = WINDOWS_1250[(byte - 0x80) as usize];
+            if code_point == 0 {
+                return Err(anyhow!(
+                    "byte {byte:#04x} at offset {} is neither UTF-8 nor Windows-1250",
+                    offset + index
+                ));
+            }
+            ret.push(char::from_u32(code_point.into()).unwrap());
+        }
+        Ok(ret)
+    }
+}
+
+/// Decodes text which is UTF-8 in most cases, but may be in the legacy Windows-1250 encoding. Fails
+/// with the offset of the first byte which is invalid in both encodings, or in case of mixed
+/// encodings.
+pub fn read_text_lenient(bytes: &[u8]) -> anyhow::Result<String> {
+    let mut decoder = LenientDecoder::default();
+    let mut ret = String::with_capacity(bytes.len());
+    for line in bytes.split_inclusive(|i| *i == b'\n') {
+        ret.push_str(&decoder.decode_line(line)?);
+    }
+    Ok(ret)
+}
+
+/// A reference file stream, decoded to UTF-8 one line at a time.
+pub struct ReferenceReader<'a> {
+    path: String,
+    inner: Box<dyn BufRead + 'a>,
+    decoder: LenientDecoder,
+    line: Vec<u8>,
+    decoded: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ReferenceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            self.line.clear();
+            if self.inner.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+            let decoded = self.decoder.decode_line(&self.line).map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("failed to decode '{}': {err}", self.path),
+                )
+            })?;
+            self.decoded = decoded.into_bytes();
+            self.pos = 0;
+        }
+        let len = buf.len().min(self.decoded.len() - self.pos);
+        buf[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Wraps a reference file stream: paths with a `.gz` suffix are decompressed on the fly, others are
+/// read as-is. The content is expected to be UTF-8, with a fallback to Windows-1250. The file is
+/// streamed, not read into memory at once.
+pub fn make_reference_reader<'a>(
+    path: &str,
+    read: &'a mut dyn Read,
+) -> anyhow::Result<ReferenceReader<'a>> {
+    let inner: Box<dyn BufRead + 'a> = if path.ends_with(".gz") {
+        Box::new(std::io::BufReader::new(flate2::read::GzDecoder::new(read)))
+    } else {
+        Box::new(std::io::BufReader::new(read))
+    };
+    Ok(ReferenceReader {
+        path: path.to_string(),
+        inner,
+        decoder: LenientDecoder::default(),
+        line: Vec::new(),
+        decoded: Vec::new(),
+        pos: 0,
+    })
+}
+
 /// Splits house_number into a numerical and a remainder part.
 pub fn split_house_number(house_number: &str) -> (i32, String) {
     let mut number = 0;
@@ -616,44 +786,44 @@ pub fn build_reference_index(ctx: &context::Context, paths: &[String]) -> anyhow
     }
 
     for abspath in paths {
-        let mut tokens = abspath.split('/');
-        let path = tokens.next_back().unwrap();
-        if path.starts_with("hazszamok_kieg") {
-            let stream = ctx.get_file_system().open_read(abspath)?;
-            let mut guard = stream.borrow_mut();
-            let read = std::io::BufReader::new(guard.deref_mut());
-            let mut reader = csv::ReaderBuilder::new()
-                .delimiter(b'\t')
-                .double_quote(true)
-                .from_reader(read);
-            let tx = conn.transaction()?;
-            for result in reader.deserialize() {
-                let row: RefHouseNumber = result?;
-                tx.execute(
-                        "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values (?1, ?2, ?3, ?4, ?5)",
-                        [row.county, row.settlement, row.street, row.housenumber, row.comment.unwrap_or(" ".into())],
-                    )?;
-            }
-            tx.commit()?;
+        let tx = conn.transaction()?;
+        for_each_reference_housenumber(ctx, abspath, &mut |row| {
+            tx.execute(
+                "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values (?1, ?2, ?3, ?4, ?5)",
+                [row.county, row.settlement, row.street, row.housenumber, row.comment.unwrap_or_default()],
+            )?;
+            Ok(())
+        })?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Parses a reference TSV (house number version) and calls `callback` for each row. The comment of
+/// the row is always set: rows from "hazszamok_kieg" files without a comment get " ", rows from
+/// "hazszamok_" files get "".
+pub fn for_each_reference_housenumber(
+    ctx: &context::Context,
+    abspath: &str,
+    callback: &mut dyn FnMut(RefHouseNumber) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut tokens = abspath.split('/');
+    let path = tokens.next_back().unwrap();
+    let kieg = path.starts_with("hazszamok_kieg");
+    let stream = ctx.get_file_system().open_read(abspath)?;
+    let mut guard = stream.borrow_mut();
+    let mut read = make_reference_reader(abspath, guard.deref_mut())?;
+    let mut reader = make_csv_reader(&mut read);
+    for result in reader.deserialize() {
+        let mut row: RefHouseNumber = result?;
+        if kieg {
+            row.comment = Some(row.comment.unwrap_or(" ".into()));
         } else {
             // Assume "hazszamok_".
-            let stream = ctx.get_file_system().open_read(abspath)?;
-            let mut guard = stream.borrow_mut();
-            let read = std::io::BufReader::new(guard.deref_mut());
-            let mut reader = csv::ReaderBuilder::new()
-                .delimiter(b'\t')
-                .double_quote(true)
-                .from_reader(read);
-            let tx = conn.transaction()?;
-            for result in reader.deserialize() {
-                let row: RefHouseNumber = result?;
-                tx.execute(
-                          "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values (?1, ?2, ?3, ?4, '')",
-                          [row.county, row.settlement, row.street, row.housenumber],
-                      )?;
-            }
-            tx.commit()?;
+            row.comment = Some("".into());
         }
+        callback(row)?;
     }
 
     Ok(())
@@ -676,11 +846,8 @@ pub fn build_street_reference_index(ctx: &context::Context, path: &str) -> anyho
 
     let stream = ctx.get_file_system().open_read(path)?;
     let mut guard = stream.borrow_mut();
-    let read = std::io::BufReader::new(guard.deref_mut());
-    let mut reader = csv::ReaderBuilder::new()
-        .delimiter(b'\t')
-        .double_quote(true)
-        .from_reader(read);
+    let mut read = make_reference_reader(path, guard.deref_mut())?;
+    let mut reader = make_csv_reader(&mut read);
     let tx = conn.transaction()?;
     for result in reader.deserialize() {
         let row: RefStreet = result?;
@@ -770,11 +937,20 @@ pub fn write_html_header(doc: &yattag::Doc) {
 }
 
 /// Turns an overpass query template to an actual query.
-pub fn process_template(buf: &str, osm_relation: u64) -> String {
-    let mut buf = buf.replace("@RELATION@", &osm_relation.to_string());
+pub fn process_template(buf: &str, osm_relations: &[u64]) -> String {
     // area is relation + 3600000000 (3600000000 == relation), see js/ide.js
     // in https://github.com/tyrasd/overpass-turbo
-    buf = buf.replace("@AREA@", &(3600000000 + osm_relation).to_string());
+    let areas: Vec<u64> = osm_relations.iter().map(|i| 3600000000 + i).collect();
+    let join = |ids: &[u64]| -> String {
+        let ids: Vec<String> = ids.iter().map(|i| i.to_string()).collect();
+        if ids.len() == 1 {
+            return ids[0].clone();
+        }
+        // Multiple relations: the id filter gives their union.
+        format!("id:{}", ids.join(","))
+    };
+    let mut buf = buf.replace("@RELATION@", &join(osm_relations));
+    buf = buf.replace("@AREA@", &join(&areas));
     buf
 }
 
@@ -975,6 +1151,8 @@ pub struct OsmHouseNumber {
     pub place: Option<String>,
     /// Object type.
     pub object_type: String,
+    /// The user who last modified the object.
+    pub user: String,
 }
 
 impl OsmHouseNumber {
@@ -986,12 +1164,14 @@ impl OsmHouseNumber {
         street: &str,
         place: &Option<String>,
         object_type: &str,
+        user: &str,
     ) -> Self {
         let housenumber = housenumber.to_string();
         let conscriptionnumber = conscriptionnumber.to_string();
         let street = street.to_string();
         let place = place.clone();
         let object_type = object_type.to_string();
+        let user = user.to_string();
         OsmHouseNumber {
             id,
             housenumber,
@@ -999,6 +1179,7 @@ impl OsmHouseNumber {
             street,
             place,
             object_type,
+            user,
         }
     }
 }
@@ -1049,6 +1230,63 @@ pub fn get_housenumber_ranges(house_numbers: &[HouseNumber]) -> Vec<HouseNumberR
     ret
 }
 
+/// Collapses runs of at least 3 house numbers with a step of 1 or 2 into a single item, e.g.
+/// 2, 4, ..., 20 is "2-20 (even)". Numbers with a suffix and isolated numbers are kept as-is.
+pub fn collapse_ranges(house_numbers: &[HouseNumber]) -> Vec<String> {
+    let mut numbers: Vec<i32> = Vec::new();
+    let mut ret: Vec<((i32, String), String)> = Vec::new();
+    for house_number in house_numbers {
+        let number = house_number.get_number();
+        match number.parse::<i32>() {
+            Ok(value) => numbers.push(value),
+            Err(_) => ret.push((split_house_number(number), number.to_string())),
+        }
+    }
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    let mut index = 0;
+    while index < numbers.len() {
+        let start = numbers[index];
+        let mut end_index = index;
+        if index + 1 < numbers.len() {
+            let step = numbers[index + 1] - start;
+            if step == 1 || step == 2 {
+                while end_index + 1 < numbers.len()
+                    && numbers[end_index + 1] - numbers[end_index] == step
+                {
+                    end_index += 1;
+                }
+                if end_index - index + 1 >= 3 {
+                    let end = numbers[end_index];
+                    let string = match step {
+                        1 => format!("{start}-{end}"),
+                        _ if start % 2 == 0 => format!("{start}-{end} (even)"),
+                        _ => format!("{start}-{end} (odd)"),
+                    };
+                    ret.push(((start, "".into()), string));
+                    index = end_index + 1;
+                    continue;
+                }
+            }
+        }
+        ret.push(((start, "".into()), start.to_string()));
+        index += 1;
+    }
+
+    ret.sort_by(|a, b| a.0.cmp(&b.0));
+    ret.into_iter().map(|(_key, value)| value).collect()
+}
+
+/// Splits house numbers into the even and the odd side of the street. Numbers with a suffix (e.g.
+/// 7/A) are on the side of their numeric part.
+pub fn partition_by_parity(house_numbers: &[HouseNumber]) -> (Vec<HouseNumber>, Vec<HouseNumber>) {
+    house_numbers
+        .iter()
+        .cloned()
+        .partition(|i| split_house_number(i.get_number()).0 % 2 == 0)
+}
+
 /// Generates a HTML link based on a website prefix and a git-describe version.
 pub fn git_link(version: &str, prefix: &str) -> yattag::Doc {
     let mut commit_hash: String = "".into();
@@ -1164,6 +1402,70 @@ pub fn split_house_number_by_separator(
     (ret_numbers, ret_numbers_nofilter)
 }
 
+/// Decides if a relation name is safe to be used in file paths: only ASCII letters, digits, dashes
+/// and underscores.
+pub fn is_safe_relation_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|i| i.is_ascii_alphanumeric() || i == '-' || i == '_')
+}
+
+/// Decides if a reference house number (range) can be interpreted, i.e. all its parts contain a
+/// number. The comment after a tab is ignored.
+pub fn is_parseable_house_number(house_numbers: &str) -> bool {
+    let house_numbers = house_numbers.split('\t').next().unwrap_or("");
+    let separator = if house_numbers.contains(';') {
+        ";"
+    } else if house_numbers.contains(',') {
+        ","
+    } else {
+        "-"
+    };
+    house_numbers
+        .split(separator)
+        .all(|i| NUMBER_WITH_JUNK.is_match(i))
+}
+
+/// Splits a combined 'street housenumber' value (e.g. addr:full) into a street and a house number.
+/// The trailing group starting with a digit is the house number, if there is none, then the whole
+/// input is the street name.
+pub fn split_street_and_number(full: &str) -> (String, String) {
+    let full = full.trim();
+    match STREET_AND_NUMBER.captures(full) {
+        Some(cap) => (cap[1].to_string(), cap[2].to_string()),
+        None => (full.to_string(), "".to_string()),
+    }
+}
+
+/// Computes HMAC-SHA256 (RFC 2104) of `message`, using `key`.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    use sha2::Digest as _;
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0_u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        let digest = sha2::Sha256::digest(key);
+        block[..digest.len()].copy_from_slice(&digest);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = sha2::Sha256::new();
+    inner.update(block.iter().map(|i| i ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+    let mut outer = sha2::Sha256::new();
+    outer.update(block.iter().map(|i| i ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+/// Replaces a user name with a keyed hash (HMAC-SHA256, using the salt as the key), so stats
+/// don't expose editor names. The hex digest is truncated to 16 characters.
+pub fn anonymize_user(salt: &str, user: &str) -> String {
+    let digest = hmac_sha256(salt.as_bytes(), user.as_bytes());
+    let hex: String = digest.iter().map(|i| format!("{i:02x}")).collect();
+    format!("user-{}", &hex[..16])
+}
+
 /// Constructs a city name based on postcode the nominal city.
 pub fn get_city_key(
     postcode: &str,
@@ -1195,6 +1497,16 @@ pub fn get_city_key(
     Ok("_Empty".into())
 }
 
+/// Hashes `bytes` using 64-bit FNV-1a, which is stable across runs, unlike the std hashers.
+pub fn get_stable_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
 /// Returns a string comparator which allows locale-aware lexical sorting.
 #[cfg(feature = "icu")]
 pub fn get_sort_key(bytes: &str) -> Vec<u8> {
@@ -1213,6 +1525,17 @@ pub fn get_sort_key(bytes: &str) -> Vec<u8> {
     bytes.as_bytes().to_vec()
 }
 
+/// Replaces accented letters with their base form, e.g. 'Ő' -> 'O'. Only use this for matching,
+/// not for display.
+pub fn fold_diacritics(string: &str) -> String {
+    unidecode::unidecode(string)
+}
+
+/// Compares two strings, ignoring case and diacritics, e.g. "Fő utca" equals "fo Utca".
+pub fn casefold_eq(a: &str, b: &str) -> bool {
+    fold_diacritics(a).to_lowercase() == fold_diacritics(b).to_lowercase()
+}
+
 /// Builds a set of valid settlement names.
 pub fn get_valid_settlements(ctx: &context::Context) -> anyhow::Result<HashSet<String>> {
     let mut settlements: HashSet<String> = HashSet::new();
@@ -1223,7 +1546,7 @@ pub fn get_valid_settlements(ctx: &context::Context) -> anyhow::Result<HashSet<S
         .open_read(&path)
         .context("open_read() failed")?;
     let mut guard = stream.borrow_mut();
-    let mut read = guard.deref_mut();
+    let mut read = make_reference_reader(&path, guard.deref_mut())?;
     let mut csv_reader = make_csv_reader(&mut read);
     for result in csv_reader.deserialize() {
         let row: CityCount = match result {
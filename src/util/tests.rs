@@ -310,7 +310,16 @@ fn test_gen_link() {
 fn test_process_template() {
     let template = "aaa @RELATION@ bbb @AREA@ ccc";
     let expected = "aaa 42 bbb 3600000042 ccc";
-    let actual = process_template(template, 42);
+    let actual = process_template(template, &[42]);
+    assert_eq!(actual, expected);
+}
+
+/// Tests process_template(): multiple relations.
+#[test]
+fn test_process_template_multiple() {
+    let template = "rel(@RELATION@); area(@AREA@);";
+    let expected = "rel(id:42,43); area(id:3600000042,3600000043);";
+    let actual = process_template(template, &[42, 43]);
     assert_eq!(actual, expected);
 }
 
@@ -507,6 +516,82 @@ fn test_git_link() {
     assert_eq!(actual, expected);
 }
 
+/// Tests collapse_ranges(): even steps.
+#[test]
+fn test_collapse_ranges_even() {
+    let house_numbers: Vec<HouseNumber> = (1..=10)
+        .map(|i| HouseNumber::new(&(i * 2).to_string(), "", ""))
+        .collect();
+    assert_eq!(collapse_ranges(&house_numbers), ["2-20 (even)"]);
+}
+
+/// Tests collapse_ranges(): odd steps.
+#[test]
+fn test_collapse_ranges_odd() {
+    let house_numbers = [
+        HouseNumber::new("5", "", ""),
+        HouseNumber::new("1", "", ""),
+        HouseNumber::new("3", "", ""),
+        HouseNumber::new("9", "", ""),
+    ];
+    assert_eq!(collapse_ranges(&house_numbers), ["1-5 (odd)", "9"]);
+}
+
+/// Tests collapse_ranges(): mixed input, with a step of 1, isolated numbers and suffixes.
+#[test]
+fn test_collapse_ranges_mixed() {
+    let house_numbers = [
+        HouseNumber::new("1", "", ""),
+        HouseNumber::new("2", "", ""),
+        HouseNumber::new("3", "", ""),
+        HouseNumber::new("4", "", ""),
+        HouseNumber::new("7", "", ""),
+        HouseNumber::new("7/A", "", ""),
+        HouseNumber::new("10", "", ""),
+        HouseNumber::new("12", "", ""),
+    ];
+    assert_eq!(
+        collapse_ranges(&house_numbers),
+        ["1-4", "7", "7/A", "10", "12"]
+    );
+}
+
+/// Tests partition_by_parity(): a street with even numbers only.
+#[test]
+fn test_partition_by_parity_even() {
+    let house_numbers = [HouseNumber::new("2", "", ""), HouseNumber::new("4", "", "")];
+    let (even, odd) = partition_by_parity(&house_numbers);
+    let even: Vec<_> = even.iter().map(|i| i.get_number()).collect();
+    assert_eq!(even, ["2", "4"]);
+    assert!(odd.is_empty());
+}
+
+/// Tests partition_by_parity(): a street with odd numbers only.
+#[test]
+fn test_partition_by_parity_odd() {
+    let house_numbers = [HouseNumber::new("1", "", ""), HouseNumber::new("3", "", "")];
+    let (even, odd) = partition_by_parity(&house_numbers);
+    assert!(even.is_empty());
+    let odd: Vec<_> = odd.iter().map(|i| i.get_number()).collect();
+    assert_eq!(odd, ["1", "3"]);
+}
+
+/// Tests partition_by_parity(): both sides, suffixes keep the parity of their numeric part.
+#[test]
+fn test_partition_by_parity_mixed() {
+    let house_numbers = [
+        HouseNumber::new("1", "", ""),
+        HouseNumber::new("2", "", ""),
+        HouseNumber::new("7/A", "", ""),
+        HouseNumber::new("10b", "", ""),
+    ];
+    let (even, odd) = partition_by_parity(&house_numbers);
+    let even: Vec<_> = even.iter().map(|i| i.get_number()).collect();
+    assert_eq!(even, ["2", "10b"]);
+    let odd: Vec<_> = odd.iter().map(|i| i.get_number()).collect();
+    assert_eq!(odd, ["1", "7/A"]);
+}
+
 /// Tests sort_numerically(): numbers.
 #[test]
 fn test_sort_numerically_numbers() {
@@ -552,6 +637,63 @@ fn test_street() {
     assert_eq!(street.to_html().get_value(), "foo<br />(bar)");
 }
 
+/// Tests split_street_and_number().
+#[test]
+fn test_split_street_and_number() {
+    assert_eq!(
+        split_street_and_number("Fő utca 12"),
+        ("Fő utca".to_string(), "12".to_string())
+    );
+    assert_eq!(
+        split_street_and_number("Fő utca 12/a"),
+        ("Fő utca".to_string(), "12/a".to_string())
+    );
+    // A number in the street name is not a house number.
+    assert_eq!(
+        split_street_and_number("1848 utca"),
+        ("1848 utca".to_string(), "".to_string())
+    );
+    assert_eq!(
+        split_street_and_number("1848 utca 3"),
+        ("1848 utca".to_string(), "3".to_string())
+    );
+}
+
+/// Tests hmac_sha256(), using test cases from RFC 4231.
+#[test]
+fn test_hmac_sha256() {
+    let to_hex = |bytes: Vec<u8>| -> String { bytes.iter().map(|i| format!("{i:02x}")).collect() };
+    assert_eq!(
+        to_hex(hmac_sha256(&[0x0b; 20], b"Hi There")),
+        "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+    );
+    assert_eq!(
+        to_hex(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    // A key larger than the block size is hashed first.
+    assert_eq!(
+        to_hex(hmac_sha256(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First"
+        )),
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+}
+
+/// Tests anonymize_user().
+#[test]
+fn test_anonymize_user() {
+    let hashed = anonymize_user("mysalt", "myuser1");
+    assert!(hashed.starts_with("user-"));
+    assert_eq!(hashed.len(), "user-".len() + 16);
+    assert!(!hashed.contains("myuser1"));
+    // Stable for the same input, different for a different user or salt.
+    assert_eq!(anonymize_user("mysalt", "myuser1"), hashed);
+    assert_ne!(anonymize_user("mysalt", "myuser2"), hashed);
+    assert_ne!(anonymize_user("othersalt", "myuser1"), hashed);
+}
+
 /// Tests get_city_key().
 #[test]
 fn test_get_city_key() {
@@ -598,6 +740,7 @@ fn test_get_street_from_housenumber_addr_place() {
     let street = "";
     let place = &Some("Tolvajos tanya".to_string());
     let object_type = "node";
+    let user = "";
     let housenumber = OsmHouseNumber::new(
         id,
         housenumber,
@@ -605,6 +748,7 @@ fn test_get_street_from_housenumber_addr_place() {
         street,
         place,
         object_type,
+        user,
     );
 
     let actual = get_street_from_housenumber(&[housenumber]).unwrap();
@@ -707,6 +851,40 @@ fn test_get_valid_settlements() {
     assert_eq!(ret, expected);
 }
 
+/// Tests get_valid_settlements(): a gzipped citycounts reference gives the same result.
+#[test]
+fn test_get_valid_settlements_gz() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_value(
+        &mut ctx,
+        "reference_citycounts",
+        "workdir/refs/varosok_count_20190717.tsv.gz",
+    )
+    .unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(b"CITY\tCNT\tORIG\nmycity1\t1\tMycity1\nmycity2\t2\tMycity2\n")
+        .unwrap();
+    let citycounts = context::tests::TestFileSystem::make_file();
+    citycounts
+        .borrow_mut()
+        .write_all(&encoder.finish().unwrap())
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/refs/varosok_count_20190717.tsv.gz", &citycounts)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = get_valid_settlements(&ctx).unwrap();
+
+    let mut expected: HashSet<String> = HashSet::new();
+    expected.insert("Mycity1".to_string());
+    expected.insert("Mycity2".to_string());
+    assert_eq!(ret, expected);
+}
+
 /// Tests get_valid_settlements(): ignore broken lines.
 #[test]
 fn test_get_valid_settlements_error() {
@@ -772,3 +950,217 @@ fn test_numbered_street_debug() {
 
     assert_eq!(ret.starts_with("NumberedStreet"), true);
 }
+
+/// Tests fold_diacritics().
+#[test]
+fn test_fold_diacritics() {
+    assert_eq!(fold_diacritics("áéíóöőúüű"), "aeiooouuu");
+    assert_eq!(fold_diacritics("ÁÉÍÓÖŐÚÜŰ"), "AEIOOOUUU");
+    assert_eq!(fold_diacritics("Fő utca"), "Fo utca");
+}
+
+/// Tests casefold_eq().
+#[test]
+fn test_casefold_eq() {
+    assert_eq!(casefold_eq("Fő utca", "fo Utca"), true);
+    assert_eq!(casefold_eq("Őrház utca", "orhaz utca"), true);
+    assert_eq!(casefold_eq("Szőlő utca", "SZŰLŐ UTCA"), false);
+}
+
+/// Tests make_csv_reader(): a leading UTF-8 BOM doesn't end up in the first header.
+#[test]
+fn test_make_csv_reader_bom() {
+    let mut cursor = std::io::Cursor::new(b"\xEF\xBB\xBFCITY\tCNT\n\xC3\x9Ajbuda\t42\n".to_vec());
+    let mut csv_reader = make_csv_reader(&mut cursor);
+    let rows: Vec<CityCount> = csv_reader.deserialize().map(|i| i.unwrap()).collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].city, "Újbuda");
+    assert_eq!(rows[0].count, 42);
+}
+
+/// Tests read_text_lenient().
+#[test]
+fn test_read_text_lenient() {
+    // Strict UTF-8 is preferred: this would be "TĹ±zkĹ‘ utca" in Windows-1250.
+    assert_eq!(
+        read_text_lenient("Tűzkő utca".as_bytes()).unwrap(),
+        "Tűzkő utca"
+    );
+    // Windows-1250 fallback.
+    assert_eq!(
+        read_text_lenient(b"T\xfbzk\xf5 utca, \xc1rp\xe1d \xfat").unwrap(),
+        "Tűzkő utca, Árpád út"
+    );
+    // 0x81 is undefined in Windows-1250.
+    let err = read_text_lenient(b"Fo utca\x81").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "byte 0x81 at offset 7 is neither UTF-8 nor Windows-1250"
+    );
+}
+
+/// Tests read_text_lenient(): mixed encodings are an error, not mojibake.
+#[test]
+fn test_read_text_lenient_mixed() {
+    // "Tűzkő utca\n" is 13 bytes, then the 2nd byte of the next line is invalid.
+    let err = read_text_lenient(b"T\xc5\xb1zk\xc5\x91 utca\nT\xfbzk\xf5 utca\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid UTF-8 at offset 14 after UTF-8 content"
+    );
+
+    let err = read_text_lenient(b"T\xfbzk\xf5 utca\nT\xc5\xb1zk\xc5\x91 utca\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "UTF-8 line at offset 11 after Windows-1250 content"
+    );
+
+    // ASCII lines are fine with both.
+    assert_eq!(
+        read_text_lenient(b"Fo utca\nT\xfbzk\xf5 utca\n").unwrap(),
+        "Fo utca\nTűzkő utca\n"
+    );
+}
+
+/// Tests make_reference_reader(): the content is decoded while streaming it.
+#[test]
+fn test_make_reference_reader() {
+    let mut input: &[u8] = b"a\tb\nT\xfbzk\xf5\tutca\n";
+    let mut read = make_reference_reader("ref.tsv", &mut input).unwrap();
+    let mut buf = [0_u8; 3];
+    let mut actual: Vec<u8> = Vec::new();
+    loop {
+        let len = read.read(&mut buf).unwrap();
+        if len == 0 {
+            break;
+        }
+        actual.extend(&buf[..len]);
+    }
+
+    assert_eq!(String::from_utf8(actual).unwrap(), "a\tb\nTűzkő\tutca\n");
+}
+
+/// Tests build_street_reference_index(): a Windows-1250 reference is transcoded to UTF-8.
+#[test]
+fn test_build_street_reference_index_windows_1250() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    {
+        let conn = ctx.get_database_connection().unwrap();
+        conn.execute("delete from ref_streets", []).unwrap();
+    }
+    let ref_streets = context::tests::TestFileSystem::make_file();
+    ref_streets
+        .borrow_mut()
+        .write_all(b"COUNTY_CODE\tSETTLEMENT_CODE\tSTREET\n01\t011\tT\xfbzk\xf5 utca\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/refs/utcak_20190514.tsv", &ref_streets)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let refpath = ctx.get_abspath("workdir/refs/utcak_20190514.tsv");
+    build_street_reference_index(&ctx, &refpath).unwrap();
+
+    let conn = ctx.get_database_connection().unwrap();
+    let mut stmt = conn.prepare("select street from ref_streets").unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut streets: Vec<String> = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        streets.push(row.get(0).unwrap());
+    }
+    assert_eq!(streets, ["Tűzkő utca"]);
+}
+
+/// Tests for_each_reference_housenumber(): a gzipped reference gives the same rows as the plain one.
+#[test]
+fn test_for_each_reference_housenumber_gz() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let plain = std::fs::read(ctx.get_abspath("workdir/refs/hazszamok_20190511.tsv")).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&plain).unwrap();
+    let gz_value = context::tests::TestFileSystem::make_file();
+    gz_value
+        .borrow_mut()
+        .write_all(&encoder.finish().unwrap())
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("workdir/refs/hazszamok_20190511.tsv.gz", &gz_value)],
+    );
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+    let read_rows = |path: &str| -> Vec<String> {
+        let mut rows: Vec<String> = Vec::new();
+        for_each_reference_housenumber(&ctx, &ctx.get_abspath(path), &mut |row| {
+            rows.push(format!(
+                "{}\t{}\t{}\t{}",
+                row.county, row.settlement, row.street, row.housenumber
+            ));
+            Ok(())
+        })
+        .unwrap();
+        rows
+    };
+
+    let expected = read_rows("workdir/refs/hazszamok_20190511.tsv");
+    let actual = read_rows("workdir/refs/hazszamok_20190511.tsv.gz");
+
+    assert_eq!(expected.is_empty(), false);
+    assert_eq!(actual, expected);
+}
+
+/// Tests is_parseable_house_number().
+#[test]
+fn test_is_parseable_house_number() {
+    assert_eq!(is_parseable_house_number("42"), true);
+    assert_eq!(is_parseable_house_number("42/A"), true);
+    assert_eq!(is_parseable_house_number("2-6\tcomment"), true);
+    assert_eq!(is_parseable_house_number("1;3;5"), true);
+    assert_eq!(is_parseable_house_number("hrsz"), false);
+    assert_eq!(is_parseable_house_number("2-x"), false);
+    assert_eq!(is_parseable_house_number(""), false);
+}
+
+/// Tests is_safe_relation_name().
+#[test]
+fn test_is_safe_relation_name() {
+    assert_eq!(is_safe_relation_name("budapest_11"), true);
+    assert_eq!(is_safe_relation_name("gh-195"), true);
+    assert_eq!(is_safe_relation_name("../secret"), false);
+    assert_eq!(is_safe_relation_name("a/b"), false);
+    assert_eq!(is_safe_relation_name(".."), false);
+    assert_eq!(is_safe_relation_name(""), false);
+}
+
+/// Tests for_each_line(): the streaming path sees the same lines as reading the whole file.
+#[test]
+fn test_for_each_line() {
+    let ctx = context::tests::make_test_context().unwrap();
+    let path = "src/fixtures/file-system/access_log";
+    let expected: Vec<String> = std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|i| i.to_string())
+        .collect();
+
+    let mut actual: Vec<String> = Vec::new();
+    for_each_line(&ctx, path, |line| {
+        actual.push(line.to_string());
+        Ok(())
+    })
+    .unwrap();
+
+    assert_eq!(actual.is_empty(), false);
+    assert_eq!(actual, expected);
+}
+
+/// Tests get_stable_hash().
+#[test]
+fn test_get_stable_hash() {
+    assert_eq!(get_stable_hash(b""), "cbf29ce484222325");
+    assert_eq!(get_stable_hash(b"a"), "af63dc4c8601ec8c");
+}
@@ -12,8 +12,11 @@
 
 use crate::areas;
 use crate::context;
+use crate::util;
 use anyhow::Context;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::io::Write;
 
 /// Validates a range description: check for missing keys."""
@@ -63,6 +66,45 @@ fn validate_range(
     Ok(())
 }
 
+/// Validates that ranges of a range list don't overlap, unless they have different
+/// refsettlement values.
+fn validate_ranges_overlap(
+    errors: &mut Vec<String>,
+    parent: &str,
+    ranges: &[areas::RelationRangesDict],
+    filter_data: &areas::RelationFiltersDict,
+) {
+    let interpolation_all = filter_data.interpolation == Some("all".to_string());
+    let parsed: Vec<_> = ranges
+        .iter()
+        .filter_map(|range_data| {
+            let start: i64 = range_data.start.parse().ok()?;
+            let end: i64 = range_data.end.parse().ok()?;
+            Some((start, end, &range_data.refsettlement))
+        })
+        .collect();
+    for (index, (start, end, refsettlement)) in parsed.iter().enumerate() {
+        for (other_start, other_end, other_refsettlement) in &parsed[index + 1..] {
+            if refsettlement != other_refsettlement {
+                continue;
+            }
+
+            if start > other_end || other_start > end {
+                continue;
+            }
+
+            // Without interpolation=all, an even and an odd range have no common numbers.
+            if !interpolation_all && start % 2 != other_start % 2 {
+                continue;
+            }
+
+            errors.push(format!(
+                "overlapping ranges [{start}-{end}] and [{other_start}-{other_end}] for '{parent}'"
+            ));
+        }
+    }
+}
+
 /// Validates a range list.
 fn validate_ranges(
     errors: &mut Vec<String>,
@@ -78,6 +120,7 @@ fn validate_ranges(
             filter_data,
         )?;
     }
+    validate_ranges_overlap(errors, parent, ranges, filter_data);
 
     Ok(())
 }
@@ -88,14 +131,17 @@ fn validate_filter_invalid_valid(
     parent: &str,
     invalid: &[String],
 ) -> anyhow::Result<()> {
+    let number = regex::Regex::new(r"^[0-9]+$")?;
+    let number_letter = regex::Regex::new(r"^[0-9]+[a-z]$")?;
+    let number_slash = regex::Regex::new(r"^[0-9]+/[0-9]$")?;
     for (index, invalid_data) in invalid.iter().enumerate() {
-        if regex::Regex::new(r"^[0-9]+$")?.is_match(invalid_data) {
+        if number.is_match(invalid_data) {
             continue;
         }
-        if regex::Regex::new(r"^[0-9]+[a-z]$")?.is_match(invalid_data) {
+        if number_letter.is_match(invalid_data) {
             continue;
         }
-        if regex::Regex::new(r"^[0-9]+/[0-9]$")?.is_match(invalid_data) {
+        if number_slash.is_match(invalid_data) {
             continue;
         }
         errors.push(format!(
@@ -132,6 +178,7 @@ fn validate_filters(
     errors: &mut Vec<String>,
     parent: &str,
     filters: &HashMap<String, areas::RelationFiltersDict>,
+    interpolation: &Option<String>,
 ) -> anyhow::Result<()> {
     let context = format!("{parent}.");
     for (key, value) in filters {
@@ -141,7 +188,12 @@ fn validate_filters(
             ));
         }
 
-        validate_filter(errors, &format!("{context}{key}"), value)?;
+        // Ranges inherit the relation-level interpolation, unless they have their own.
+        let mut value = value.clone();
+        if value.interpolation.is_none() {
+            value.interpolation = interpolation.clone();
+        }
+        validate_filter(errors, &format!("{context}{key}"), &value)?;
     }
 
     Ok(())
@@ -168,10 +220,7 @@ fn validate_refstreets(
             errors.push(format!("expected value != key for '{context}{key}'"));
         }
     }
-    let mut reverse: Vec<_> = refstreets
-        .iter()
-        .map(|(_key, value)| value.as_str())
-        .collect();
+    let mut reverse: Vec<_> = refstreets.values().map(|value| value.as_str()).collect();
     reverse.sort_unstable();
     reverse.dedup();
     if refstreets.keys().len() != reverse.len() {
@@ -212,8 +261,14 @@ fn validate_relation(
 
         // Just to be consistent, we require these keys in relations.yaml for now, even if code would
         // handle having them there or in relation-foo.yaml as well.
-        if relation.osmrelation.is_none() {
-            errors.push(format!("missing key '{context}osmrelation'"));
+        match relation.osmrelation {
+            None => errors.push(format!("missing key '{context}osmrelation'")),
+            Some(areas::OsmRelation::Multiple(ref ids)) if ids.is_empty() => {
+                errors.push(format!(
+                    "expected non-empty list for '{context}osmrelation'"
+                ));
+            }
+            _ => (),
         }
         if relation.refcounty.is_none() {
             errors.push(format!("missing key '{context}refcounty'"));
@@ -223,8 +278,20 @@ fn validate_relation(
         }
     }
 
+    if let Some(ref interpolation) = relation.interpolation {
+        if interpolation != "all" {
+            errors.push(format!(
+                "expected value for '{context}interpolation' is 'all'"
+            ));
+        }
+    }
     if let Some(ref filters) = relation.filters {
-        validate_filters(errors, &format!("{}{}", context, "filters"), filters)?;
+        validate_filters(
+            errors,
+            &format!("{}{}", context, "filters"),
+            filters,
+            &relation.interpolation,
+        )?;
     }
     if let Some(ref refstreets) = relation.refstreets {
         validate_refstreets(errors, &format!("{}{}", context, "refstreets"), refstreets)?;
@@ -236,11 +303,80 @@ fn validate_relation(
             street_filters,
         )?;
     }
+    if let Some(ref reference_housenumbers) = relation.reference_housenumbers {
+        match reference_housenumbers {
+            serde_yaml::Value::String(_) => (),
+            serde_yaml::Value::Sequence(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    if !value.is_string() {
+                        errors.push(format!(
+                            "expected value type for '{context}reference-housenumbers[{index}]' is str"
+                        ));
+                    }
+                }
+            }
+            _ => {
+                errors.push(format!(
+                    "expected value type for '{context}reference-housenumbers' is str or list"
+                ));
+            }
+        }
+    }
+    if let Some(ref description) = relation.description {
+        if description.parse::<i64>().is_ok() {
+            errors.push(format!(
+                "expected value type for '{context}description' is str"
+            ));
+        }
+    }
+    if let Some(ref display_name) = relation.display_name {
+        if display_name.parse::<i64>().is_ok() {
+            errors.push(format!(
+                "expected value type for '{context}display-name' is str"
+            ));
+        }
+    }
     if let Some(ref source) = relation.source {
         if source.parse::<i64>().is_ok() {
             errors.push(format!("expected value type for '{context}source' is str"));
         }
     }
+    if let Some(ref excluded_users) = relation.excluded_users {
+        for (index, user) in excluded_users.iter().enumerate() {
+            if user.is_empty() {
+                errors.push(format!(
+                    "expected non-empty value for '{context}excluded-users[{index}]'"
+                ));
+            }
+        }
+    }
+    if let Some(ref street_match) = relation.street_match {
+        if street_match != "exact" && street_match != "casefold" {
+            errors.push(format!(
+                "expected value for '{context}street-match' is 'exact' or 'casefold'"
+            ));
+        }
+    }
+    for (key, value) in [
+        ("osm-streets-query", &relation.osm_streets_query),
+        ("osm-housenumbers-query", &relation.osm_housenumbers_query),
+    ] {
+        if let Some(ref template) = value {
+            if !template.ends_with(".overpassql") || template.contains('/') {
+                errors.push(format!(
+                    "expected value for '{context}{key}' is a .overpassql file name in data/"
+                ));
+            }
+        }
+    }
+    if let Some(ref osm_snapshot) = relation.osm_snapshot {
+        let format = time::format_description::parse("[year]-[month]-[day]")?;
+        if time::Date::parse(osm_snapshot, &format).is_err() {
+            errors.push(format!(
+                "expected value for '{context}osm-snapshot' is a YYYY-MM-DD date"
+            ));
+        }
+    }
     if let Some(ref aliases) = relation.alias {
         for (index, alias) in aliases.iter().enumerate() {
             if alias.parse::<i64>().is_ok() {
@@ -254,6 +390,68 @@ fn validate_relation(
     Ok(())
 }
 
+/// Validates that the refcounty of a relation is a known one, to catch typos like '0l'.
+fn validate_refcounty(
+    errors: &mut Vec<String>,
+    parent: &str,
+    relation: &areas::RelationDict,
+    refcounties: &HashSet<String>,
+) {
+    let mut context: String = "".into();
+    if !parent.is_empty() {
+        context = format!("{parent}.");
+    }
+    if let Some(ref refcounty) = relation.refcounty {
+        if !refcounties.contains(refcounty) {
+            errors.push(format!(
+                "unknown refcounty '{refcounty}' for '{context}refcounty'"
+            ));
+        }
+    }
+}
+
+/// Loads the known refcounty codes from the refcounty-names.yaml reference data.
+fn get_refcounties(ctx: &context::Context, datadir: &str) -> anyhow::Result<HashSet<String>> {
+    let path = format!("{datadir}/refcounty-names.yaml");
+    let data = areas::read_yaml_with_includes(ctx, datadir, &path)?;
+    let names: HashMap<String, String> =
+        serde_yaml::from_str(&data).context(format!("failed to parse {path}"))?;
+    Ok(names.into_keys().collect())
+}
+
+/// Counts what was checked, so a successful run can show that the sections were not skipped.
+#[derive(Default)]
+struct ValidationSummary {
+    filters: usize,
+    refstreets: usize,
+    street_filters: usize,
+}
+
+impl ValidationSummary {
+    /// Adds the counts of a single relation.
+    fn add(&mut self, relation: &areas::RelationDict) {
+        if let Some(ref filters) = relation.filters {
+            self.filters += filters.len();
+        }
+        if let Some(ref refstreets) = relation.refstreets {
+            self.refstreets += refstreets.len();
+        }
+        if let Some(ref street_filters) = relation.street_filters {
+            self.street_filters += street_filters.len();
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "validated {} filters, {} refstreets mappings, {} street-filters",
+            self.filters, self.refstreets, self.street_filters
+        )
+    }
+}
+
 /// Validates a relation list.
 fn validate_relations(
     errors: &mut Vec<String>,
@@ -262,10 +460,85 @@ fn validate_relations(
     for (key, value) in relations {
         validate_relation(errors, key, value)?;
     }
+    validate_duplicate_osmrelations(errors, relations);
+
+    Ok(())
+}
+
+/// Validates that no two relations point to the same OSM relation, which would produce confusing
+/// duplicate results.
+fn validate_duplicate_osmrelations(errors: &mut Vec<String>, relations: &areas::RelationsDict) {
+    let mut names: Vec<&String> = relations.keys().collect();
+    names.sort();
+    let mut users: BTreeMap<u64, Vec<&str>> = BTreeMap::new();
+    for name in names {
+        if let Some(ref osmrelation) = relations[name].osmrelation {
+            for id in osmrelation.get_ids() {
+                let users = users.entry(id).or_default();
+                if users.last() != Some(&name.as_str()) {
+                    users.push(name);
+                }
+            }
+        }
+    }
+    for (id, names) in users {
+        if names.len() > 1 {
+            errors.push(format!(
+                "duplicate osmrelation '{id}' in '{}'",
+                names.join("', '")
+            ));
+        }
+    }
+}
+
+/// Validates that the top-level keys of relations.yaml are sorted, reports the first key which is
+/// out of order.
+fn validate_sorted(errors: &mut Vec<String>, data: &str) -> anyhow::Result<()> {
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(data).context("serde_yaml::from_str() failed")?;
+    let mapping = match value.as_mapping() {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+    let keys: Vec<&str> = mapping.keys().filter_map(|i| i.as_str()).collect();
+    for pair in keys.windows(2) {
+        if util::get_sort_key(pair[1]) < util::get_sort_key(pair[0]) {
+            errors.push(format!(
+                "expected sorted keys, '{}' should come before '{}'",
+                pair[1], pair[0]
+            ));
+            break;
+        }
+    }
 
     Ok(())
 }
 
+/// Gets the category of an error message: its leading phrase, e.g. 'missing key'.
+fn get_error_category(error: &str) -> String {
+    let end = error.find(['\'', '[']).unwrap_or(error.len());
+    let category = error[..end].trim();
+    category
+        .strip_suffix(" for")
+        .unwrap_or(category)
+        .to_string()
+}
+
+/// Summarizes errors by category, e.g. "missing key: 4, expected value type for: 2".
+fn get_error_histogram(errors: &[String]) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for error in errors {
+        *counts.entry(get_error_category(error)).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+        .iter()
+        .map(|(category, count)| format!("{category}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Similar to plain main(), but with an interface that allows testing.
 pub fn main(argv: &[String], stream: &mut dyn Write, ctx: &context::Context) -> i32 {
     match our_main(argv, stream, ctx) {
@@ -285,9 +558,48 @@ pub fn our_main(
     stream: &mut dyn Write,
     ctx: &context::Context,
 ) -> anyhow::Result<()> {
-    let yaml_path = &argv[1];
-    let data = ctx.get_file_system().read_to_string(yaml_path)?;
+    let path = clap::Arg::new("path")
+        .required(true)
+        .help("yaml file to validate");
+    let verbose = clap::Arg::new("verbose")
+        .long("verbose")
+        .action(clap::ArgAction::SetTrue)
+        .help("print a summary of what was validated");
+    let strict = clap::Arg::new("strict")
+        .long("strict")
+        .action(clap::ArgAction::SetTrue)
+        .help("also check values against the reference data, e.g. refcounty codes");
+    let check_sorted = clap::Arg::new("check-sorted")
+        .long("check-sorted")
+        .action(clap::ArgAction::SetTrue)
+        .help("also check that the keys of relations.yaml are sorted");
+    let summary_arg = clap::Arg::new("summary")
+        .long("summary")
+        .action(clap::ArgAction::SetTrue)
+        .help("print the number of errors per category before the errors");
+    let args = [path, verbose, strict, check_sorted, summary_arg];
+    let app = clap::Command::new("osm-gimmisn").override_usage(
+        "osm-gimmisn validator [--verbose] [--strict] [--check-sorted] [--summary] data/relation-foo.yaml",
+    );
+    let args = app.args(&args).try_get_matches_from(argv)?;
+    let yaml_path: &String = args.get_one("path").unwrap();
+    let verbose = *args.get_one::<bool>("verbose").unwrap();
+    let strict = *args.get_one::<bool>("strict").unwrap();
+    let check_sorted = *args.get_one::<bool>("check-sorted").unwrap();
+    let print_summary = *args.get_one::<bool>("summary").unwrap();
+    let datadir = std::path::Path::new(yaml_path)
+        .parent()
+        .map(|i| i.to_string_lossy().to_string())
+        .filter(|i| !i.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    let data = areas::read_yaml_with_includes(ctx, &datadir, yaml_path)?;
     let mut errors: Vec<String> = Vec::new();
+    let mut summary = ValidationSummary::default();
+    let refcounties = if strict {
+        Some(get_refcounties(ctx, &datadir)?)
+    } else {
+        None
+    };
 
     if data.contains('\t') {
         // serde can parse this, but not some of the 3rd-party parsers.
@@ -298,6 +610,15 @@ pub fn our_main(
         let relations_dict: areas::RelationsDict =
             serde_yaml::from_str(&data).context("serde_yaml::from_str() failed")?;
         validate_relations(&mut errors, &relations_dict)?;
+        if check_sorted {
+            validate_sorted(&mut errors, &data)?;
+        }
+        for (key, relation) in &relations_dict {
+            if let Some(ref refcounties) = refcounties {
+                validate_refcounty(&mut errors, key, relation, refcounties);
+            }
+            summary.add(relation);
+        }
     } else {
         // This will fail if the data is not well-formed (e.g. in case of duplicated keys):
         serde_yaml::from_str::<serde_yaml::Value>(&data)
@@ -308,14 +629,25 @@ pub fn our_main(
             serde_yaml::from_str(&data).context(format!("failed to validate {yaml_path}"))?;
         let parent = "";
         validate_relation(&mut errors, parent, &relation_dict)?;
+        if let Some(ref refcounties) = refcounties {
+            validate_refcounty(&mut errors, parent, &relation_dict, refcounties);
+        }
+        summary.add(&relation_dict);
     }
     if !errors.is_empty() {
+        if print_summary {
+            stream.write_all(format!("{}\n", get_error_histogram(&errors)).as_bytes())?;
+        }
         for error in errors {
             stream.write_all(format!("{error}\n").as_bytes())?;
         }
         return Err(anyhow::anyhow!("failed to validate {}", yaml_path));
     }
 
+    if verbose {
+        stream.write_all(format!("{summary}\n").as_bytes())?;
+    }
+
     Ok(())
 }
 
@@ -35,6 +35,218 @@ fn test_relations() {
     assert_eq!(ret, 0);
 }
 
+/// Tests main(): a missing path prints the usage instead of panicking.
+#[test]
+fn test_main_no_path() {
+    let ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into()];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 1);
+    let output = String::from_utf8(buf.into_inner()).unwrap();
+    assert!(output.contains("Usage: osm-gimmisn validator"));
+}
+
+/// Tests main(): --verbose prints what was validated.
+#[test]
+fn test_relation_verbose() {
+    let content = r#"filters:
+  'Budaörsi út':
+    ranges:
+      - {start: '1', end: '9'}
+  'Csiki-hegyek utca':
+    invalid: ['7']
+refstreets:
+  'OSM Name 1': 'Ref Name 1'
+street-filters:
+  - 'Only In OSM utca'
+  - 'Second Only In OSM utca'
+"#;
+    let path = "data/relation-myrelation.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), "--verbose".into(), ctx.get_abspath(path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let file = context::tests::TestFileSystem::make_file();
+    file.borrow_mut().write_all(content.as_bytes()).unwrap();
+    let files = context::tests::TestFileSystem::make_files(&ctx, &[(path, &file)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 0);
+    assert_eq!(
+        String::from_utf8(buf.into_inner()).unwrap(),
+        "validated 2 filters, 1 refstreets mappings, 2 street-filters\n"
+    );
+}
+
+/// Tests main(): a relation including a shared filters fragment is validated like an inline one.
+#[test]
+fn test_relation_include() {
+    let content = "filters: !include filters-shared.yaml\n";
+    let shared_content = r#"'Budaörsi út':
+  invalid: ['7']
+'Csiki-hegyek utca':
+  invalid: ['hello']
+"#;
+    let path = "data/relation-myrelation.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), ctx.get_abspath(path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let file = context::tests::TestFileSystem::make_file();
+    file.borrow_mut().write_all(content.as_bytes()).unwrap();
+    let shared = context::tests::TestFileSystem::make_file();
+    shared
+        .borrow_mut()
+        .write_all(shared_content.as_bytes())
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[(path, &file), ("data/filters-shared.yaml", &shared)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 1);
+    let expected = format!(
+        "expected format for 'filters.Csiki-hegyek utca.invalid[0]' is '42', '42a' or '42/1'\nfailed to validate {}\n",
+        ctx.get_abspath(path)
+    );
+    assert_eq!(String::from_utf8(buf.into_inner()).unwrap(), expected);
+}
+
+/// Runs main() with --strict on relations.yaml with the given content.
+fn run_strict_relations(content: &str) -> (i32, String, context::Context) {
+    let path = "data/relations.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), "--strict".into(), ctx.get_abspath(path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let file = context::tests::TestFileSystem::make_file();
+    file.borrow_mut().write_all(content.as_bytes()).unwrap();
+    let refcounty_names = context::tests::TestFileSystem::make_file();
+    refcounty_names
+        .borrow_mut()
+        .write_all(b"'01': 'Budapest'\n'02': 'Baranya'\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[
+            (path, &file),
+            ("data/refcounty-names.yaml", &refcounty_names),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    (ret, String::from_utf8(buf.into_inner()).unwrap(), ctx)
+}
+
+/// Tests main(): --strict accepts a known refcounty.
+#[test]
+fn test_relations_strict() {
+    let content = r#"gazdagret:
+    osmrelation: 2713748
+    refcounty: "01"
+    refsettlement: "011"
+"#;
+
+    let (ret, output, _ctx) = run_strict_relations(content);
+
+    assert_eq!(ret, 0);
+    assert_eq!(output, "");
+}
+
+/// Tests main(): --strict rejects an unknown refcounty.
+#[test]
+fn test_relations_strict_unknown_refcounty() {
+    let content = r#"gazdagret:
+    osmrelation: 2713748
+    refcounty: "0l"
+    refsettlement: "011"
+"#;
+
+    let (ret, output, ctx) = run_strict_relations(content);
+
+    assert_eq!(ret, 1);
+    let expected = format!(
+        "unknown refcounty '0l' for 'gazdagret.refcounty'\nfailed to validate {}\n",
+        ctx.get_abspath("data/relations.yaml")
+    );
+    assert_eq!(output, expected);
+}
+
+/// Tests main(): --check-sorted reports the first out-of-order key.
+#[test]
+fn test_relations_check_sorted() {
+    let content = r#"budafok:
+    osmrelation: 42
+    refcounty: "01"
+    refsettlement: "011"
+gazdagret:
+    osmrelation: 2713748
+    refcounty: "01"
+    refsettlement: "011"
+albertfalva:
+    osmrelation: 43
+    refcounty: "01"
+    refsettlement: "011"
+"#;
+    let path = "data/relations.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), "--check-sorted".into(), ctx.get_abspath(path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let file = context::tests::TestFileSystem::make_file();
+    file.borrow_mut().write_all(content.as_bytes()).unwrap();
+    let files = context::tests::TestFileSystem::make_files(&ctx, &[(path, &file)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 1);
+    let expected = format!(
+        "expected sorted keys, 'albertfalva' should come before 'gazdagret'\nfailed to validate {}\n",
+        ctx.get_abspath(path)
+    );
+    assert_eq!(String::from_utf8(buf.into_inner()).unwrap(), expected);
+}
+
+/// Tests main(): --summary prints the number of errors per category first.
+#[test]
+fn test_relations_summary() {
+    let content = r#"budafok:
+    osmrelation: 42
+gazdagret:
+    refcounty: "01"
+    refsettlement: "011"
+    source: 42
+"#;
+    let path = "data/relations.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), "--summary".into(), ctx.get_abspath(path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let file = context::tests::TestFileSystem::make_file();
+    file.borrow_mut().write_all(content.as_bytes()).unwrap();
+    let files = context::tests::TestFileSystem::make_files(&ctx, &[(path, &file)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 1);
+    let output = String::from_utf8(buf.into_inner()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "missing key: 3, expected value type: 1");
+    assert_eq!(lines.len(), 6);
+}
+
 /// Tests the missing-osmrelation relations path.
 #[test]
 fn test_relations_missing_osmrelation() {
@@ -69,6 +281,87 @@ fn test_relations_missing_osmrelation() {
     assert_eq!(String::from_utf8(buf.into_inner()).unwrap(), expected);
 }
 
+/// Tests the relations path: osmrelation can be a list.
+#[test]
+fn test_relations_osmrelation_list() {
+    // Set up arguments.
+    let relations_yaml_path = "data/relations.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), ctx.get_abspath(relations_yaml_path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let relations_yaml = context::tests::TestFileSystem::make_file();
+    relations_yaml
+        .borrow_mut()
+        .write_all(
+            br#"budafok:
+    osmrelation: []
+    refcounty: "01"
+    refsettlement: "011"
+gazdagret:
+    osmrelation: [42, 43]
+    refcounty: "01"
+    refsettlement: "012"
+"#,
+        )
+        .unwrap();
+    let files =
+        context::tests::TestFileSystem::make_files(&ctx, &[(relations_yaml_path, &relations_yaml)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    // The list is fine for gazdagret, but an empty list is not.
+    assert_eq!(ret, 1);
+    let expected = format!(
+        "expected non-empty list for 'budafok.osmrelation'\nfailed to validate {}\n",
+        ctx.get_abspath(relations_yaml_path)
+    );
+    assert_eq!(String::from_utf8(buf.into_inner()).unwrap(), expected);
+}
+
+/// Tests the relations path: two relations share an osmrelation.
+#[test]
+fn test_relations_duplicate_osmrelation() {
+    // Set up arguments.
+    let relations_yaml_path = "data/relations.yaml";
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let argv: &[String] = &["".into(), ctx.get_abspath(relations_yaml_path)];
+    let mut buf: std::io::Cursor<Vec<u8>> = std::io::Cursor::new(Vec::new());
+    let relations_yaml = context::tests::TestFileSystem::make_file();
+    relations_yaml
+        .borrow_mut()
+        .write_all(
+            br#"budafok:
+    osmrelation: 42
+    refcounty: "01"
+    refsettlement: "011"
+gazdagret:
+    osmrelation: [43, 42]
+    refcounty: "01"
+    refsettlement: "012"
+ujbuda:
+    osmrelation: 44
+    refcounty: "01"
+    refsettlement: "013"
+"#,
+        )
+        .unwrap();
+    let files =
+        context::tests::TestFileSystem::make_files(&ctx, &[(relations_yaml_path, &relations_yaml)]);
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+
+    let ret = main(argv, &mut buf, &ctx);
+
+    assert_eq!(ret, 1);
+    let expected = format!(
+        "duplicate osmrelation '42' in 'budafok', 'gazdagret'\nfailed to validate {}\n",
+        ctx.get_abspath(relations_yaml_path)
+    );
+    assert_eq!(String::from_utf8(buf.into_inner()).unwrap(), expected);
+}
+
 /// Tests the missing-refcounty relations path.
 #[test]
 fn test_relations_missing_refcounty() {
@@ -223,6 +516,89 @@ fn test_validate_filter_invalid_valid2() {
     assert_success(content);
 }
 
+/// Tests the relation path: reference-housenumbers can be a string or a list of strings.
+#[test]
+fn test_relation_reference_housenumbers() {
+    assert_success("reference-housenumbers: 'refs/hazszamok_detailed.tsv'\n");
+    assert_success("reference-housenumbers: ['refs/a.tsv', 'refs/b.tsv']\n");
+}
+
+/// Tests the relation path: bad reference-housenumbers type.
+#[test]
+fn test_relation_reference_housenumbers_bad_type() {
+    let content = "reference-housenumbers: 42\n";
+    let expected =
+        "expected value type for 'reference-housenumbers' is str or list\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+
+    let content = "reference-housenumbers: ['refs/a.tsv', 42]\n";
+    let expected =
+        "expected value type for 'reference-housenumbers[1]' is str\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: excluded-users.
+#[test]
+fn test_relation_excluded_users() {
+    assert_success("excluded-users: ['baduser']\n");
+
+    let content = "excluded-users: ['baduser', '']\n";
+    let expected = "expected non-empty value for 'excluded-users[1]'\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: street-match.
+#[test]
+fn test_relation_street_match() {
+    assert_success("street-match: casefold\n");
+
+    let content = "street-match: ignorecase\n";
+    let expected =
+        "expected value for 'street-match' is 'exact' or 'casefold'\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: bad osm-snapshot value.
+#[test]
+fn test_relation_osm_snapshot() {
+    assert_success("osm-snapshot: 2020-01-01\n");
+
+    let content = "osm-snapshot: 2020-13-01\n";
+    let expected =
+        "expected value for 'osm-snapshot' is a YYYY-MM-DD date\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: bad query template names.
+#[test]
+fn test_relation_osm_query_templates() {
+    assert_success("osm-streets-query: streets-template-gazdagret.overpassql\n");
+
+    let content = "osm-housenumbers-query: ../wsgi.ini\n";
+    let expected = "expected value for 'osm-housenumbers-query' is a .overpassql file name in data/\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: bad description type.
+#[test]
+fn test_relation_description_bad_type() {
+    assert_success("description: 'waiting on municipality data'\n");
+
+    let content = "description: 42\n";
+    let expected = "expected value type for 'description' is str\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: bad display-name type.
+#[test]
+fn test_relation_display_name_bad_type() {
+    assert_success("display-name: 'Újbuda (XI. kerület)'\n");
+
+    let content = "display-name: 42\n";
+    let expected = "expected value type for 'display-name' is str\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
 /// Tests the relation path: bad source type.
 #[test]
 fn test_relation_source_bad_type() {
@@ -369,6 +745,55 @@ fn test_relation_filters_ranges_start_end_even_odd() {
     assert_failure_msg(content, expected);
 }
 
+/// Tests the relation path: ranges inherit the relation-level interpolation.
+#[test]
+fn test_relation_interpolation_default() {
+    let content = r#"interpolation: all
+filters:
+  'Budaörsi út':
+    ranges:
+      - {start: '42', end: '143'}
+"#;
+    assert_success(content);
+}
+
+/// Tests the relation path: bad relation-level interpolation.
+#[test]
+fn test_relation_interpolation_bad() {
+    let content = r#"interpolation: some
+"#;
+    let expected = "expected value for 'interpolation' is 'all'\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: bad filters -> ... -> ranges -> overlapping ranges.
+#[test]
+fn test_relation_filters_ranges_overlap() {
+    let content = r#"filters:
+  'Budaörsi út':
+    ranges:
+      - {start: '1', end: '11'}
+      - {start: '5', end: '21'}
+"#;
+    let expected = "overlapping ranges [1-11] and [5-21] for 'filters.Budaörsi út.ranges'\nfailed to validate {0}\n";
+    assert_failure_msg(content, expected);
+}
+
+/// Tests the relation path: filters -> ... -> ranges -> non-overlapping ranges.
+#[test]
+fn test_relation_filters_ranges_no_overlap() {
+    // Odd and even ranges or ranges of different settlements don't overlap.
+    let content = r#"filters:
+  'Budaörsi út':
+    ranges:
+      - {start: '1', end: '11'}
+      - {start: '13', end: '21'}
+      - {start: '2', end: '20'}
+      - {start: '1', end: '11', refsettlement: '012'}
+"#;
+    assert_success(content);
+}
+
 /// Tests the relation path: bad filters -> ... -> ranges -> start type.
 #[test]
 fn test_relation_filters_ranges_bad_start() {
@@ -425,6 +850,18 @@ Caused by:
     assert_failure_msg(content, expected);
 }
 
+/// Tests the done key: bad type.
+#[test]
+fn test_relation_done_bad() {
+    let content = "done: 'yes'\n";
+    let expected = r#"failed to validate {0}
+
+Caused by:
+    done: invalid type: string "yes", expected a boolean at line 1 column 7
+"#;
+    assert_failure_msg(content, expected);
+}
+
 /// Tests the relation path: bad alias subkey.
 #[test]
 fn test_relation_alias_bad() {
@@ -11,9 +11,11 @@
 //! The webframe module provides the header, toolbar and footer code.
 
 use crate::areas;
+use crate::cache;
 use crate::context;
 use crate::cron;
 use crate::i18n::translate as tr;
+use crate::overpass_query;
 use crate::stats;
 use crate::util;
 use crate::yattag;
@@ -31,13 +33,10 @@ pub fn get_footer(last_updated: &str) -> yattag::Doc {
     {
         let doc = yattag::Doc::new();
         doc.text(&tr("Version: "));
-        doc.append_value(
-            util::git_link(
-                git_version::git_version!(args = ["--always", "--long"]),
-                "https://github.com/vmiklos/osm-gimmisn/commit/",
-            )
-            .get_value(),
-        );
+        doc.append_doc(&util::git_link(
+            git_version::git_version!(args = ["--always", "--long"]),
+            "https://github.com/vmiklos/osm-gimmisn/commit/",
+        ));
         items.push(doc);
         items.push(yattag::Doc::from_text(&tr(
             "OSM data © OpenStreetMap contributors.",
@@ -56,7 +55,7 @@ pub fn get_footer(last_updated: &str) -> yattag::Doc {
             if index > 0 {
                 div.text(" ¦ ");
             }
-            div.append_value(item.get_value());
+            div.append_doc(item);
         }
     }
     doc
@@ -326,17 +325,50 @@ pub fn get_toolbar(
     function: &str,
     relation_name: &str,
     relation_osmid: u64,
+) -> anyhow::Result<yattag::Doc> {
+    get_toolbar_with_js(
+        ctx,
+        relations,
+        function,
+        relation_name,
+        relation_osmid,
+        /*with_js=*/ true,
+    )
+}
+
+/// Same as get_toolbar(), but allows omitting the hidden localized strings which are only used by
+/// JS, e.g. when embedding the toolbar as a fragment.
+pub fn get_toolbar_with_js(
+    ctx: &context::Context,
+    relations: Option<&mut areas::Relations<'_>>,
+    function: &str,
+    relation_name: &str,
+    relation_osmid: u64,
+    with_js: bool,
 ) -> anyhow::Result<yattag::Doc> {
     let mut items: Vec<yattag::Doc> = Vec::new();
 
     let mut streets: String = "".into();
     let mut additional_housenumbers = false;
+    let mut description: Option<String> = None;
+    let mut display_name: Option<String> = None;
     if let Some(relations) = relations {
         if !relation_name.is_empty() {
             let relation = relations.get_relation(relation_name)?;
             streets = relation.get_config().should_check_missing_streets();
             additional_housenumbers = relation.get_config().should_check_additional_housenumbers();
+            description = relation.get_config().get_description();
+            display_name = relation.get_config().get_display_name();
+        }
+    }
+
+    if let Some(site_name) = ctx.get_ini().get_site_name() {
+        let doc = yattag::Doc::new();
+        {
+            let strong = doc.tag("strong", &[]);
+            strong.text(&site_name);
         }
+        items.push(doc);
     }
 
     let doc = yattag::Doc::new();
@@ -346,6 +378,15 @@ pub fn get_toolbar(
     }
     items.push(doc);
 
+    if let Some(display_name) = display_name {
+        let doc = yattag::Doc::new();
+        {
+            let span = doc.tag("span", &[("id", "relation-display-name")]);
+            span.text(&display_name);
+        }
+        items.push(doc);
+    }
+
     if !relation_name.is_empty() {
         items = fill_missing_header_items(
             ctx,
@@ -373,7 +414,9 @@ pub fn get_toolbar(
         ),
         ("str-toolbar-reference-error", tr("Error from reference: ")),
     ];
-    emit_l10n_strings_for_js(&doc, string_pairs);
+    if with_js {
+        emit_l10n_strings_for_js(&doc, string_pairs);
+    }
 
     {
         let a = doc.tag("a", &[("href", "https://overpass-turbo.eu/")]);
@@ -436,15 +479,43 @@ pub fn get_toolbar(
             if index > 0 {
                 div.text(" ¦ ");
             }
-            div.append_value(item.get_value());
+            div.append_doc(item);
         }
     }
+    if let Some(description) = description {
+        let p = doc.tag("p", &[("id", "relation-description")]);
+        p.text(&description);
+    }
     doc.stag("hr");
     Ok(doc)
 }
 
 pub type Headers = Vec<(Cow<'static, str>, Cow<'static, str>)>;
 
+/// Where handle_static() looks for a static file.
+#[derive(Clone, Copy)]
+enum StaticRoot {
+    /// Generated by the browser build, under target/browser/.
+    Browser,
+    /// Generated by cron, under workdir/stats/.
+    Stats,
+    /// Part of the source tree, at the top level.
+    Top,
+}
+
+/// Maps the extension of a static file to its content type and location.
+const STATIC_CONTENT_TYPES: &[(&str, &str, StaticRoot)] = &[
+    (
+        "js",
+        "application/x-javascript; charset=utf-8",
+        StaticRoot::Browser,
+    ),
+    ("css", "text/css; charset=utf-8", StaticRoot::Browser),
+    ("json", "application/json; charset=utf-8", StaticRoot::Stats),
+    ("ico", "image/x-icon", StaticRoot::Top),
+    ("svg", "image/svg+xml; charset=utf-8", StaticRoot::Top),
+];
+
 /// Handles serving static content.
 pub fn handle_static(
     ctx: &context::Context,
@@ -453,43 +524,131 @@ pub fn handle_static(
     let mut tokens = request_uri.split('/');
     let path = tokens.next_back().context("next_back() failed")?;
     let extra_headers = Vec::new();
-
-    if request_uri.ends_with(".js") {
-        let content_type = "application/x-javascript; charset=utf-8";
-        let (content, extra_headers) =
-            get_content_with_meta(ctx, &ctx.get_abspath(&format!("target/browser/{path}")))?;
-        return Ok((content, content_type.into(), extra_headers));
-    }
-    if request_uri.ends_with(".css") {
-        let content_type = "text/css; charset=utf-8";
-        let (content, extra_headers) =
-            get_content_with_meta(ctx, &ctx.get_abspath(&format!("target/browser/{path}")))
-                .context("get_content_with_meta() failed")?;
-        return Ok((content, content_type.into(), extra_headers));
-    }
-    if request_uri.ends_with(".json") {
-        let content_type = "application/json; charset=utf-8";
-        let (content, extra_headers) = get_content_with_meta(
-            ctx,
-            &format!("{}/stats/{}", ctx.get_ini().get_workdir(), path),
-        )?;
-        return Ok((content, content_type.into(), extra_headers));
-    }
-    if request_uri.ends_with(".ico") {
-        let content_type = "image/x-icon";
-        let (content, extra_headers) = get_content_with_meta(ctx, &ctx.get_abspath(path))?;
-        return Ok((content, content_type.into(), extra_headers));
+    let extension = match path.rsplit_once('.') {
+        Some((_, extension)) => extension,
+        None => return Ok((Vec::new(), "".into(), extra_headers)),
+    };
+
+    let mut found = STATIC_CONTENT_TYPES
+        .iter()
+        .find(|i| i.0 == extension)
+        .map(|i| (i.1.to_string(), i.2));
+    if found.is_none() {
+        found = ctx
+            .get_ini()
+            .get_static_content_types()?
+            .into_iter()
+            .find(|i| i.0 == extension)
+            .map(|i| (i.1, StaticRoot::Browser));
     }
-    if request_uri.ends_with(".svg") {
-        let content_type = "image/svg+xml; charset=utf-8";
-        let (content, extra_headers) = get_content_with_meta(ctx, &ctx.get_abspath(path))?;
-        return Ok((content, content_type.into(), extra_headers));
+    if let Some((content_type, root)) = found {
+        let full_path = match root {
+            StaticRoot::Browser => ctx.get_abspath(&format!("target/browser/{path}")),
+            StaticRoot::Stats => format!("{}/stats/{}", ctx.get_ini().get_workdir(), path),
+            StaticRoot::Top => ctx.get_abspath(path),
+        };
+        let (content, mut extra_headers) =
+            get_content_with_meta(ctx, &full_path).context("get_content_with_meta() failed")?;
+        if let StaticRoot::Top = root {
+            // The favicon has a fixed URL and rarely changes: allow caching it for a week.
+            extra_headers.push(("Cache-Control".into(), "max-age=604800".into()));
+        }
+        return Ok((content, content_type, extra_headers));
     }
 
     let bytes: Vec<u8> = Vec::new();
     Ok((bytes, "".into(), extra_headers))
 }
 
+/// Decides if the client accepts a gzip-encoded response, based on its Accept-Encoding header.
+fn accepts_gzip(request: &rouille::Request) -> bool {
+    let value = match request.header("Accept-Encoding") {
+        Some(value) => value,
+        None => return false,
+    };
+    value.split(',').any(|item| {
+        let mut tokens = item.split(';').map(|i| i.trim());
+        let coding = tokens.next().unwrap_or_default();
+        let disabled = tokens.any(|i| i.replace(' ', "") == "q=0");
+        (coding == "gzip" || coding == "*") && !disabled
+    })
+}
+
+/// Handles serving the reference house number list of a relation. In case a gzipped form is
+/// stored next to it, that is passed through as-is if the client accepts gzip, and decompressed
+/// otherwise.
+pub fn handle_static_lst(
+    request: &rouille::Request,
+    ctx: &context::Context,
+    request_uri: &str,
+) -> anyhow::Result<rouille::Response> {
+    let path = request_uri
+        .split('/')
+        .next_back()
+        .context("next_back() failed")?;
+    let full_path = format!("{}/{}", ctx.get_ini().get_workdir(), path);
+    let gz_path = format!("{full_path}.gz");
+    let file_system = ctx.get_file_system();
+    let mut headers: Headers = vec![("Content-type".into(), "text/plain; charset=utf-8".into())];
+    if !path.starts_with("street-housenumbers-reference-") {
+        // Only the reference lists are public, not the rest of the workdir.
+    } else if file_system.path_exists(&gz_path) {
+        let (content, extra_headers) =
+            get_content_with_meta(ctx, &gz_path).context("get_content_with_meta() failed")?;
+        headers.extend(extra_headers);
+        if accepts_gzip(request) {
+            // Already compressed, the presence of this header also avoids compressing it again.
+            headers.push(("Content-Encoding".into(), "gzip".into()));
+            return Ok(make_response(200_u16, headers, content));
+        }
+
+        let mut buf: Vec<u8> = Vec::new();
+        flate2::read::GzDecoder::new(content.as_slice())
+            .read_to_end(&mut buf)
+            .context(format!("failed to decompress '{gz_path}'"))?;
+        return Ok(make_response(200_u16, headers, buf));
+    } else if file_system.path_exists(&full_path) {
+        let (content, extra_headers) =
+            get_content_with_meta(ctx, &full_path).context("get_content_with_meta() failed")?;
+        headers.extend(extra_headers);
+        return Ok(make_response(200_u16, headers, content));
+    }
+
+    let doc = handle_404();
+    Ok(make_response(
+        404_u16,
+        vec![("Content-type".into(), "text/html; charset=utf-8".into())],
+        doc.get_value().as_bytes().to_vec(),
+    ))
+}
+
+/// Served when the configured robots.txt doesn't exist: keeps crawlers away from the routes which
+/// call Overpass or produce raw output.
+const DEFAULT_ROBOTS: &str = "User-agent: *
+Disallow: /*update-result$
+Disallow: /*view-turbo$
+Disallow: /*.txt$
+Disallow: /*.chkl$
+";
+
+/// Handles serving robots.txt.
+pub fn handle_robots(ctx: &context::Context) -> anyhow::Result<rouille::Response> {
+    let path = ctx.get_ini().get_robots_path();
+    let content = if ctx.get_file_system().path_exists(&path) {
+        ctx.get_file_system().read_to_string(&path)?
+    } else {
+        DEFAULT_ROBOTS.to_string()
+    };
+    Ok(make_response(
+        200_u16,
+        vec![
+            ("Content-type".into(), "text/plain; charset=utf-8".into()),
+            ("Cache-Control".into(), "max-age=86400".into()),
+        ],
+        content.as_bytes().to_vec(),
+    ))
+}
+
 /// Displays an unhandled error on the page.
 pub fn handle_error(request: &rouille::Request, error: &str) -> rouille::Response {
     if request.url().ends_with(".json") {
@@ -549,23 +708,19 @@ fn handle_stats_cityprogress(
     relations: &mut areas::Relations<'_>,
 ) -> anyhow::Result<yattag::Doc> {
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
 
     let mut ref_citycounts: HashMap<String, u64> = HashMap::new();
-    let csv_stream: Rc<RefCell<dyn Read>> = ctx
-        .get_file_system()
-        .open_read(&ctx.get_ini().get_reference_citycounts_path()?)?;
+    let path = ctx.get_ini().get_reference_citycounts_path()?;
+    let csv_stream: Rc<RefCell<dyn Read>> = ctx.get_file_system().open_read(&path)?;
     let mut guard = csv_stream.borrow_mut();
-    let mut read = guard.deref_mut();
+    let mut read = util::make_reference_reader(&path, guard.deref_mut())?;
     let mut csv_reader = util::make_csv_reader(&mut read);
     for result in csv_reader.deserialize() {
         let row: util::CityCount = result?;
@@ -594,12 +749,7 @@ fn handle_stats_cityprogress(
     let in_both = util::get_in_both(&ref_cities, &osm_cities);
     let mut cities: Vec<_> = in_both.iter().map(|i| i.get_osm_name()).collect();
     cities.sort_by_key(|i| util::get_sort_key(i));
-    let mut table: Vec<Vec<yattag::Doc>> = vec![vec![
-        yattag::Doc::from_text(&tr("City name")),
-        yattag::Doc::from_text(&tr("House number coverage")),
-        yattag::Doc::from_text(&tr("OSM count")),
-        yattag::Doc::from_text(&tr("Reference count")),
-    ]];
+    let mut table: Vec<Vec<yattag::Doc>> = Vec::new();
     for city in cities {
         let mut percent = 100_f64;
         if ref_citycounts[city] > 0 && osm_citycounts[city] < ref_citycounts[city] {
@@ -615,7 +765,14 @@ fn handle_stats_cityprogress(
             yattag::Doc::from_text(&ref_citycounts[city].to_string()),
         ]);
     }
-    doc.append_value(util::html_table_from_list(&table).get_value());
+    let headers = [
+        tr("City name"),
+        tr("House number coverage"),
+        tr("OSM count"),
+        tr("Reference count"),
+    ];
+    let headers: Vec<&str> = headers.iter().map(|i| i.as_str()).collect();
+    doc.table(&headers, &table);
 
     {
         let h2 = doc.tag("h2", &[]);
@@ -629,7 +786,7 @@ Only cities with house numbers in OSM are considered."#,
         ));
     }
 
-    doc.append_value(get_footer(/*last_updated=*/ "").get_value());
+    doc.append_doc(&get_footer(/*last_updated=*/ ""));
     Ok(doc)
 }
 
@@ -639,22 +796,18 @@ fn handle_stats_zipprogress(
     relations: &mut areas::Relations<'_>,
 ) -> anyhow::Result<yattag::Doc> {
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
     let mut ref_zipcounts: HashMap<String, u64> = HashMap::new();
-    let csv_stream: Rc<RefCell<dyn Read>> = ctx
-        .get_file_system()
-        .open_read(&ctx.get_ini().get_reference_zipcounts_path()?)?;
+    let path = ctx.get_ini().get_reference_zipcounts_path()?;
+    let csv_stream: Rc<RefCell<dyn Read>> = ctx.get_file_system().open_read(&path)?;
     let mut guard = csv_stream.borrow_mut();
-    let mut read = guard.deref_mut();
+    let mut read = util::make_reference_reader(&path, guard.deref_mut())?;
     let mut csv_reader = util::make_csv_reader(&mut read);
     for result in csv_reader.deserialize() {
         let row: util::ZipCount = result?;
@@ -683,12 +836,7 @@ fn handle_stats_zipprogress(
     let in_both = util::get_in_both(&ref_zips, &osm_zips);
     let mut zips: Vec<_> = in_both.iter().map(|i| i.get_osm_name()).collect();
     zips.sort_by_key(|i| util::get_sort_key(i));
-    let mut table: Vec<Vec<yattag::Doc>> = vec![vec![
-        yattag::Doc::from_text(&tr("ZIP code")),
-        yattag::Doc::from_text(&tr("House number coverage")),
-        yattag::Doc::from_text(&tr("OSM count")),
-        yattag::Doc::from_text(&tr("Reference count")),
-    ]];
+    let mut table: Vec<Vec<yattag::Doc>> = Vec::new();
     for zip in zips {
         let mut percent = 100_f64;
         if *ref_zipcounts.get(zip).unwrap() > 0
@@ -706,7 +854,14 @@ fn handle_stats_zipprogress(
             yattag::Doc::from_text(&ref_zipcounts.get(zip).unwrap().to_string()),
         ]);
     }
-    doc.append_value(util::html_table_from_list(&table).get_value());
+    let headers = [
+        tr("ZIP code"),
+        tr("House number coverage"),
+        tr("OSM count"),
+        tr("Reference count"),
+    ];
+    let headers: Vec<&str> = headers.iter().map(|i| i.as_str()).collect();
+    doc.table(&headers, &table);
 
     {
         let h2 = doc.tag("h2", &[]);
@@ -720,7 +875,7 @@ Only zip codes with house numbers in OSM are considered."#,
         ));
     }
 
-    doc.append_value(get_footer(/*last_updated=*/ "").get_value());
+    doc.append_doc(&get_footer(/*last_updated=*/ ""));
     Ok(doc)
 }
 
@@ -738,16 +893,13 @@ fn handle_invalid_addr_cities(
     relations: &mut areas::Relations<'_>,
 ) -> anyhow::Result<yattag::Doc> {
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "invalid-addr-cities",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "invalid-addr-cities",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
 
     let mut table: Vec<Vec<yattag::Doc>> = Vec::new();
     let mut count = 0;
@@ -809,8 +961,8 @@ fn handle_invalid_addr_cities(
                 .replace("{0}", &count.to_string()),
         );
     }
-    doc.append_value(util::html_table_from_list(&table).get_value());
-    doc.append_value(get_footer(&get_whole_county_last_modified(ctx)?).get_value());
+    doc.append_doc(&util::html_table_from_list(&table));
+    doc.append_doc(&get_footer(&get_whole_county_last_modified(ctx)?));
     Ok(doc)
 }
 
@@ -836,23 +988,20 @@ fn handle_invalid_addr_cities_update_html(
     handle_invalid_addr_cities_update(ctx)?;
 
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
 
     doc.text(&tr("Update successful: "));
     let prefix = ctx.get_ini().get_uri_prefix();
     let link = format!("{prefix}/lints/whole-country/invalid-addr-cities");
-    doc.append_value(util::gen_link(&link, &tr("View updated result")).get_value());
+    doc.append_doc(&util::gen_link(&link, &tr("View updated result")));
 
-    doc.append_value(get_footer(&get_whole_county_last_modified(ctx)?).get_value());
+    doc.append_doc(&get_footer(&get_whole_county_last_modified(ctx)?));
     Ok(doc)
 }
 
@@ -862,16 +1011,13 @@ fn handle_invalid_refstreets(
     relations: &mut areas::Relations<'_>,
 ) -> anyhow::Result<yattag::Doc> {
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
 
     let prefix = ctx.get_ini().get_uri_prefix();
     for relation in relations.get_relations()? {
@@ -899,13 +1045,14 @@ fn handle_invalid_refstreets(
                 a.text(&relation_name);
             }
         }
-        doc.append_value(
-            util::invalid_refstreets_to_html(&osm_invalids, &ref_invalids).get_value(),
-        );
-        doc.append_value(util::invalid_filter_keys_to_html(&key_invalids).get_value());
+        doc.append_doc(&util::invalid_refstreets_to_html(
+            &osm_invalids,
+            &ref_invalids,
+        ));
+        doc.append_doc(&util::invalid_filter_keys_to_html(&key_invalids));
     }
 
-    doc.append_value(get_footer(/*last_updated=*/ "").get_value());
+    doc.append_doc(&get_footer(/*last_updated=*/ ""));
     Ok(doc)
 }
 
@@ -926,16 +1073,13 @@ pub fn handle_stats(
     }
 
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
 
     let prefix = ctx.get_ini().get_uri_prefix();
 
@@ -1096,7 +1240,7 @@ more meaningful than a lot of useless work."#,
         ));
     }
 
-    doc.append_value(get_footer(/*last_updated=*/ "").get_value());
+    doc.append_doc(&get_footer(/*last_updated=*/ ""));
     Ok(doc)
 }
 
@@ -1119,16 +1263,13 @@ pub fn handle_lints(
     }
 
     let doc = yattag::Doc::new();
-    doc.append_value(
-        get_toolbar(
-            ctx,
-            Some(relations),
-            /*function=*/ "",
-            /*relation_name=*/ "",
-            /*relation_osmid=*/ 0,
-        )?
-        .get_value(),
-    );
+    doc.append_doc(&get_toolbar(
+        ctx,
+        Some(relations),
+        /*function=*/ "",
+        /*relation_name=*/ "",
+        /*relation_osmid=*/ 0,
+    )?);
 
     let prefix = ctx.get_ini().get_uri_prefix();
 
@@ -1166,7 +1307,7 @@ pub fn handle_lints(
         }
     }
 
-    doc.append_value(get_footer(/*last_updated=*/ "").get_value());
+    doc.append_doc(&get_footer(/*last_updated=*/ ""));
     Ok(doc)
 }
 
@@ -1209,6 +1350,475 @@ pub fn get_request_uri(
     Ok(request_uri)
 }
 
+/// Rejects requests where the relation name in the URI is not safe to be used in file paths, e.g.
+/// /osm/streets/../view-result.
+pub fn check_relation_name(ctx: &context::Context, request_uri: &str) -> Option<rouille::Response> {
+    let prefix = ctx.get_ini().get_uri_prefix();
+    let rest = request_uri.strip_prefix(&format!("{prefix}/"))?;
+    let tokens: Vec<&str> = rest.split('/').collect();
+    if tokens.len() < 3 {
+        return None;
+    }
+    let relation_routes = [
+        "streets",
+        "missing-streets",
+        "additional-streets",
+        "street-housenumbers",
+        "missing-housenumbers",
+        "additional-housenumbers",
+        "relations",
+        "lints",
+    ];
+    if !relation_routes.contains(&tokens[0]) || util::is_safe_relation_name(tokens[1]) {
+        return None;
+    }
+
+    Some(make_response(
+        400_u16,
+        vec![("Content-type".into(), "text/plain; charset=utf-8".into())],
+        b"invalid relation name\n".to_vec(),
+    ))
+}
+
+/// Decides if the Accept header of the request prefers JSON over HTML. No Accept header or */*
+/// means HTML.
+pub fn prefers_json(request: &rouille::Request) -> bool {
+    let accept = match request.header("Accept") {
+        Some(value) => value,
+        None => return false,
+    };
+    let mut best: Option<(&str, f64)> = None;
+    for item in accept.split(',') {
+        let mut tokens = item.split(';').map(|i| i.trim());
+        let media_type = tokens.next().unwrap_or_default();
+        let quality = tokens
+            .filter_map(|i| i.strip_prefix("q="))
+            .find_map(|i| i.parse::<f64>().ok())
+            .unwrap_or(1.0);
+        // The first of the equally preferred types wins.
+        if quality > 0.0 && best.is_none_or(|i| quality > i.1) {
+            best = Some((media_type, quality));
+        }
+    }
+    best.is_some_and(|i| i.0 == "application/json")
+}
+
+/// Handles the readiness check: all configured reference files are readable.
+pub fn handle_readyz(ctx: &context::Context) -> anyhow::Result<rouille::Response> {
+    let ini = ctx.get_ini();
+    let mut paths: Vec<(&str, String)> = ini
+        .get_reference_housenumber_paths()?
+        .into_iter()
+        .map(|path| ("reference_housenumbers", path))
+        .collect();
+    paths.push(("reference_street", ini.get_reference_street_path()?));
+    paths.push(("reference_citycounts", ini.get_reference_citycounts_path()?));
+    paths.push(("reference_zipcounts", ini.get_reference_zipcounts_path()?));
+    let mut missing: Vec<&str> = Vec::new();
+    for (key, path) in paths {
+        if ctx.get_file_system().path_exists(&path)
+            && ctx.get_file_system().open_read(&path).is_ok()
+        {
+            continue;
+        }
+
+        // Only the log has the path, the response is public.
+        log::warn!("handle_readyz: {key}: '{path}' is missing or unreadable");
+        if !missing.contains(&key) {
+            missing.push(key);
+        }
+    }
+
+    let headers = vec![("Content-type".into(), "text/plain; charset=utf-8".into())];
+    if missing.is_empty() {
+        return Ok(make_response(200_u16, headers, b"ok\n".to_vec()));
+    }
+
+    let mut output = String::from("missing or unreadable reference files:\n");
+    for key in missing {
+        output.push_str(&format!("{key}\n"));
+    }
+    Ok(make_response(503_u16, headers, output.as_bytes().to_vec()))
+}
+
+/// Expected request_uri: e.g. /osm/debug/config, shows the (redacted) wsgi.ini values, if enabled.
+pub fn handle_debug_config(ctx: &context::Context) -> anyhow::Result<rouille::Response> {
+    let headers = vec![("Content-type".into(), "text/html; charset=utf-8".into())];
+    if !ctx.get_ini().get_debug_enabled() {
+        let doc = handle_404();
+        return Ok(make_response(
+            404_u16,
+            headers,
+            doc.get_value().as_bytes().to_vec(),
+        ));
+    }
+
+    let mut table = vec![vec![
+        yattag::Doc::from_text(&tr("Key")),
+        yattag::Doc::from_text(&tr("Value")),
+    ]];
+    for (key, value) in ctx.get_ini().get_redacted_values()? {
+        table.push(vec![
+            yattag::Doc::from_text(&key),
+            yattag::Doc::from_text(&value),
+        ]);
+    }
+    let doc = yattag::Doc::new();
+    util::write_html_header(&doc);
+    {
+        let html = doc.tag("html", &[]);
+        let body = html.tag("body", &[]);
+        body.append_value(util::html_table_from_list(&table).get_value());
+    }
+    Ok(make_response(
+        200_u16,
+        headers,
+        doc.get_value().as_bytes().to_vec(),
+    ))
+}
+
+/// Handles POST /osm/debug/clear-cache: removes the cached output of all relations, but keeps the
+/// fetched OSM data and the coverages, so nothing has to be downloaded again.
+pub fn handle_debug_clear_cache(
+    request: &rouille::Request,
+    ctx: &context::Context,
+    relations: &mut areas::Relations<'_>,
+) -> anyhow::Result<rouille::Response> {
+    if !ctx.get_ini().get_debug_enabled() {
+        let doc = handle_404();
+        return Ok(make_response(
+            404_u16,
+            vec![("Content-type".into(), "text/html; charset=utf-8".into())],
+            doc.get_value().as_bytes().to_vec(),
+        ));
+    }
+
+    let mut headers: Headers = vec![(
+        "Content-type".into(),
+        "application/json; charset=utf-8".into(),
+    )];
+    if request.method() != "POST" {
+        let output = serde_json::json!({"error": "expected POST"}).to_string();
+        headers.push(("Allow".into(), "POST".into()));
+        return Ok(make_response(405_u16, headers, output.as_bytes().to_vec()));
+    }
+
+    let mut removed = 0;
+    for relation_name in relations.get_names() {
+        let relation = relations.get_relation(&relation_name)?;
+        if cache::invalidate_relation(&relation)? {
+            removed += 1;
+        }
+    }
+    let output = serde_json::json!({"removed": removed}).to_string();
+    Ok(make_response(200_u16, headers, output.as_bytes().to_vec()))
+}
+
+/// Token bucket of the routes which call Overpass on behalf of a visitor.
+struct UpdateBucket {
+    /// Tokens per minute, also the capacity of the bucket.
+    rate: u32,
+    tokens: f64,
+    /// Unix timestamp in nanoseconds of the last refill.
+    last: i128,
+}
+
+#[cfg(not(test))]
+lazy_static::lazy_static! {
+    static ref UPDATE_BUCKET: std::sync::Mutex<Option<UpdateBucket>> =
+        std::sync::Mutex::new(None);
+}
+
+#[cfg(not(test))]
+fn with_update_bucket<T>(f: impl FnOnce(&mut Option<UpdateBucket>) -> T) -> T {
+    let mut guard = UPDATE_BUCKET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+thread_local! {
+    // Tests run in parallel, give each of them its own bucket.
+    static UPDATE_BUCKET: RefCell<Option<UpdateBucket>> = const { RefCell::new(None) };
+}
+
+#[cfg(test)]
+fn with_update_bucket<T>(f: impl FnOnce(&mut Option<UpdateBucket>) -> T) -> T {
+    UPDATE_BUCKET.with(|bucket| f(&mut bucket.borrow_mut()))
+}
+
+/// Takes a token from the bucket. Returns the number of seconds to wait if the bucket is empty.
+fn take_update_token(bucket: &mut Option<UpdateBucket>, rate: u32, now: i128) -> Option<u64> {
+    if bucket.as_ref().map(|i| i.rate) != Some(rate) {
+        *bucket = Some(UpdateBucket {
+            rate,
+            tokens: rate as f64,
+            last: now,
+        });
+    }
+    let bucket = bucket.as_mut().unwrap();
+    let elapsed = (now - bucket.last).max(0) as f64 / 1_000_000_000_f64;
+    bucket.tokens = (bucket.tokens + elapsed * rate as f64 / 60_f64).min(rate as f64);
+    bucket.last = now;
+    if bucket.tokens >= 1_f64 {
+        bucket.tokens -= 1_f64;
+        return None;
+    }
+
+    Some(((1_f64 - bucket.tokens) * 60_f64 / rate as f64).ceil() as u64)
+}
+
+/// Decides if request_uri is a route which calls Overpass, e.g.
+/// /osm/streets/gazdagret/update-result.
+fn is_overpass_update_uri(ctx: &context::Context, request_uri: &str) -> bool {
+    let prefix = ctx.get_ini().get_uri_prefix();
+    if request_uri.starts_with(&format!(
+        "{prefix}/lints/whole-country/invalid-addr-cities/update-result"
+    )) {
+        return true;
+    }
+    if request_uri.starts_with(&format!("{prefix}/relations/")) && request_uri.ends_with("/refresh")
+    {
+        return true;
+    }
+    (request_uri.starts_with(&format!("{prefix}/streets/"))
+        || request_uri.starts_with(&format!("{prefix}/street-housenumbers/")))
+        && (request_uri.ends_with("/update-result") || request_uri.ends_with("/update-result.json"))
+}
+
+/// Limits how often visitors can trigger Overpass queries: returns a 429 response once the
+/// per-process budget is used up. Cron talks to Overpass directly, so it's not limited.
+pub fn check_update_rate(
+    ctx: &context::Context,
+    request_uri: &str,
+) -> anyhow::Result<Option<rouille::Response>> {
+    let rate = ctx.get_ini().get_update_rate_per_minute()?;
+    if rate == 0 || !is_overpass_update_uri(ctx, request_uri) {
+        return Ok(None);
+    }
+
+    let now = ctx.get_time().now().unix_timestamp_nanos();
+    let retry_after = match with_update_bucket(|bucket| take_update_token(bucket, rate, now)) {
+        Some(value) => value,
+        None => {
+            return Ok(None);
+        }
+    };
+    let headers = vec![
+        ("Content-type".into(), "text/plain; charset=utf-8".into()),
+        ("Retry-After".into(), retry_after.to_string().into()),
+    ];
+    let output = format!("too many update requests, try again in {retry_after} seconds\n");
+    Ok(Some(make_response(
+        429_u16,
+        headers,
+        output.as_bytes().to_vec(),
+    )))
+}
+
+/// State of an asynchronous relation refresh.
+#[derive(Clone, serde::Serialize)]
+struct RefreshState {
+    /// "pending", "done" or "failed".
+    status: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    error: String,
+}
+
+#[cfg(not(test))]
+lazy_static::lazy_static! {
+    static ref REFRESH_STATES: std::sync::Mutex<HashMap<String, RefreshState>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+#[cfg(not(test))]
+fn with_refresh_states<T>(f: impl FnOnce(&mut HashMap<String, RefreshState>) -> T) -> T {
+    let mut guard = REFRESH_STATES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+thread_local! {
+    static REFRESH_STATES: RefCell<HashMap<String, RefreshState>> = RefCell::new(HashMap::new());
+}
+
+#[cfg(test)]
+fn with_refresh_states<T>(f: impl FnOnce(&mut HashMap<String, RefreshState>) -> T) -> T {
+    REFRESH_STATES.with(|states| f(&mut states.borrow_mut()))
+}
+
+/// Updates the OSM streets and house numbers of a relation.
+fn refresh_relation(ctx: &context::Context, relation_name: &str) -> anyhow::Result<()> {
+    let mut relations = areas::Relations::new(ctx)?;
+    let relation = relations.get_relation(relation_name)?;
+    let query = relation.get_osm_streets_json_query()?;
+    let buf = overpass_query::overpass_query(ctx, &query)?;
+    relation.get_files().write_osm_json_streets(ctx, &buf)?;
+    let query = relation.get_osm_housenumbers_json_query()?;
+    let buf = overpass_query::overpass_query(ctx, &query)?;
+    relation.get_files().write_osm_json_housenumbers(ctx, &buf)
+}
+
+/// Records the outcome of a finished refresh.
+fn finish_refresh(relation_name: &str, result: anyhow::Result<()>) {
+    let state = match result {
+        Ok(_) => RefreshState {
+            status: "done".into(),
+            error: "".into(),
+        },
+        Err(err) => RefreshState {
+            status: "failed".into(),
+            error: format!("{err:?}"),
+        },
+    };
+    with_refresh_states(|states| states.insert(relation_name.to_string(), state));
+}
+
+/// Runs the refresh on a background thread, with its own context.
+#[cfg(not(test))]
+fn spawn_refresh(_ctx: &context::Context, relation_name: &str) {
+    let relation_name = relation_name.to_string();
+    std::thread::spawn(move || {
+        let result =
+            context::Context::new("").and_then(|ctx| refresh_relation(&ctx, &relation_name));
+        finish_refresh(&relation_name, result);
+    });
+}
+
+/// Runs the refresh synchronously, so tests can see the final state.
+#[cfg(test)]
+fn spawn_refresh(ctx: &context::Context, relation_name: &str) {
+    let result = refresh_relation(ctx, relation_name);
+    finish_refresh(relation_name, result);
+}
+
+/// Handles the asynchronous refresh of a relation:
+/// POST /osm/relations/<name>/refresh starts it and GET /osm/relations/<name>/refresh/status
+/// reports its state. Returns None for other request URIs.
+pub fn handle_refresh(
+    request: &rouille::Request,
+    ctx: &context::Context,
+    relations: &areas::Relations<'_>,
+    request_uri: &str,
+) -> anyhow::Result<Option<rouille::Response>> {
+    let prefix = ctx.get_ini().get_uri_prefix();
+    let path = match request_uri.strip_prefix(&format!("{prefix}/relations/")) {
+        Some(value) => value,
+        None => {
+            return Ok(None);
+        }
+    };
+    let (relation_name, status) = if let Some(name) = path.strip_suffix("/refresh/status") {
+        (name, true)
+    } else if let Some(name) = path.strip_suffix("/refresh") {
+        (name, false)
+    } else {
+        return Ok(None);
+    };
+
+    let headers: Headers = vec![(
+        "Content-type".into(),
+        "application/json; charset=utf-8".into(),
+    )];
+    if !relations.get_names().contains(&relation_name.to_string()) {
+        let output = serde_json::json!({"error": "no such relation"}).to_string();
+        return Ok(Some(make_response(
+            404_u16,
+            headers,
+            output.as_bytes().to_vec(),
+        )));
+    }
+
+    if status {
+        let state = with_refresh_states(|states| states.get(relation_name).cloned());
+        let (status_code, output) = match state {
+            Some(value) => (200_u16, serde_json::to_string(&value)?),
+            None => (
+                404_u16,
+                serde_json::json!({"error": "no refresh was requested"}).to_string(),
+            ),
+        };
+        return Ok(Some(make_response(
+            status_code,
+            headers,
+            output.as_bytes().to_vec(),
+        )));
+    }
+
+    if request.method() != "POST" {
+        let output = serde_json::json!({"error": "expected POST"}).to_string();
+        let mut headers = headers;
+        headers.push(("Allow".into(), "POST".into()));
+        return Ok(Some(make_response(
+            405_u16,
+            headers,
+            output.as_bytes().to_vec(),
+        )));
+    }
+
+    let status_url = format!("{prefix}/relations/{relation_name}/refresh/status");
+    let already_pending = with_refresh_states(|states| {
+        let pending = states
+            .get(relation_name)
+            .is_some_and(|i| i.status == "pending");
+        states.insert(
+            relation_name.to_string(),
+            RefreshState {
+                status: "pending".into(),
+                error: "".into(),
+            },
+        );
+        pending
+    });
+    let output = serde_json::json!({"status": "pending", "status_url": status_url}).to_string();
+    let mut headers = headers;
+    headers.push(("Location".into(), status_url.into()));
+    let response = make_response(202_u16, headers, output.as_bytes().to_vec());
+    if !already_pending {
+        spawn_refresh(ctx, relation_name);
+    }
+    Ok(Some(response))
+}
+
+/// Decides if request_uri is the canonical form of a relation page, e.g.
+/// /osm/missing-housenumbers/gazdagret/view-result, without a trailing slash.
+fn is_canonical_relation_uri(ctx: &context::Context, request_uri: &str) -> bool {
+    let prefix = ctx.get_ini().get_uri_prefix();
+    let path = match request_uri.strip_prefix(&prefix) {
+        Some(value) => value,
+        None => {
+            return false;
+        }
+    };
+    let tokens: Vec<&str> = path.split('/').collect();
+    if tokens.len() != 4 || !tokens[0].is_empty() || tokens[2].is_empty() || tokens[3].is_empty() {
+        return false;
+    }
+
+    [
+        "streets",
+        "missing-streets",
+        "additional-streets",
+        "additional-housenumbers",
+        "street-housenumbers",
+        "missing-housenumbers",
+    ]
+    .contains(&tokens[1])
+}
+
+/// Gets the canonical form of a relation page URI, or None if request_uri is not a relation page.
+pub fn get_canonical_relation_uri(ctx: &context::Context, request_uri: &str) -> Option<String> {
+    let canonical = request_uri.trim_end_matches('/');
+    if !is_canonical_relation_uri(ctx, canonical) {
+        return None;
+    }
+
+    Some(canonical.to_string())
+}
+
 /// Prevents serving outdated data from a relation that has been renamed.
 pub fn check_existing_relation(
     ctx: &context::Context,
@@ -1349,6 +1959,60 @@ pub fn make_response(status_code: u16, headers: Headers, data: Vec<u8>) -> rouil
     }
 }
 
+/// Parses a single range of a 'Range: bytes=...' header into an inclusive start and end offset,
+/// for a content of the given length. Returns None if the range is invalid, not satisfiable or has
+/// multiple parts, then the full content is served.
+fn parse_byte_range(value: &str, len: usize) -> Option<(usize, usize)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let last = len.checked_sub(1)?;
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = if start.is_empty() {
+        // Suffix range, e.g. 'bytes=-500' is the last 500 bytes.
+        let suffix: usize = end.parse().ok()?;
+        if suffix == 0 {
+            return None;
+        }
+        (len.saturating_sub(suffix), last)
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end: usize = if end.is_empty() {
+            last
+        } else {
+            std::cmp::min(end.parse().ok()?, last)
+        };
+        (start, end)
+    };
+    if start > end {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Makes a response for a static file, serving only part of it in case the request has a valid
+/// Range header.
+pub fn make_range_response(
+    request: &rouille::Request,
+    mut headers: Headers,
+    data: Vec<u8>,
+) -> rouille::Response {
+    headers.push(("Accept-Ranges".into(), "bytes".into()));
+    let range = request
+        .header("Range")
+        .and_then(|value| parse_byte_range(value, data.len()));
+    if let Some((start, end)) = range {
+        let content_range = format!("bytes {start}-{end}/{}", data.len());
+        headers.push(("Content-Range".into(), content_range.into()));
+        // The range is of the uncompressed content, so this disables compression.
+        headers.push(("Content-Encoding".into(), "identity".into()));
+        return make_response(206_u16, headers, data[start..=end].to_vec());
+    }
+
+    make_response(200_u16, headers, data)
+}
+
 /// Gets the content of a file in workdir with metadata.
 fn get_content_with_meta(ctx: &context::Context, path: &str) -> anyhow::Result<(Vec<u8>, Headers)> {
     let stream = ctx
@@ -75,6 +75,22 @@ fn test_handle_static_json() {
     assert_eq!(extra_headers[0].0, "Last-Modified");
 }
 
+/// Tests parse_byte_range().
+#[test]
+fn test_parse_byte_range() {
+    assert_eq!(parse_byte_range("bytes=0-9", 100), Some((0, 9)));
+    assert_eq!(parse_byte_range("bytes=90-", 100), Some((90, 99)));
+    assert_eq!(parse_byte_range("bytes=-10", 100), Some((90, 99)));
+    // The end is clamped to the content.
+    assert_eq!(parse_byte_range("bytes=90-200", 100), Some((90, 99)));
+    // Not satisfiable, invalid or multiple ranges: serve the full content.
+    assert_eq!(parse_byte_range("bytes=100-", 100), None);
+    assert_eq!(parse_byte_range("bytes=9-0", 100), None);
+    assert_eq!(parse_byte_range("items=0-9", 100), None);
+    assert_eq!(parse_byte_range("bytes=0-1,5-6", 100), None);
+    assert_eq!(parse_byte_range("bytes=0-", 0), None);
+}
+
 /// Tests handle_static: the ico case.
 #[test]
 fn test_handle_static_ico() {
@@ -102,8 +118,10 @@ fn test_handle_static_ico() {
 
     assert_eq!(content.is_empty(), false);
     assert_eq!(content_type, "image/x-icon");
-    assert_eq!(extra_headers.len(), 1);
+    assert_eq!(extra_headers.len(), 2);
     assert_eq!(extra_headers[0].0, "Last-Modified");
+    assert_eq!(extra_headers[1].0, "Cache-Control");
+    assert_eq!(extra_headers[1].1, "max-age=604800");
 }
 
 /// Tests handle_static: the svg case.
@@ -133,6 +151,47 @@ fn test_handle_static_svg() {
 
     assert_eq!(content.is_empty(), false);
     assert_eq!(content_type, "image/svg+xml; charset=utf-8");
+    assert_eq!(extra_headers.len(), 2);
+    assert_eq!(extra_headers[0].0, "Last-Modified");
+    assert_eq!(extra_headers[1].0, "Cache-Control");
+    assert_eq!(extra_headers[1].1, "max-age=604800");
+}
+
+/// Tests handle_static: an extra content type from wsgi.ini.
+#[test]
+fn test_handle_static_extra_content_type() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(
+        &mut ctx,
+        "static_content_types = 'wasm=application/wasm'\n",
+    )
+    .unwrap();
+    let wasm = context::tests::TestFileSystem::make_file();
+    {
+        let mut guard = wasm.borrow_mut();
+        let write = guard.deref_mut();
+        write.write_all(b"\0asm").unwrap();
+    }
+    let mut file_system = context::tests::TestFileSystem::new();
+    let files =
+        context::tests::TestFileSystem::make_files(&ctx, &[("target/browser/app.wasm", &wasm)]);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    let path = ctx.get_abspath("target/browser/app.wasm");
+    mtimes.insert(
+        path,
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_files(&files);
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    let prefix = ctx.get_ini().get_uri_prefix();
+    let (content, content_type, extra_headers) =
+        handle_static(&ctx, &format!("{prefix}/static/app.wasm")).unwrap();
+
+    assert_eq!(content, b"\0asm");
+    assert_eq!(content_type, "application/wasm");
     assert_eq!(extra_headers.len(), 1);
     assert_eq!(extra_headers[0].0, "Last-Modified");
 }
@@ -205,6 +264,113 @@ fn test_get_toolbar() {
     assert_eq!(ret.get_value().is_empty(), false);
 }
 
+/// Tests get_toolbar(): no site name is shown by default.
+#[test]
+fn test_get_toolbar_no_site_name() {
+    let ctx = context::tests::make_test_context().unwrap();
+
+    let ret = get_toolbar(&ctx, None, "myfunc", "myrel", 42).unwrap();
+
+    assert!(!ret.get_value().contains("<strong>"));
+}
+
+/// Tests get_toolbar(): the configured site name is in the header.
+#[test]
+fn test_get_toolbar_site_name() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "site_name = 'Budapest maps'\n").unwrap();
+
+    let ret = get_toolbar(&ctx, None, "myfunc", "myrel", 42).unwrap();
+
+    assert!(ret.get_value().contains("<strong>Budapest maps</strong>"));
+}
+
+/// Tests get_toolbar(): the relation description is shown, escaped.
+#[test]
+fn test_get_toolbar_description() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "myrel": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-myrel.yaml": {
+            "description": "waiting on <municipality> data",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let ret = get_toolbar(&ctx, Some(&mut relations), "myfunc", "myrel", 42).unwrap();
+
+    assert!(ret
+        .get_value()
+        .contains(r#"<p id="relation-description">waiting on &lt;municipality&gt; data</p>"#));
+    let ret = get_toolbar(&ctx, None, "myfunc", "myrel", 42).unwrap();
+    assert!(!ret.get_value().contains("relation-description"));
+}
+
+/// Tests get_toolbar(): the display name is shown, while the URLs still use the relation name.
+#[test]
+fn test_get_toolbar_display_name() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "ujbuda": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-ujbuda.yaml": {
+            "display-name": "Újbuda (XI. kerület)",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    ctx.set_file_system(&file_system);
+    let mut relations = areas::Relations::new(&ctx).unwrap();
+
+    let ret = get_toolbar(
+        &ctx,
+        Some(&mut relations),
+        "missing-housenumbers",
+        "ujbuda",
+        42,
+    )
+    .unwrap();
+
+    let value = ret.get_value();
+    assert!(value.contains(r#"<span id="relation-display-name">Újbuda (XI. kerület)</span>"#));
+    assert!(value.contains("/missing-housenumbers/ujbuda/update-result"));
+    assert!(!value.contains("/Újbuda"));
+    let relation = relations.get_relation("ujbuda").unwrap();
+    assert_eq!(relation.get_name(), "ujbuda");
+    assert_eq!(relation.get_display_name(), "Újbuda (XI. kerület)");
+}
+
+/// Tests get_toolbar_with_js(): the hidden JS strings are emitted by default only.
+#[test]
+fn test_get_toolbar_with_js() {
+    let ctx = context::tests::make_test_context().unwrap();
+
+    let ret = get_toolbar(&ctx, None, "myfunc", "myrel", 42).unwrap();
+    assert!(ret.get_value().contains("str-toolbar-overpass-wait"));
+
+    let ret = get_toolbar_with_js(&ctx, None, "myfunc", "myrel", 42, /*with_js=*/ false).unwrap();
+    assert!(!ret.get_value().contains("str-toolbar-overpass-wait"));
+    assert!(ret.get_value().contains("toolbar"));
+}
+
 /// Tests handle_invalid_addr_cities().
 #[test]
 fn test_handle_invalid_addr_cities() {
@@ -421,3 +587,101 @@ fn test_handle_error_json() {
     let value: HashMap<String, String> = serde_json::from_str(&output).unwrap();
     assert_eq!(value["error"], "myerror");
 }
+
+/// Tests handle_readyz(): the happy path.
+#[test]
+fn test_handle_readyz() {
+    let ctx = context::tests::make_test_context().unwrap();
+
+    let response = handle_readyz(&ctx).unwrap();
+
+    assert_eq!(response.status_code, 200);
+}
+
+/// Tests handle_readyz(): the config key of a missing reference file is named in the 503 response.
+#[test]
+fn test_handle_readyz_missing() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    let street_path = ctx.get_ini().get_reference_street_path().unwrap();
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_hide_paths(&[street_path.clone()]);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    ctx.set_file_system(&file_system_rc);
+
+    let response = handle_readyz(&ctx).unwrap();
+
+    assert_eq!(response.status_code, 503);
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    let body = String::from_utf8(data).unwrap();
+    assert_eq!(
+        body,
+        "missing or unreadable reference files:\nreference_street\n"
+    );
+    assert!(!body.contains(&street_path));
+}
+
+/// Tests check_update_rate(): more update requests than the bucket allows get a 429.
+#[test]
+fn test_check_update_rate() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "update_rate_per_minute = '2'\n").unwrap();
+
+    let uri = "/osm/streets/gazdagret/update-result";
+    assert_eq!(check_update_rate(&ctx, uri).unwrap().is_none(), true);
+    assert_eq!(check_update_rate(&ctx, uri).unwrap().is_none(), true);
+    let response = check_update_rate(&ctx, uri).unwrap().unwrap();
+
+    assert_eq!(response.status_code, 429);
+    let retry_after = response
+        .headers
+        .iter()
+        .find(|(key, _value)| key == "Retry-After")
+        .map(|(_key, value)| value.to_string());
+    assert_eq!(retry_after, Some("30".to_string()));
+    // Not an update route: not limited.
+    assert_eq!(
+        check_update_rate(&ctx, "/osm/streets/gazdagret/view-result")
+            .unwrap()
+            .is_none(),
+        true
+    );
+}
+
+/// Tests check_relation_name(): names which could escape the workdir are rejected.
+#[test]
+fn test_check_relation_name() {
+    let ctx = context::tests::make_test_context().unwrap();
+
+    assert_eq!(
+        check_relation_name(&ctx, "/osm/streets/gazdagret/view-result").is_none(),
+        true
+    );
+    // Not a relation route.
+    assert_eq!(
+        check_relation_name(&ctx, "/osm/static/osm.min.css").is_none(),
+        true
+    );
+    let response = check_relation_name(&ctx, "/osm/streets/../secret/view-result").unwrap();
+    assert_eq!(response.status_code, 400);
+    let response =
+        check_relation_name(&ctx, "/osm/missing-housenumbers/..%2Fsecret/view-result").unwrap();
+    assert_eq!(response.status_code, 400);
+}
+
+/// Tests check_update_rate(): the bucket refills with time.
+#[test]
+fn test_check_update_rate_refill() {
+    let mut ctx = context::tests::make_test_context().unwrap();
+    context::tests::set_test_ini_extra(&mut ctx, "update_rate_per_minute = '1'\n").unwrap();
+    let uri = "/osm/street-housenumbers/gazdagret/update-result.json";
+    assert_eq!(check_update_rate(&ctx, uri).unwrap().is_none(), true);
+    assert_eq!(check_update_rate(&ctx, uri).unwrap().is_some(), true);
+
+    let time = context::tests::TestTime::new(2020, 5, 11);
+    let time_rc: Rc<dyn context::Time> = Rc::new(time);
+    ctx.set_time(&time_rc);
+
+    assert_eq!(check_update_rate(&ctx, uri).unwrap().is_none(), true);
+}
@@ -241,6 +241,38 @@ fn missing_housenumbers_view_turbo(
     Ok(doc)
 }
 
+/// Expected request uri: /osm/missing-housenumbers/ormezo/view-unparseable.
+fn missing_housenumbers_view_unparseable(
+    relation: &mut areas::Relation<'_>,
+) -> anyhow::Result<yattag::Doc> {
+    let doc = yattag::Doc::new();
+
+    // Update the list if it's outdated.
+    cache::get_missing_housenumbers_json(relation)
+        .context("get_missing_housenumbers_json() failed")?;
+
+    let unparseable_refs = relation.get_unparseable_refs()?;
+    {
+        let p = doc.tag("p", &[]);
+        p.text(
+            &tr("The below {0} reference entries can't be interpreted as house numbers.")
+                .replace("{0}", &unparseable_refs.len().to_string()),
+        );
+    }
+    let mut table: Vec<Vec<yattag::Doc>> = vec![vec![
+        yattag::Doc::from_text(&tr("Street")),
+        yattag::Doc::from_text(&tr("Housenumber")),
+    ]];
+    for (street, housenumber) in unparseable_refs {
+        table.push(vec![
+            yattag::Doc::from_text(&street),
+            yattag::Doc::from_text(&housenumber),
+        ]);
+    }
+    doc.append_value(util::html_table_from_list(&table).get_value());
+    Ok(doc)
+}
+
 /// Expected request uri: /osm/missing-housenumbers/ormezo/view-lints.
 fn missing_housenumbers_view_lints(
     ctx: &context::Context,
@@ -324,102 +356,6 @@ fn missing_housenumbers_view_lints(
     Ok(doc)
 }
 
-/// The actual HTML part of missing_housenumbers_view_res().
-fn missing_housenumbers_view_res_html(
-    ctx: &context::Context,
-    relation: &mut areas::Relation<'_>,
-) -> anyhow::Result<yattag::Doc> {
-    let doc = yattag::Doc::new();
-    let (todo_street_count, todo_count, done_count, percent, table) = relation
-        .write_missing_housenumbers()
-        .context("write_missing_housenumbers() failed")?;
-
-    {
-        let p = doc.tag("p", &[]);
-        let prefix = ctx.get_ini().get_uri_prefix();
-        let relation_name = relation.get_name();
-        p.text(
-            &tr("OpenStreetMap is possibly missing the below {0} house numbers for {1} streets.")
-                .replace("{0}", &todo_count.to_string())
-                .replace("{1}", &todo_street_count.to_string()),
-        );
-        let percent = util::format_percent(percent).context("format_percent() failed")?;
-        p.text(
-            &tr(" (existing: {0}, ready: {1}).")
-                .replace("{0}", &done_count.to_string())
-                .replace("{1}", &percent),
-        );
-        doc.stag("br");
-        {
-            let a = doc.tag(
-                "a",
-                &[(
-                    "href",
-                    &tr("https://vmiklos.hu/osm-gimmisn/usage.html#filtering-out-incorrect-information"),
-                )],
-            );
-            a.text(&tr("Filter incorrect information"));
-        }
-        doc.text(".");
-        doc.stag("br");
-        {
-            let a = doc.tag(
-                "a",
-                &[(
-                    "href",
-                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-turbo"),
-                )],
-            );
-            a.text(&tr("Overpass turbo query for the below streets"));
-        }
-        doc.stag("br");
-        {
-            let a = doc.tag(
-                "a",
-                &[(
-                    "href",
-                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-result.txt"),
-                )],
-            );
-            a.text(&tr("Plain text format"));
-        }
-        doc.stag("br");
-        {
-            let a = doc.tag(
-                "a",
-                &[(
-                    "href",
-                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-result.chkl"),
-                )],
-            );
-            a.text(&tr("Checklist format"));
-        }
-        doc.stag("br");
-        {
-            let a = doc.tag(
-                "a",
-                &[(
-                    "href",
-                    &format!("{prefix}/missing-housenumbers/{relation_name}/view-lints"),
-                )],
-            );
-            a.text(&tr("View lints"));
-        }
-    }
-
-    doc.append_value(util::html_table_from_list(&table).get_value());
-    if let Ok((osm_invalids, ref_invalids)) = relation.get_invalid_refstreets() {
-        doc.append_value(
-            util::invalid_refstreets_to_html(&osm_invalids, &ref_invalids).get_value(),
-        );
-    }
-    doc.append_value(
-        util::invalid_filter_keys_to_html(&relation.get_invalid_filter_keys()?).get_value(),
-    );
-
-    Ok(doc)
-}
-
 /// Expected request_uri: e.g. /osm/missing-housenumbers/ormezo/view-result.
 fn missing_housenumbers_view_res(
     ctx: &context::Context,
@@ -443,7 +379,12 @@ fn missing_housenumbers_view_res(
     {
         doc = webframe::handle_no_ref_housenumbers(&prefix, relation_name);
     } else {
-        let ret = missing_housenumbers_view_res_html(ctx, &mut relation);
+        // Keep the coverage percent in sync with the analysis.
+        relation
+            .write_missing_housenumbers()
+            .context("write_missing_housenumbers() failed")?;
+        let ret =
+            cache::get_missing_housenumbers_html(ctx, &mut relation, /*html_only=*/ false);
         doc = ret.context("get_missing_housenumbers_html() failed")?;
     }
     Ok(doc)
@@ -746,6 +687,12 @@ fn handle_missing_housenumbers(
                 .context("missing_housenumbers_view_lints() failed")?
                 .get_value(),
         )
+    } else if action == "view-unparseable" {
+        doc.append_value(
+            missing_housenumbers_view_unparseable(&mut relation)
+                .context("missing_housenumbers_view_unparseable() failed")?
+                .get_value(),
+        )
     } else {
         // assume view-result
         let ret = missing_housenumbers_view_res(ctx, relations, request_uri);
@@ -1117,7 +1064,11 @@ fn create_filter_for_relations(relation_filter: &str) -> Box<RelationFilter> {
     let relations_arc = Arc::new(relations);
     let relations = relations_arc;
     Box::new(move |_complete, relation| {
-        relations.contains(&relation.get_config().get_osmrelation())
+        relation
+            .get_config()
+            .get_osmrelations()
+            .iter()
+            .any(|i| relations.contains(i))
     })
 }
 
@@ -1307,7 +1258,33 @@ fn handle_main_relation(
 
     let streets = relation.get_config().should_check_missing_streets();
 
-    let mut row = vec![yattag::Doc::from_text(relation_name)];
+    let name_cell = yattag::Doc::from_text(relation_name);
+    if relation.is_stale()? {
+        name_cell.text(" ");
+        let span = name_cell.tag(
+            "span",
+            &[
+                ("class", "badge-stale"),
+                ("title", &tr("OSM data was not updated recently")),
+            ],
+        );
+        span.text(&tr("stale"));
+    }
+    if relation.get_config().is_done() {
+        name_cell.text(" ");
+        let span = name_cell.tag(
+            "span",
+            &[
+                ("class", "badge-done"),
+                (
+                    "title",
+                    &tr("Marked as done, OSM data is not updated nightly"),
+                ),
+            ],
+        );
+        span.text(&tr("completed"));
+    }
+    let mut row = vec![name_cell];
 
     if streets != "only" {
         let (cell, percent) = handle_main_housenr_percent(ctx, &relation)
@@ -1360,10 +1337,49 @@ fn handle_main_relation(
     Ok(row)
 }
 
+/// Sorts the relation names of the main page, based on the sort=percent|name and order=asc|desc
+/// query parameters. Relations without a house number percent are listed last, in name order.
+fn get_main_relation_names(
+    relations: &mut areas::Relations<'_>,
+    sort: &Option<String>,
+    order: &Option<String>,
+) -> anyhow::Result<Vec<String>> {
+    let mut names = relations.get_names();
+    let descending = order.as_deref() == Some("desc");
+    if sort.as_deref() == Some("percent") {
+        let mut percents: HashMap<String, f64> = HashMap::new();
+        for name in &names {
+            let relation = relations.get_relation(name)?;
+            if relation.has_osm_housenumber_coverage()? {
+                let string = relation.get_osm_housenumber_coverage()?;
+                let percent = string.parse::<f64>().context("parse to f64 failed")?;
+                percents.insert(name.to_string(), percent);
+            }
+        }
+        // This is a stable sort, so relations with the same percent stay in name order.
+        names.sort_by(|a, b| match (percents.get(a), percents.get(b)) {
+            (Some(a), Some(b)) => {
+                if descending {
+                    b.total_cmp(a)
+                } else {
+                    a.total_cmp(b)
+                }
+            }
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    } else if descending {
+        names.reverse();
+    }
+    Ok(names)
+}
+
 /// Handles the main wsgi page.
 ///
 /// Also handles /osm/filter-for/* which filters for a condition.
 fn handle_main(
+    request: &rouille::Request,
     request_uri: &str,
     ctx: &context::Context,
     relations: &mut areas::Relations<'_>,
@@ -1391,7 +1407,9 @@ fn handle_main(
         yattag::Doc::from_text(&tr("Additional streets")),
         yattag::Doc::from_text(&tr("Area boundary")),
     ]];
-    for relation_name in relations.get_names() {
+    let sort = request.get_param("sort");
+    let order = request.get_param("order");
+    for relation_name in get_main_relation_names(relations, &sort, &order)? {
         let row = handle_main_relation(ctx, relations, &filter_for, &relation_name)
             .context("handle_main_relation() failed")?;
         if !row.is_empty() {
@@ -1416,15 +1434,23 @@ fn handle_main(
 }
 
 /// Determines the HTML title for a given function and relation name.
-fn get_html_title(request_uri: &str) -> String {
+fn get_html_title(
+    relations: &mut areas::Relations<'_>,
+    request_uri: &str,
+) -> anyhow::Result<String> {
     let tokens: Vec<String> = request_uri.split('/').map(|i| i.to_string()).collect();
     let mut function = "";
-    let mut relation_name = "";
+    let mut relation_name = "".to_string();
     if tokens.len() > 3 {
         function = &tokens[2];
-        relation_name = &tokens[3];
+        relation_name = tokens[3].clone();
+        // Only look up known relations, get_relation() would add unknown ones.
+        if relations.get_names().contains(&relation_name) {
+            relation_name = relations.get_relation(&relation_name)?.get_display_name();
+        }
     }
-    match function {
+    let relation_name = relation_name.as_str();
+    Ok(match function {
         "missing-housenumbers" => format!(
             " - {}",
             tr("{0} missing house numbers").replace("{0}", relation_name)
@@ -1433,11 +1459,16 @@ fn get_html_title(request_uri: &str) -> String {
         "street-housenumbers" => format!(" - {} {}", relation_name, tr("existing house numbers")),
         "streets" => format!(" - {} {}", relation_name, tr("existing streets")),
         _ => "".into(),
-    }
+    })
 }
 
 /// Produces the <head> tag and its contents.
-fn write_html_head(ctx: &context::Context, doc: &yattag::Tag, title: &str) -> anyhow::Result<()> {
+fn write_html_head(
+    ctx: &context::Context,
+    doc: &yattag::Tag,
+    request_uri: &str,
+    title: &str,
+) -> anyhow::Result<()> {
     let prefix = ctx.get_ini().get_uri_prefix();
     let head = doc.tag("head", &[]);
     head.stag("meta", &[("charset", "UTF-8")]);
@@ -1450,7 +1481,14 @@ fn write_html_head(ctx: &context::Context, doc: &yattag::Tag, title: &str) -> an
     );
     {
         let title_tag = head.tag("title", &[]);
-        title_tag.text(&format!("{}{}", tr("Where to map?"), title))
+        let site_name = match ctx.get_ini().get_site_name() {
+            Some(value) => format!("{value}: "),
+            None => "".into(),
+        };
+        title_tag.text(&format!("{}{}{}", site_name, tr("Where to map?"), title))
+    }
+    if let Some(canonical) = webframe::get_canonical_relation_uri(ctx, request_uri) {
+        head.stag("link", &[("rel", "canonical"), ("href", &canonical)]);
     }
     head.stag(
         "link",
@@ -1521,6 +1559,26 @@ fn our_application_gpx(
     Ok(webframe::make_response(200_u16, headers, data))
 }
 
+/// Dispatches CSV requests based on their URIs.
+fn our_application_csv(
+    relations: &mut areas::Relations<'_>,
+    request_uri: &str,
+) -> anyhow::Result<rouille::Response> {
+    let content_type = "text/csv; charset=utf-8";
+    let mut headers: webframe::Headers = Vec::new();
+    // assume prefix + "/additional-streets/"
+    let (output, relation_name) =
+        wsgi_additional::additional_streets_view_csv(relations, request_uri)
+            .context("additional_streets_view_csv() failed")?;
+    headers.push((
+        "Content-Disposition".into(),
+        format!(r#"attachment;filename="{relation_name}.csv""#).into(),
+    ));
+    let data = output.as_bytes().to_vec();
+    headers.push(("Content-type".into(), content_type.into()));
+    Ok(webframe::make_response(200_u16, headers, data))
+}
+
 /// Dispatches plain text requests based on their URIs.
 fn our_application_txt(
     ctx: &context::Context,
@@ -1568,12 +1626,6 @@ fn our_application_txt(
                 format!(r#"attachment;filename="{relation_name}.txt""#).into(),
             ));
             data = output.as_bytes().to_vec();
-        } else if request_uri.ends_with("robots.txt") {
-            data = ctx
-                .get_file_system()
-                .read_to_string(&ctx.get_abspath("data/robots.txt"))?
-                .as_bytes()
-                .into();
         } else {
             // assume txt
             let output = missing_housenumbers_view_txt(ctx, relations, request_uri)?;
@@ -1623,16 +1675,57 @@ fn our_application(
 ) -> anyhow::Result<rouille::Response> {
     let language = util::setup_localization(ctx, request.headers());
 
+    // Relation pages have no trailing slash in their canonical form.
+    let url = request.url();
+    if url == format!("{}/readyz", ctx.get_ini().get_uri_prefix()) {
+        return webframe::handle_readyz(ctx);
+    }
+    if let Some(canonical) = webframe::get_canonical_relation_uri(ctx, &url) {
+        if canonical != url {
+            let mut location = canonical;
+            if !request.raw_query_string().is_empty() {
+                location = format!("{location}?{}", request.raw_query_string());
+            }
+            return Ok(webframe::make_response(
+                301_u16,
+                vec![("Location".into(), location.into())],
+                Vec::new(),
+            ));
+        }
+    }
+
     let mut relations = areas::Relations::new(ctx).context("areas::Relations::new() failed")?;
 
     let request_uri = webframe::get_request_uri(request, ctx, &mut relations)
         .context("get_request_uri() failed")?;
+    if let Some(response) = webframe::check_relation_name(ctx, &request_uri) {
+        return Ok(response);
+    }
+    if let Some(response) = webframe::check_update_rate(ctx, &request_uri)? {
+        return Ok(response);
+    }
+    if let Some(response) = webframe::handle_refresh(request, ctx, &relations, &request_uri)? {
+        return Ok(response);
+    }
     let mut ext: String = "".into();
     let tokens: Vec<_> = request_uri.split('.').collect();
     if let Some((last, _elements)) = tokens.split_last() {
         ext = (*last).into();
     }
 
+    let prefix = ctx.get_ini().get_uri_prefix();
+    let negotiable = request_uri.starts_with(&format!("{prefix}/missing-housenumbers/"))
+        || request_uri.starts_with(&format!("{prefix}/additional-housenumbers/"));
+    if negotiable && request_uri.ends_with("/view-result") && webframe::prefers_json(request) {
+        let json_uri = format!("{request_uri}.json");
+        let response = wsgi_json::our_application_json(ctx, &mut relations, &json_uri)?;
+        return Ok(response.with_additional_header("Vary", "Accept"));
+    }
+
+    if request_uri.ends_with("/robots.txt") {
+        return webframe::handle_robots(ctx);
+    }
+
     if ext == "txt" || ext == "chkl" {
         return our_application_txt(ctx, &mut relations, &request_uri);
     }
@@ -1642,7 +1735,11 @@ fn our_application(
             .context("our_application_gpx() failed");
     }
 
-    let prefix = ctx.get_ini().get_uri_prefix();
+    if ext == "csv" {
+        return our_application_csv(&mut relations, &request_uri)
+            .context("our_application_csv() failed");
+    }
+
     if !(request_uri == "/" || request_uri.starts_with(&prefix)) {
         let doc = webframe::handle_404();
         return Ok(webframe::make_response(
@@ -1652,16 +1749,36 @@ fn our_application(
         ));
     }
 
+    if request_uri == format!("{prefix}/debug/config") {
+        return webframe::handle_debug_config(ctx);
+    }
+
+    if request_uri == format!("{prefix}/debug/clear-cache") {
+        return webframe::handle_debug_clear_cache(request, ctx, &mut relations);
+    }
+
+    if request_uri.starts_with(&format!("{prefix}/static/")) && ext == "lst" {
+        return webframe::handle_static_lst(request, ctx, &request_uri);
+    }
+
     if request_uri.starts_with(&format!("{prefix}/static/"))
         || request_uri.ends_with("favicon.ico")
         || request_uri.ends_with("favicon.svg")
     {
         let (output, content_type, mut headers) = webframe::handle_static(ctx, &request_uri)?;
+        let json = content_type.starts_with("application/json");
         headers.push(("Content-type".into(), content_type.into()));
+        if json {
+            // Large stats, allow resuming their download.
+            return Ok(webframe::make_range_response(request, headers, output));
+        }
         return Ok(webframe::make_response(200_u16, headers, output));
     }
 
-    if ext == "json" {
+    if ext == "json"
+        || request_uri == format!("{prefix}/api/relations")
+        || request_uri == format!("{prefix}/api/freshness")
+    {
         return wsgi_json::our_application_json(ctx, &mut relations, &request_uri);
     }
 
@@ -1669,8 +1786,13 @@ fn our_application(
     util::write_html_header(&doc);
     {
         let html = doc.tag("html", &[("lang", &language)]);
-        write_html_head(ctx, &html, &get_html_title(&request_uri))
-            .context("write_html_head() failed")?;
+        write_html_head(
+            ctx,
+            &html,
+            &request_uri,
+            &get_html_title(&mut relations, &request_uri)?,
+        )
+        .context("write_html_head() failed")?;
 
         let body = html.tag("body", &[]);
         let no_such_relation = webframe::check_existing_relation(ctx, &relations, &request_uri)?;
@@ -1685,8 +1807,8 @@ fn our_application(
         } else if request_uri.starts_with(&format!("{prefix}/webhooks/github")) {
             body.append_value(webframe::handle_github_webhook(request, ctx)?.get_value());
         } else {
-            let doc =
-                handle_main(&request_uri, ctx, &mut relations).context("handle_main() failed")?;
+            let doc = handle_main(request, &request_uri, ctx, &mut relations)
+                .context("handle_main() failed")?;
             body.append_value(doc.get_value());
         }
     }
@@ -31,6 +31,7 @@ pub struct TestWsgi {
 }
 
 impl TestWsgi {
+    /// Creates a new wsgi test fixture.
     pub fn new() -> Self {
         let gzip_compress = false;
         let ctx = context::tests::make_test_context().unwrap();
@@ -50,6 +51,7 @@ impl TestWsgi {
         }
     }
 
+    /// Gets the context of the fixture.
     pub fn get_ctx(&mut self) -> &mut context::Context {
         &mut self.ctx
     }
@@ -65,6 +67,7 @@ impl TestWsgi {
         ret
     }
 
+    /// Sets the expected content type of the response.
     pub fn set_content_type(&mut self, content_type: &str) {
         self.content_type = content_type.to_string();
     }
@@ -142,7 +145,7 @@ impl TestWsgi {
     pub fn get_json_for_path(&mut self, path: &str) -> serde_json::Value {
         let prefix = self.ctx.get_ini().get_uri_prefix();
         let abspath = format!("{prefix}{path}");
-        let request = rouille::Request::fake_http("GET", abspath, vec![], vec![]);
+        let request = rouille::Request::fake_http("GET", abspath, self.headers.clone(), vec![]);
         let response = application(&request, &self.ctx);
         let mut data = Vec::new();
         let (mut reader, _size) = response.data.into_reader_and_size();
@@ -262,6 +265,154 @@ fn test_handle_streets_view_query_well_formed() {
     assert_eq!(results.len(), 1);
 }
 
+/// Tests that relation pages refer to their canonical URL.
+#[test]
+fn test_relation_canonical_link() {
+    let mut test_wsgi = TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let template_value = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &template_value),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+
+    let root = test_wsgi.get_dom_for_path("/streets/gazdagret/view-query");
+
+    let results = TestWsgi::find_all(&root, "head/link[@rel='canonical']/@href");
+    assert_eq!(results, ["/osm/streets/gazdagret/view-query"]);
+}
+
+/// Tests that the trailing-slash form of a relation page redirects to the canonical form.
+#[test]
+fn test_relation_trailing_slash_redirect() {
+    let ctx = context::tests::make_test_context().unwrap();
+    let request = rouille::Request::fake_http(
+        "GET",
+        "/osm/missing-housenumbers/gazdagret/view-result/?sort=1",
+        vec![],
+        vec![],
+    );
+
+    let response = application(&request, &ctx);
+
+    assert_eq!(response.status_code, 301);
+    let headers_map: HashMap<_, _> = response.headers.into_iter().collect();
+    assert_eq!(
+        headers_map["Location"],
+        "/osm/missing-housenumbers/gazdagret/view-result?sort=1"
+    );
+}
+
+/// Tests handle_refresh(): POST starts the refresh and the status reports the result.
+#[test]
+fn test_relation_refresh() {
+    let mut test_wsgi = TestWsgi::new();
+    let routes = vec![
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-streets-gazdagret.json",
+        ),
+        context::tests::URLRoute::new(
+            /*url=*/ "https://overpass-api.de/api/interpreter",
+            /*data_path=*/ "",
+            /*result_path=*/ "src/fixtures/network/overpass-housenumbers-gazdagret.json",
+        ),
+    ];
+    let network = context::tests::TestNetwork::new(&routes);
+    let network_rc: Rc<dyn context::Network> = Rc::new(network);
+    test_wsgi.ctx.set_network(network_rc);
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let streets_template = context::tests::TestFileSystem::make_file();
+    let housenumbers_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &streets_template),
+            (
+                "data/street-housenumbers-template.overpassql",
+                &housenumbers_template,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+    let request =
+        rouille::Request::fake_http("POST", "/osm/relations/gazdagret/refresh", vec![], vec![]);
+
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 202);
+    let headers_map: HashMap<_, _> = response.headers.into_iter().collect();
+    assert_eq!(
+        headers_map["Location"],
+        "/osm/relations/gazdagret/refresh/status"
+    );
+    // The test executor runs the refresh synchronously, so it's already done.
+    let value = test_wsgi.get_json_for_path("/relations/gazdagret/refresh/status");
+    assert_eq!(value["status"], "done");
+    assert_eq!(
+        stats::has_sql_mtime(&test_wsgi.ctx, "streets/gazdagret").unwrap(),
+        true
+    );
+    assert_eq!(
+        stats::has_sql_mtime(&test_wsgi.ctx, "housenumbers/gazdagret").unwrap(),
+        true
+    );
+}
+
+/// Tests handle_refresh(): the status reports a failed refresh.
+#[test]
+fn test_relation_refresh_failed() {
+    let mut test_wsgi = TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let streets_template = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("data/streets-template.overpassql", &streets_template),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+    let request =
+        rouille::Request::fake_http("POST", "/osm/relations/gazdagret/refresh", vec![], vec![]);
+
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 202);
+    let value = test_wsgi.get_json_for_path("/relations/gazdagret/refresh/status");
+    assert_eq!(value["status"], "failed");
+}
+
 /// Tests handle_streets(): if the update-result output is well-formed.
 #[test]
 fn test_handle_streets_update_result_well_formed() {
@@ -556,6 +707,72 @@ fn test_per_relation_lints() {
     );
 }
 
+/// Tests the unparseable reference entries page.
+#[test]
+fn test_missing_housenumbers_view_unparseable() {
+    let mut test_wsgi = TestWsgi::new();
+    let mut file_system = context::tests::TestFileSystem::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "refcounty": "0",
+                "refsettlement": "0",
+                "osmrelation": 42,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let ref_file = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_file,
+            ),
+        ],
+    );
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        test_wsgi
+            .ctx
+            .get_abspath("workdir/street-housenumbers-reference-gazdagret.lst"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    test_wsgi.ctx.set_file_system(&file_system_rc);
+    let mtime = test_wsgi.get_ctx().get_time().now_string();
+    {
+        let conn = test_wsgi.get_ctx().get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Tűzkő utca', '1', '');
+             insert into ref_housenumbers (county_code, settlement_code, street, housenumber, comment) values ('0', '0', 'Tűzkő utca', 'telek', '');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', '');"
+        )
+        .unwrap();
+        conn.execute(
+            "insert into mtimes (page, last_modified) values (?1, ?2)",
+            ["housenumbers/gazdagret", &mtime],
+        )
+        .unwrap();
+    }
+    {
+        let mut relations = areas::Relations::new(&test_wsgi.ctx).unwrap();
+        let relation = relations.get_relation("gazdagret").unwrap();
+        relation.write_ref_housenumbers().unwrap();
+    }
+
+    let root = test_wsgi.get_dom_for_path("/missing-housenumbers/gazdagret/view-unparseable");
+
+    // Don't depend on the order of the cells.
+    let mut results = TestWsgi::find_all(&root, "body/table/tr/td");
+    results.sort();
+    assert_eq!(results, ["Tűzkő utca", "telek"]);
+}
+
 /// Tests the per-relation lints page, the out-of-range case.
 #[test]
 fn test_per_relation_lints_out_of_range() {
@@ -2472,6 +2689,153 @@ fn test_main_filter_for_incomplete() {
     assert_eq!(results.len(), 2);
 }
 
+/// Tests handle_main(): ?sort=percent&order=asc puts the lowest-coverage relation first.
+#[test]
+fn test_main_sort_percent() {
+    let mut test_wsgi = TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 42,
+            },
+            "gazdagret": {
+                "osmrelation": 43,
+            },
+            "ujbuda": {
+                "osmrelation": 44,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+    {
+        let conn = test_wsgi.ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_housenumber_coverages (relation_name, coverage, last_modified) values ('budafok', '50.00', '0');
+             insert into osm_housenumber_coverages (relation_name, coverage, last_modified) values ('ujbuda', '10.00', '0');"
+        )
+        .unwrap();
+    }
+
+    let root = test_wsgi.get_dom_for_path("/?sort=percent&order=asc");
+
+    // Node sets are unordered, so look up the rows one by one, the first row is the header.
+    let results: Vec<String> = (2..=4)
+        .flat_map(|row| TestWsgi::find_all(&root, &format!("body/table/tr[{row}]/td[1]")))
+        .collect();
+    // gazdagret has no percent, so it's the last.
+    assert_eq!(results, ["ujbuda", "budafok", "gazdagret"]);
+}
+
+/// Tests handle_main(): relations with old OSM data get a stale badge.
+#[test]
+fn test_main_stale() {
+    let mut test_wsgi = TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 42,
+            },
+            "gazdagret": {
+                "osmrelation": 43,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+    {
+        let conn = test_wsgi.ctx.get_database_connection().unwrap();
+        // budafok is from 1970, gazdagret is fresh.
+        conn.execute_batch(
+            "insert into mtimes (page, last_modified) values ('housenumbers/budafok', '0');",
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&test_wsgi.ctx, "housenumbers/gazdagret").unwrap();
+
+    let root = test_wsgi.get_dom_for_path("/");
+
+    let results = TestWsgi::find_all(&root, "body/table/tr/td/span[@class='badge-stale']");
+    assert_eq!(results.len(), 1);
+}
+
+/// Tests handle_main(): relations marked as done get a completed badge instead of a stale one.
+#[test]
+fn test_main_done() {
+    let mut test_wsgi = TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "budafok": {
+                "osmrelation": 42,
+                "done": true,
+            },
+            "gazdagret": {
+                "osmrelation": 43,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+    {
+        let conn = test_wsgi.ctx.get_database_connection().unwrap();
+        // budafok is from 1970, but that's expected, as it's done.
+        conn.execute_batch(
+            "insert into mtimes (page, last_modified) values ('housenumbers/budafok', '0');",
+        )
+        .unwrap();
+    }
+
+    let root = test_wsgi.get_dom_for_path("/");
+
+    let results = TestWsgi::find_all(&root, "body/table/tr/td/span[@class='badge-done']");
+    assert_eq!(results, ["completed"]);
+    let results = TestWsgi::find_all(&root, "body/table/tr/td/span[@class='badge-stale']");
+    assert_eq!(results.is_empty(), true);
+}
+
+/// Tests write_html_head(): the title is unchanged without a site name.
+#[test]
+fn test_main_no_site_name() {
+    let mut test_wsgi = TestWsgi::new();
+
+    let root = test_wsgi.get_dom_for_path("/");
+
+    let results = TestWsgi::find_all(&root, "head/title");
+    assert_eq!(results, ["Where to map?"]);
+    let results = TestWsgi::find_all(&root, "body/div[@id='toolbar']/strong");
+    assert!(results.is_empty());
+}
+
+/// Tests write_html_head(): the configured site name is in the title.
+#[test]
+fn test_main_site_name() {
+    let mut test_wsgi = TestWsgi::new();
+    context::tests::set_test_ini_extra(&mut test_wsgi.ctx, "site_name = 'Budapest maps'\n")
+        .unwrap();
+
+    let root = test_wsgi.get_dom_for_path("/");
+
+    let results = TestWsgi::find_all(&root, "head/title");
+    assert_eq!(results, ["Budapest maps: Where to map?"]);
+    let results = TestWsgi::find_all(&root, "body/div[@id='toolbar']/strong");
+    assert_eq!(results, ["Budapest maps"]);
+}
+
 /// Tests handle_main(): if the /osm/filter-for/everything output is well-formed.
 #[test]
 fn test_main_filter_for_everything_well_formed() {
@@ -2743,6 +3107,133 @@ fn test_static_css() {
     assert_eq!(result.ends_with('}'), true);
 }
 
+/// Tests /osm/static/: the json case advertises range support.
+#[test]
+fn test_static_json() {
+    let test_wsgi = TestWsgi::new();
+    let prefix = test_wsgi.ctx.get_ini().get_uri_prefix();
+    let abspath = format!("{prefix}/static/stats-empty.json");
+    let request = rouille::Request::fake_http("GET", abspath, vec![], vec![]);
+
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 200);
+    let headers: HashMap<_, _> = response.headers.clone().into_iter().collect();
+    assert_eq!(headers["Accept-Ranges"], "bytes");
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    let expected =
+        std::fs::read(test_wsgi.ctx.get_abspath("workdir/stats/stats-empty.json")).unwrap();
+    assert_eq!(data, expected);
+}
+
+/// Tests /osm/static/: the json case, a ranged request gets partial content, uncompressed.
+#[test]
+fn test_static_json_range() {
+    let test_wsgi = TestWsgi::new();
+    let prefix = test_wsgi.ctx.get_ini().get_uri_prefix();
+    let abspath = format!("{prefix}/static/stats-empty.json");
+    let request = rouille::Request::fake_http(
+        "GET",
+        abspath,
+        vec![
+            ("Range".into(), "bytes=0-0".into()),
+            ("Accept-Encoding".into(), "gzip, deflate".into()),
+        ],
+        vec![],
+    );
+
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 206);
+    let headers: HashMap<_, _> = response.headers.clone().into_iter().collect();
+    let expected =
+        std::fs::read(test_wsgi.ctx.get_abspath("workdir/stats/stats-empty.json")).unwrap();
+    assert_eq!(
+        headers["Content-Range"],
+        format!("bytes 0-0/{}", expected.len())
+    );
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"{");
+}
+
+/// Registers a gzipped reference house number list for gazdagret, returns its uncompressed form.
+fn set_ref_housenumbers_gz(test_wsgi: &mut TestWsgi) -> Vec<u8> {
+    let plain = b"Hamzsab\xc3\xa9gi \xc3\xbat\t1\n".to_vec();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&plain).unwrap();
+    let gz_value = context::tests::TestFileSystem::make_file();
+    gz_value
+        .borrow_mut()
+        .write_all(&encoder.finish().unwrap())
+        .unwrap();
+    let path = "workdir/street-housenumbers-reference-gazdagret.lst.gz";
+    let files = context::tests::TestFileSystem::make_files(&test_wsgi.ctx, &[(path, &gz_value)]);
+    let mut file_system = context::tests::TestFileSystem::new();
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        test_wsgi.ctx.get_abspath(path),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    test_wsgi.ctx.set_file_system(&file_system_rc);
+    plain
+}
+
+/// Tests /osm/static/: a gzipped reference list is passed through to a client accepting gzip.
+#[test]
+fn test_static_lst_gz() {
+    let mut test_wsgi = TestWsgi::new();
+    let plain = set_ref_housenumbers_gz(&mut test_wsgi);
+    let prefix = test_wsgi.ctx.get_ini().get_uri_prefix();
+    let abspath = format!("{prefix}/static/street-housenumbers-reference-gazdagret.lst");
+    let request = rouille::Request::fake_http(
+        "GET",
+        abspath,
+        vec![("Accept-Encoding".into(), "gzip, deflate".into())],
+        vec![],
+    );
+
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 200);
+    let headers: HashMap<_, _> = response.headers.clone().into_iter().collect();
+    assert_eq!(headers["Content-Encoding"], "gzip");
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    let mut gz = flate2::read::GzDecoder::new(data.as_slice());
+    let mut actual = Vec::new();
+    gz.read_to_end(&mut actual).unwrap();
+    assert_eq!(actual, plain);
+}
+
+/// Tests /osm/static/: a gzipped reference list is decompressed for a client not accepting gzip.
+#[test]
+fn test_static_lst_gz_decompress() {
+    let mut test_wsgi = TestWsgi::new();
+    let plain = set_ref_housenumbers_gz(&mut test_wsgi);
+    let prefix = test_wsgi.ctx.get_ini().get_uri_prefix();
+    let abspath = format!("{prefix}/static/street-housenumbers-reference-gazdagret.lst");
+    let request = rouille::Request::fake_http("GET", abspath, vec![], vec![]);
+
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 200);
+    let headers: HashMap<_, _> = response.headers.clone().into_iter().collect();
+    assert_eq!(headers.contains_key("Content-Encoding"), false);
+    assert_eq!(headers["Content-type"], "text/plain; charset=utf-8");
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    assert_eq!(data, plain);
+}
+
 /// Tests /osm/static/: the plain text case.
 #[test]
 fn test_static_text() {
@@ -2766,6 +3257,19 @@ fn test_static_text() {
     assert_eq!(result, "User-agent: *\n");
 }
 
+/// Tests /robots.txt: the default when the configured file doesn't exist.
+#[test]
+fn test_robots_default() {
+    let mut test_wsgi = TestWsgi::new();
+    context::tests::set_test_ini_extra(&mut test_wsgi.ctx, "robots_path = 'data/no-robots.txt'\n")
+        .unwrap();
+
+    let result = test_wsgi.get_txt_for_path("/robots.txt");
+
+    assert_eq!(result.starts_with("User-agent: *\n"), true);
+    assert_eq!(result.contains("Disallow: /*update-result$\n"), true);
+}
+
 /// Tests handle_stats_cityprogress(): if the output is well-formed.
 #[test]
 fn test_handle_stats_cityprogress_well_formed() {
@@ -2786,9 +3290,11 @@ fn test_handle_stats_cityprogress_well_formed() {
 
     let root = test_wsgi.get_dom_for_path("/housenumber-stats/whole-country/cityprogress");
 
-    let results = TestWsgi::find_all(&root, "body/table/tr");
-    // header; also budapest_11/budapest_12 are both in ref and osm
-    assert_eq!(results.len(), 3);
+    let results = TestWsgi::find_all(&root, "body/table/thead/tr");
+    assert_eq!(results.len(), 1);
+    let results = TestWsgi::find_all(&root, "body/table/tbody/tr");
+    // budapest_11/budapest_12 are both in ref and osm
+    assert_eq!(results.len(), 2);
 }
 
 /// Tests handle_stats_zipprogress(): if the output is well-formed.
@@ -2811,9 +3317,11 @@ fn test_handle_stats_zipprogress_well_formed() {
 
     let root = test_wsgi.get_dom_for_path("/housenumber-stats/whole-country/zipprogress");
 
-    let results = TestWsgi::find_all(&root, "body/table/tr");
-    // header; also 1111/1121 is both in ref and osm
-    assert_eq!(results.len(), 3);
+    let results = TestWsgi::find_all(&root, "body/table/thead/tr");
+    assert_eq!(results.len(), 1);
+    let results = TestWsgi::find_all(&root, "body/table/tbody/tr");
+    // 1111/1121 is both in ref and osm
+    assert_eq!(results.len(), 2);
 }
 
 /// Tests handle_invalid_refstreets().
@@ -2953,6 +3461,205 @@ fn test_not_found_well_formed() {
     assert_eq!(results.is_empty(), false);
 }
 
+/// Tests /osm/debug/config: not found by default.
+#[test]
+fn test_debug_config_disabled() {
+    let mut test_wsgi = TestWsgi::new();
+    test_wsgi.expected_status = 404;
+
+    let root = test_wsgi.get_dom_for_path("/debug/config");
+
+    let results = TestWsgi::find_all(&root, "body/h1");
+    assert_eq!(results.is_empty(), false);
+}
+
+/// Tests /osm/debug/config: renders the keys with credentials redacted, when enabled.
+#[test]
+fn test_debug_config() {
+    let mut test_wsgi = TestWsgi::new();
+    context::tests::set_test_ini_extra(
+        test_wsgi.get_ctx(),
+        "debug_enabled = 'True'\nuser_agent = 'osm-gimmisn-test'\n",
+    )
+    .unwrap();
+
+    let root = test_wsgi.get_dom_for_path("/debug/config");
+
+    // Node sets are unordered, so look up the key and value of each row positionally.
+    let row_count = TestWsgi::find_all(&root, "body/table/tr").len();
+    let rows: Vec<(String, String)> = (1..=row_count)
+        .map(|row| {
+            let key = TestWsgi::find_all(&root, &format!("body/table/tr[{row}]/td[1]"));
+            let value = TestWsgi::find_all(&root, &format!("body/table/tr[{row}]/td[2]"));
+            (key.concat(), value.concat())
+        })
+        .collect();
+    assert_eq!(
+        rows.contains(&("debug_enabled".to_string(), "True".to_string())),
+        true
+    );
+    assert_eq!(
+        rows.contains(&("user_agent".to_string(), "osm-gimmisn-test".to_string())),
+        true
+    );
+}
+
+/// Tests /osm/debug/clear-cache: cached output is removed, fetched data and coverages are kept.
+#[test]
+fn test_debug_clear_cache() {
+    let mut test_wsgi = TestWsgi::new();
+    context::tests::set_test_ini_extra(test_wsgi.get_ctx(), "debug_enabled = 'True'\n").unwrap();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+            "budafok": {
+                "osmrelation": 43,
+            },
+            "ujbuda": {
+                "osmrelation": 44,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let additional_cache = context::tests::TestFileSystem::make_file();
+    let ref_housenumbers = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        &test_wsgi.ctx,
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/additional-cache-gazdagret.json", &additional_cache),
+            (
+                "workdir/street-housenumbers-reference-gazdagret.lst",
+                &ref_housenumbers,
+            ),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.ctx.set_file_system(&file_system);
+    {
+        let conn = test_wsgi.ctx.get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into missing_housenumbers_cache (relation, json) values ('budafok', '{}');
+             insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', '');
+             insert into osm_housenumber_coverages (relation_name, coverage, last_modified) values ('gazdagret', '50.00', '0');",
+        )
+        .unwrap();
+    }
+    stats::set_sql_mtime(&test_wsgi.ctx, "missing-housenumbers-cache/budafok").unwrap();
+    stats::set_sql_mtime(&test_wsgi.ctx, "streets/gazdagret").unwrap();
+
+    // GET is not allowed.
+    let request = rouille::Request::fake_http("GET", "/osm/debug/clear-cache", vec![], vec![]);
+    let response = application(&request, &test_wsgi.ctx);
+    assert_eq!(response.status_code, 405);
+
+    let request = rouille::Request::fake_http("POST", "/osm/debug/clear-cache", vec![], vec![]);
+    let response = application(&request, &test_wsgi.ctx);
+
+    assert_eq!(response.status_code, 200);
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&data).unwrap();
+    assert_eq!(value["removed"], 2);
+    // Cached output is gone.
+    let ctx = &test_wsgi.ctx;
+    assert_eq!(
+        file_system.path_exists(&ctx.get_abspath("workdir/additional-cache-gazdagret.json")),
+        false
+    );
+    assert_eq!(
+        stats::has_sql_mtime(ctx, "missing-housenumbers-cache/budafok").unwrap(),
+        false
+    );
+    // Fetched data and coverages are kept.
+    assert_eq!(
+        file_system
+            .path_exists(&ctx.get_abspath("workdir/street-housenumbers-reference-gazdagret.lst")),
+        true
+    );
+    assert_eq!(
+        stats::has_sql_mtime(ctx, "streets/gazdagret").unwrap(),
+        true
+    );
+    let mut relations = areas::Relations::new(ctx).unwrap();
+    let relation = relations.get_relation("gazdagret").unwrap();
+    assert_eq!(relation.get_osm_housenumber_coverage().unwrap(), "50.00");
+    let conn = ctx.get_database_connection().unwrap();
+    let count: i64 = conn
+        .query_row("select count(*) from osm_streets", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+/// Tests the missing house numbers page: Accept: application/json gives the JSON of the same URL.
+#[test]
+fn test_missing_housenumbers_view_result_accept_json() {
+    let mut test_wsgi = TestWsgi::new();
+    let mut file_system = context::tests::TestFileSystem::new();
+    let json_cache = context::tests::TestFileSystem::make_file();
+    let files = context::tests::TestFileSystem::make_files(
+        test_wsgi.get_ctx(),
+        &[("workdir/cache-budafok.json", &json_cache)],
+    );
+    file_system.set_files(&files);
+    let mut mtimes: HashMap<String, Rc<RefCell<time::OffsetDateTime>>> = HashMap::new();
+    mtimes.insert(
+        test_wsgi
+            .get_ctx()
+            .get_abspath("workdir/cache-budafok.json"),
+        Rc::new(RefCell::new(time::OffsetDateTime::UNIX_EPOCH)),
+    );
+    file_system.set_mtimes(&mtimes);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    test_wsgi.get_ctx().set_file_system(&file_system_rc);
+    {
+        let conn = test_wsgi.get_ctx().get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('budafok', '458338075', 'Vöröskúti határsor', '', '', '', '', '');"
+        )
+        .unwrap();
+    }
+    test_wsgi
+        .headers
+        .push(("Accept".into(), "application/json, text/html;q=0.9".into()));
+
+    let result = test_wsgi.get_json_for_path("/missing-housenumbers/budafok/view-result");
+
+    let missing_housenumbers: areas::MissingHousenumbers = serde_json::from_value(result).unwrap();
+    assert_eq!(missing_housenumbers.ongoing_streets.len(), 1);
+}
+
+/// Tests prefers_json(): HTML is the default.
+#[test]
+fn test_prefers_json() {
+    let make_request = |accept: &str| {
+        rouille::Request::fake_http(
+            "GET",
+            "/",
+            vec![("Accept".to_string(), accept.to_string())],
+            vec![],
+        )
+    };
+    assert_eq!(
+        webframe::prefers_json(&make_request("application/json")),
+        true
+    );
+    assert_eq!(webframe::prefers_json(&make_request("*/*")), false);
+    assert_eq!(
+        webframe::prefers_json(&make_request("text/html, application/json")),
+        false
+    );
+    assert_eq!(
+        webframe::prefers_json(&make_request("text/html;q=0.5, application/json")),
+        true
+    );
+    let request = rouille::Request::fake_http("GET", "/", vec![], vec![]);
+    assert_eq!(webframe::prefers_json(&request), false);
+}
+
 /// Tests gzip compress case.
 #[test]
 fn test_compress() {
@@ -163,6 +163,35 @@ pub fn additional_streets_view_txt(
     Ok((output, relation_name.into()))
 }
 
+/// Expected request_uri: e.g. /osm/additional-streets/ujbuda/view-result.csv.
+pub fn additional_streets_view_csv(
+    relations: &mut areas::Relations<'_>,
+    request_uri: &str,
+) -> anyhow::Result<(String, String)> {
+    let mut tokens = request_uri.split('/');
+    tokens.next_back();
+    let relation_name = tokens.next_back().context("next_back() failed")?;
+    let relation = relations
+        .get_relation(relation_name)
+        .context("get_relation() failed")?;
+    let mut streets = relation.get_additional_streets(/*sorted_result=*/ true)?;
+    streets.sort_by_key(|street| util::get_sort_key(street.get_osm_name()));
+
+    // The OSM streets data has no coordinates, provide the OSM object instead, so GIS tools can
+    // look up the geometry.
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["name", "osm_type", "osm_id"])?;
+    for street in streets {
+        writer.write_record([
+            street.get_osm_name(),
+            street.get_osm_type(),
+            &street.get_osm_id().to_string(),
+        ])?;
+    }
+    let output = String::from_utf8(writer.into_inner()?)?;
+    Ok((output, relation_name.into()))
+}
+
 /// Expected request_uri: e.g. /osm/additional-streets/budapest_11/view-result.
 pub fn additional_streets_view_result(
     ctx: &context::Context,
@@ -249,6 +278,17 @@ pub fn additional_streets_view_result(
                 a.text(&tr("GPX format"));
             }
             p.stag("br", &[]);
+            {
+                let a = p.tag(
+                    "a",
+                    &[(
+                        "href",
+                        &format!("{prefix}/additional-streets/{relation_name}/view-result.csv"),
+                    )],
+                );
+                a.text(&tr("CSV format"));
+            }
+            p.stag("br", &[]);
             {
                 let a = doc.tag(
                     "a",
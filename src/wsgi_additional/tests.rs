@@ -12,6 +12,7 @@
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::Read;
 use std::rc::Rc;
 
 use crate::areas;
@@ -101,6 +102,65 @@ fn test_streets_view_result_txt() {
     assert_eq!(result, "Only In OSM utca\nSecond Only In OSM utca\n");
 }
 
+/// Tests additional streets: the csv output.
+#[test]
+fn test_streets_view_result_csv() {
+    let mut test_wsgi = wsgi::tests::TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 42,
+            },
+        },
+        "relation-gazdagret.yaml": {
+            "refcounty": "01",
+            "refsettlement": "011",
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        test_wsgi.get_ctx(),
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.get_ctx().set_file_system(&file_system);
+    {
+        let conn = test_wsgi.get_ctx().get_database_connection().unwrap();
+        conn.execute(
+            r#"insert into ref_streets (county_code, settlement_code, street) values (?1, ?2, ?3)"#,
+            ["01", "011", "Tűzkő utca"],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '1', 'Tűzkő utca', '', '', '', '', 'way');
+            insert into osm_streets (relation, osm_id, name, highway, service, surface, leisure, osm_type) values ('gazdagret', '2', 'Only In OSM utca', '', '', '', '', 'way');
+            insert into mtimes (page, last_modified) values ('streets/gazdagret', '0');",
+        )
+        .unwrap();
+    }
+    let prefix = test_wsgi.get_ctx().get_ini().get_uri_prefix();
+    let abspath = format!("{prefix}/additional-streets/gazdagret/view-result.csv");
+    let request = rouille::Request::fake_http("GET", abspath, vec![], vec![]);
+
+    let response = wsgi::application(&request, test_wsgi.get_ctx());
+
+    assert_eq!(response.status_code, 200);
+    let headers: HashMap<_, _> = response.headers.clone().into_iter().collect();
+    assert_eq!(headers["Content-type"], "text/csv; charset=utf-8");
+    assert_eq!(
+        headers["Content-Disposition"],
+        r#"attachment;filename="gazdagret.csv""#
+    );
+    let mut data = Vec::new();
+    let (mut reader, _size) = response.data.into_reader_and_size();
+    reader.read_to_end(&mut data).unwrap();
+    let output = String::from_utf8(data).unwrap();
+    let mut lines = output.lines();
+    assert_eq!(lines.next().unwrap(), "name,osm_type,osm_id");
+    assert_eq!(lines.next().unwrap(), "Only In OSM utca,way,2");
+    assert_eq!(lines.next(), None);
+}
+
 /// Tests additional streets: the gpx output.
 #[test]
 fn test_streets_view_result_gpx() {
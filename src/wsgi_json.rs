@@ -14,6 +14,7 @@ use crate::areas;
 use crate::cache;
 use crate::context;
 use crate::overpass_query;
+use crate::stats;
 use crate::webframe;
 use anyhow::Context;
 use std::collections::HashMap;
@@ -93,6 +94,23 @@ fn missing_housenumbers_view_result_json(
     cache::get_missing_housenumbers_json(&mut relation)
 }
 
+/// Expected request_uri: e.g. /osm/missing-housenumbers/ormezo/coverage-history.json.
+fn missing_housenumbers_coverage_history_json(
+    relations: &mut areas::Relations<'_>,
+    request_uri: &str,
+) -> anyhow::Result<String> {
+    let mut tokens = request_uri.split('/');
+    tokens.next_back();
+    let relation_name = tokens.next_back().context("short tokens")?;
+    let relation = relations.get_relation(relation_name)?;
+    let mut ret: Vec<serde_json::Value> = Vec::new();
+    for (date, coverage) in relation.get_osm_housenumber_coverage_history()? {
+        let percent: f64 = coverage.parse()?;
+        ret.push(serde_json::json!({"date": date, "percent": percent}));
+    }
+    Ok(serde_json::to_string(&ret)?)
+}
+
 /// Expected request_uri: e.g. /osm/additional-housenumbers/ormezo/view-result.json.
 fn additional_housenumbers_view_result_json(
     relations: &mut areas::Relations<'_>,
@@ -105,6 +123,88 @@ fn additional_housenumbers_view_result_json(
     cache::get_additional_housenumbers_json(&mut relation)
 }
 
+/// Decides if a relation has full coverage, considering both streets and house numbers, depending
+/// on what is checked for the relation.
+fn is_relation_complete(relation: &areas::Relation<'_>) -> anyhow::Result<bool> {
+    let streets = relation.get_config().should_check_missing_streets();
+    let mut complete = true;
+    if streets != "only" {
+        let mut percent = 0_f64;
+        if relation.has_osm_housenumber_coverage()? {
+            let string = relation.get_osm_housenumber_coverage()?;
+            percent = string.parse::<f64>().context("parse to f64 failed")?;
+        }
+        complete &= percent >= 100_f64;
+    }
+    if streets != "no" {
+        let mut percent = 0_f64;
+        if relation.has_osm_street_coverage()? {
+            let string = relation.get_osm_street_coverage()?;
+            percent = string.parse::<f64>().context("parse to f64 failed")?;
+        }
+        complete &= percent >= 100_f64;
+    }
+    Ok(complete)
+}
+
+/// Expected request_uri: e.g. /osm/api/relations.
+fn relations_json(relations: &mut areas::Relations<'_>) -> anyhow::Result<String> {
+    let mut ret: Vec<serde_json::Value> = Vec::new();
+    for relation_name in relations.get_active_names()? {
+        let relation = relations.get_relation(&relation_name)?;
+        let config = relation.get_config();
+        ret.push(serde_json::json!({
+            "name": relation_name,
+            "osmrelation": config.get_osmrelation(),
+            "refcounty": config.get_refcounty(),
+            "refsettlement": config.get_refsettlement(),
+            "complete": is_relation_complete(&relation)?,
+            "stale": relation.is_stale()?,
+        }));
+    }
+    Ok(serde_json::to_string(&ret)?)
+}
+
+/// Formats an optional mtime as an ISO timestamp, or null.
+fn format_freshness_mtime(
+    mtime: Option<time::OffsetDateTime>,
+) -> anyhow::Result<serde_json::Value> {
+    Ok(match mtime {
+        Some(value) => {
+            serde_json::Value::String(value.format(&time::format_description::well_known::Rfc3339)?)
+        }
+        None => serde_json::Value::Null,
+    })
+}
+
+/// Expected request_uri: e.g. /osm/api/freshness.
+fn freshness_json(
+    ctx: &context::Context,
+    relations: &mut areas::Relations<'_>,
+) -> anyhow::Result<String> {
+    let mut ret: Vec<serde_json::Value> = Vec::new();
+    for relation_name in relations.get_active_names()? {
+        let relation = relations.get_relation(&relation_name)?;
+        let page = format!("housenumbers/{relation_name}");
+        let housenumbers_mtime = if stats::has_sql_mtime(ctx, &page)? {
+            Some(stats::get_sql_mtime(ctx, &page)?)
+        } else {
+            None
+        };
+        let percent_mtime = if relation.has_osm_housenumber_coverage()? {
+            Some(relation.get_osm_housenumber_coverage_mtime()?)
+        } else {
+            None
+        };
+        ret.push(serde_json::json!({
+            "name": relation_name,
+            "housenumbers_mtime": format_freshness_mtime(housenumbers_mtime)?,
+            "percent_mtime": format_freshness_mtime(percent_mtime)?,
+        }));
+    }
+    Ok(serde_json::to_string(&ret)?)
+}
+
 /// Dispatches json requests based on their URIs.
 pub fn our_application_json(
     ctx: &context::Context,
@@ -121,10 +221,16 @@ pub fn our_application_json(
     } else if request_uri.starts_with(&format!("{prefix}/missing-housenumbers/")) {
         if request_uri.ends_with("/update-result.json") {
             output = missing_housenumbers_update_result_json(relations, request_uri)?;
+        } else if request_uri.ends_with("/coverage-history.json") {
+            output = missing_housenumbers_coverage_history_json(relations, request_uri)?;
         } else {
             // Assume view-result.json.
             output = missing_housenumbers_view_result_json(relations, request_uri)?;
         }
+    } else if request_uri == format!("{prefix}/api/relations") {
+        output = relations_json(relations)?;
+    } else if request_uri == format!("{prefix}/api/freshness") {
+        output = freshness_json(ctx, relations)?;
     } else if request_uri
         == format!("{prefix}/lints/whole-country/invalid-addr-cities/update-result.json")
     {
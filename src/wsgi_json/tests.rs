@@ -366,3 +366,146 @@ fn test_additional_housenumbers_view_result_json() {
     let additional_housenumbers: util::NumberedStreets = serde_json::from_value(result).unwrap();
     assert_eq!(additional_housenumbers.len(), 0);
 }
+
+/// Tests relations_json().
+#[test]
+fn test_relations_json() {
+    let mut test_wsgi = wsgi::tests::TestWsgi::new();
+    let mut file_system = context::tests::TestFileSystem::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+                "refcounty": "01",
+                "refsettlement": "011",
+            },
+            "budafok": {
+                "osmrelation": 2702687,
+                "refcounty": "01",
+                "refsettlement": "022",
+            },
+            "ujbuda": {
+                "osmrelation": 2700869,
+                "refcounty": "01",
+                "refsettlement": "011",
+                "inactive": true,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        test_wsgi.get_ctx(),
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    file_system.set_files(&files);
+    let file_system_rc: Rc<dyn context::FileSystem> = Rc::new(file_system);
+    test_wsgi.get_ctx().set_file_system(&file_system_rc);
+    {
+        let conn = test_wsgi.get_ctx().get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into osm_housenumber_coverages (relation_name, coverage, last_modified) values ('gazdagret', '100.00', '0');
+             insert into osm_street_coverages (relation_name, coverage, last_modified) values ('gazdagret', '100.00', '0');
+             insert into mtimes (page, last_modified) values ('housenumbers/budafok', '0');"
+        )
+        .unwrap();
+    }
+
+    let root = test_wsgi.get_json_for_path("/api/relations");
+
+    // ujbuda is inactive.
+    let relations = root.as_array().unwrap();
+    assert_eq!(relations.len(), 2);
+    // Sorted by name.
+    assert_eq!(relations[0]["name"], "budafok");
+    assert_eq!(relations[0]["osmrelation"], 2702687);
+    assert_eq!(relations[0]["refcounty"], "01");
+    assert_eq!(relations[0]["refsettlement"], "022");
+    assert_eq!(relations[0]["complete"], false);
+    // The house numbers are from 1970.
+    assert_eq!(relations[0]["stale"], true);
+    assert_eq!(relations[1]["name"], "gazdagret");
+    assert_eq!(relations[1]["complete"], true);
+    // No house numbers yet.
+    assert_eq!(relations[1]["stale"], false);
+}
+
+/// Tests freshness_json().
+#[test]
+fn test_freshness_json() {
+    let mut test_wsgi = wsgi::tests::TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+            "budafok": {
+                "osmrelation": 2702687,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let files = context::tests::TestFileSystem::make_files(
+        test_wsgi.get_ctx(),
+        &[("data/yamls.cache", &yamls_cache_value)],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.get_ctx().set_file_system(&file_system);
+    {
+        let conn = test_wsgi.get_ctx().get_database_connection().unwrap();
+        conn.execute_batch(
+            "insert into mtimes (page, last_modified) values ('housenumbers/gazdagret', '1589068800000000000');
+             insert into osm_housenumber_coverages (relation_name, coverage, last_modified) values ('gazdagret', '50.00', '1589072400000000000');"
+        )
+        .unwrap();
+    }
+
+    let root = test_wsgi.get_json_for_path("/api/freshness");
+
+    let relations = root.as_array().unwrap();
+    assert_eq!(relations.len(), 2);
+    // Sorted by name, budafok was never updated.
+    assert_eq!(relations[0]["name"], "budafok");
+    assert_eq!(relations[0]["housenumbers_mtime"], serde_json::Value::Null);
+    assert_eq!(relations[0]["percent_mtime"], serde_json::Value::Null);
+    assert_eq!(relations[1]["name"], "gazdagret");
+    assert_eq!(relations[1]["housenumbers_mtime"], "2020-05-10T00:00:00Z");
+    assert_eq!(relations[1]["percent_mtime"], "2020-05-10T01:00:00Z");
+}
+
+/// Tests missing_housenumbers_coverage_history_json().
+#[test]
+fn test_missing_housenumbers_coverage_history_json() {
+    let mut test_wsgi = wsgi::tests::TestWsgi::new();
+    let yamls_cache = serde_json::json!({
+        "relations.yaml": {
+            "gazdagret": {
+                "osmrelation": 2713748,
+            },
+        },
+    });
+    let yamls_cache_value = context::tests::TestFileSystem::write_json_to_file(&yamls_cache);
+    let percent_log = context::tests::TestFileSystem::make_file();
+    percent_log
+        .borrow_mut()
+        .write_all(b"2020-05-11\t50.00\n2020-05-10\t42.50\n")
+        .unwrap();
+    let files = context::tests::TestFileSystem::make_files(
+        test_wsgi.get_ctx(),
+        &[
+            ("data/yamls.cache", &yamls_cache_value),
+            ("workdir/gazdagret.percent.log", &percent_log),
+        ],
+    );
+    let file_system = context::tests::TestFileSystem::from_files(&files);
+    test_wsgi.get_ctx().set_file_system(&file_system);
+
+    let root = test_wsgi.get_json_for_path("/missing-housenumbers/gazdagret/coverage-history.json");
+
+    assert_eq!(
+        root,
+        serde_json::json!([
+            {"date": "2020-05-10", "percent": 42.5},
+            {"date": "2020-05-11", "percent": 50.0},
+        ])
+    );
+}
@@ -48,6 +48,11 @@ impl Doc {
         self.value.borrow_mut().push_str(&value)
     }
 
+    /// Appends an already rendered document to the value.
+    pub fn append_doc(&self, other: &Doc) {
+        self.append_value(other.get_value())
+    }
+
     /// Starts a new tag.
     pub fn tag(&self, name: &str, attrs: &[(&str, &str)]) -> Tag {
         Tag::new(&self.value, name, attrs)
@@ -63,6 +68,27 @@ impl Doc {
         let encoded = html_escape::encode_safe(text).to_string();
         self.append_value(encoded);
     }
+
+    /// Appends a sortable table: headers are plain text, which gets escaped, cells are Docs.
+    pub fn table(&self, headers: &[&str], rows: &[Vec<Doc>]) {
+        let table = self.tag("table", &[("class", "sortable")]);
+        {
+            let thead = table.tag("thead", &[]);
+            let tr = thead.tag("tr", &[]);
+            for header in headers {
+                let th = tr.tag("th", &[]);
+                th.text(header);
+            }
+        }
+        let tbody = table.tag("tbody", &[]);
+        for row in rows {
+            let tr = tbody.tag("tr", &[]);
+            for cell in row {
+                let td = tr.tag("td", &[]);
+                td.append_value(cell.get_value());
+            }
+        }
+    }
 }
 
 /// HtmlTable is a matrix (rows, then cols) of Doc instances.
@@ -123,6 +149,11 @@ impl Tag {
     pub fn append_value(&self, value: String) {
         self.value.borrow_mut().push_str(&value)
     }
+
+    /// Appends an already rendered document inside a tag.
+    pub fn append_doc(&self, other: &Doc) {
+        self.append_value(other.get_value())
+    }
 }
 
 impl Drop for Tag {
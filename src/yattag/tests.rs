@@ -25,3 +25,32 @@ fn test_escape() {
         r#"<a href="https://www.example.com/&quot;x">here&gt;y</a>"#
     );
 }
+
+/// Tests Doc::table().
+#[test]
+fn test_table() {
+    let doc = Doc::new();
+    let rows = vec![vec![Doc::from_text("<b>"), Doc::from_text("42")]];
+
+    doc.table(&["Name", "Count & more"], &rows);
+
+    assert_eq!(
+        doc.get_value(),
+        r#"<table class="sortable"><thead><tr><th>Name</th><th>Count &amp; more</th></tr></thead><tbody><tr><td>&lt;b&gt;</td><td>42</td></tr></tbody></table>"#
+    );
+}
+
+/// Tests Doc::append_doc().
+#[test]
+fn test_append_doc() {
+    let doc = Doc::from_text("a<");
+    let other = Doc::new();
+    {
+        let b = other.tag("b", &[]);
+        b.text("c");
+    }
+
+    doc.append_doc(&other);
+
+    assert_eq!(doc.get_value(), "a&lt;<b>c</b>");
+}